@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{Cursor, Read, Seek};
 
 use anyhow::{anyhow, Result};
@@ -18,6 +18,8 @@ use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
 pub struct BufferedBlockstore<BS> {
     base: BS,
     write: RefCell<HashMap<Cid, Vec<u8>>>,
+    check_writes: bool,
+    reads: RefCell<Option<BTreeMap<Cid, Vec<u8>>>>,
 }
 
 impl<BS> BufferedBlockstore<BS>
@@ -28,12 +30,86 @@ where
         Self {
             base,
             write: Default::default(),
+            check_writes: false,
+            reads: Default::default(),
         }
     }
 
+    /// Enable write checks. When enabled, writing a block under a CID that already exists (in the
+    /// buffer or the underlying store) with _different_ bytes is an error instead of silently
+    /// overwriting the existing block. This catches serialization bugs that produce the wrong CID.
+    pub fn with_write_checks(mut self) -> Self {
+        self.check_writes = true;
+        self
+    }
+
     pub fn into_inner(self) -> BS {
         self.base
     }
+
+    /// Discards all buffered (unflushed) writes. Blocks already flushed to the underlying
+    /// blockstore are unaffected.
+    pub fn discard(&self) {
+        *self.write.borrow_mut() = Default::default();
+    }
+
+    /// Returns the CIDs and sizes of all buffered (unflushed) writes, sorted by CID.
+    pub fn pending(&self) -> Vec<(Cid, usize)> {
+        let mut pending: Vec<_> = self
+            .write
+            .borrow()
+            .iter()
+            .map(|(k, v)| (*k, v.len()))
+            .collect();
+        pending.sort();
+        pending
+    }
+
+    /// Starts recording the blocks read from the underlying blockstore, discarding any previous
+    /// recording. Blocks served from the write buffer aren't recorded.
+    pub fn record_reads(&self) {
+        *self.reads.borrow_mut() = Some(Default::default());
+    }
+
+    /// Returns whether reads are being recorded.
+    pub fn recording_reads(&self) -> bool {
+        self.reads.borrow().is_some()
+    }
+
+    /// Stops recording reads, returning the blocks read from the underlying blockstore since
+    /// [`record_reads`](Self::record_reads), or an empty map if reads weren't being recorded.
+    pub fn take_reads(&self) -> BTreeMap<Cid, Vec<u8>> {
+        self.reads.borrow_mut().take().unwrap_or_default()
+    }
+
+    /// Reads a block from the underlying blockstore, recording it if reads are being recorded.
+    fn base_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>> {
+        let data = self.base.get(cid)?;
+        if let (Some(reads), Some(data)) = (self.reads.borrow_mut().as_mut(), &data) {
+            reads.insert(*cid, data.clone());
+        }
+        Ok(data)
+    }
+
+    /// Checks that writing `buf` under `cid` won't replace an existing block with different bytes.
+    fn check_write(&self, cid: &Cid, buf: &[u8]) -> Result<()> {
+        if !self.check_writes {
+            return Ok(());
+        }
+        let existing = match self.write.borrow().get(cid) {
+            Some(data) => Some(data.clone()),
+            None => self.base_get(cid)?,
+        };
+        match existing {
+            Some(data) if data != buf => Err(anyhow!(
+                "block {} already exists with different content ({} bytes, attempted to write {} bytes)",
+                cid,
+                data.len(),
+                buf.len()
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<BS> Buffered for BufferedBlockstore<BS>
@@ -236,11 +312,12 @@ where
         Ok(if let Some(data) = self.write.borrow().get(cid) {
             Some(data.clone())
         } else {
-            self.base.get(cid)?
+            self.base_get(cid)?
         })
     }
 
     fn put_keyed(&self, cid: &Cid, buf: &[u8]) -> Result<()> {
+        self.check_write(cid, buf)?;
         self.write.borrow_mut().insert(*cid, Vec::from(buf));
         Ok(())
     }
@@ -248,6 +325,9 @@ where
     fn has(&self, k: &Cid) -> Result<bool> {
         if self.write.borrow().contains_key(k) {
             Ok(true)
+        } else if self.recording_reads() {
+            // Record the block, as whoever checked for it is likely to depend on it.
+            Ok(self.base_get(k)?.is_some())
         } else {
             Ok(self.base.has(k)?)
         }
@@ -259,6 +339,12 @@ where
         D: AsRef<[u8]>,
         I: IntoIterator<Item = (Cid, D)>,
     {
+        if self.check_writes {
+            for (k, v) in blocks {
+                self.put_keyed(&k, v.as_ref())?;
+            }
+            return Ok(());
+        }
         self.write
             .borrow_mut()
             .extend(blocks.into_iter().map(|(k, v)| (k, v.as_ref().into())));
@@ -270,7 +356,7 @@ where
 mod tests {
     use cid::multihash::{Code, Multihash};
     use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
-    use fvm_ipld_encoding::CborStore;
+    use fvm_ipld_encoding::{to_vec, CborStore};
     use fvm_shared::{commcid, IDENTITY_HASH};
     use serde::{Deserialize, Serialize};
 
@@ -293,6 +379,34 @@ mod tests {
         assert!(buf_store.write.borrow().get(&cid).is_none());
     }
 
+    #[test]
+    fn buffered_store_records_reads() {
+        let mem = MemoryBlockstore::default();
+        let base = mem.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        let other = mem.put_cbor(&2u8, Code::Blake2b256).unwrap();
+        let buf_store = BufferedBlockstore::new(&mem);
+        let buffered = buf_store.put_cbor(&3u8, Code::Blake2b256).unwrap();
+
+        // nothing is recorded until asked
+        assert_eq!(buf_store.get_cbor::<u8>(&base).unwrap(), Some(1));
+        assert!(buf_store.take_reads().is_empty());
+
+        // only blocks read from the underlying store are recorded, including ones checked for
+        buf_store.record_reads();
+        assert!(buf_store.recording_reads());
+        assert_eq!(buf_store.get_cbor::<u8>(&base).unwrap(), Some(1));
+        assert_eq!(buf_store.get_cbor::<u8>(&buffered).unwrap(), Some(3));
+        assert!(buf_store.has(&other).unwrap());
+        let reads = buf_store.take_reads();
+        assert_eq!(reads.keys().copied().collect::<Vec<_>>(), {
+            let mut expected = vec![base, other];
+            expected.sort();
+            expected
+        });
+        assert_eq!(reads[&base], to_vec(&1u8).unwrap());
+        assert!(!buf_store.recording_reads());
+    }
+
     #[test]
     fn buffered_store_with_links() {
         let mem = MemoryBlockstore::default();
@@ -363,4 +477,29 @@ mod tests {
         assert_eq!(mem.get_cbor::<u8>(&unconnected).unwrap(), None);
         assert_eq!(buf_store.get_cbor::<u8>(&unconnected).unwrap(), None);
     }
+
+    #[test]
+    fn write_checks() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem).with_write_checks();
+
+        let cid = buf_store.put_cbor(&8u8, Code::Blake2b256).unwrap();
+
+        // Identical re-writes are fine, both while buffered and once flushed.
+        buf_store.put_keyed(&cid, &to_vec(&8u8).unwrap()).unwrap();
+        buf_store.flush(&cid).unwrap();
+        buf_store.put_keyed(&cid, &to_vec(&8u8).unwrap()).unwrap();
+
+        // Writing different bytes under an existing CID is detected.
+        assert!(buf_store.put_keyed(&cid, &to_vec(&9u8).unwrap()).is_err());
+        assert!(buf_store
+            .put_many_keyed([(cid, to_vec(&9u8).unwrap())])
+            .is_err());
+        assert_eq!(buf_store.get_cbor::<u8>(&cid).unwrap(), Some(8));
+
+        // Without checks, the write silently replaces the buffered block.
+        let unchecked = BufferedBlockstore::new(&mem);
+        unchecked.put_keyed(&cid, &to_vec(&9u8).unwrap()).unwrap();
+        assert_eq!(unchecked.get_cbor::<u8>(&cid).unwrap(), Some(9));
+    }
 }
@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use anyhow::{anyhow, Context};
+use cid::Cid;
 use derive_more::{Deref, DerefMut};
+use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::{to_vec, RawBytes, DAG_CBOR};
 use fvm_shared::actor::builtin::Type;
 use fvm_shared::address::{Address, Protocol};
@@ -12,11 +16,14 @@ use num_traits::Zero;
 use super::{Backtrace, CallManager, InvocationResult, NO_DATA_BLOCK_ID};
 use crate::call_manager::backtrace::Frame;
 use crate::call_manager::FinishRet;
-use crate::gas::{Gas, GasTracker};
-use crate::kernel::{Block, BlockRegistry, ExecutionError, Kernel, Result, SyscallError};
+use crate::gas::{Gas, GasTracker, PriceList};
+use crate::kernel::{
+    Block, BlockRegistry, ClassifyResult, ExecutionError, Kernel, Result, SendFlags, SendOptions,
+    SyscallError,
+};
 use crate::machine::Machine;
 use crate::syscalls::error::Abort;
-use crate::syscalls::{charge_for_exec, update_gas_available};
+use crate::syscalls::{charge_for_exec, update_gas_available, GasCheckpoint};
 use crate::trace::{ExecutionEvent, ExecutionTrace};
 use crate::{account_actor, syscall_error};
 
@@ -41,12 +48,40 @@ pub struct InnerDefaultCallManager<M> {
     num_actors_created: u64,
     /// Current call-stack depth.
     call_stack_depth: u32,
+    /// Number of sends made in this message execution, including the message itself. The limit
+    /// doesn't count the message, so up to `max_sends_per_message + 1` sends are made.
+    send_count: u64,
     /// The current chain of errors, if any.
     backtrace: Backtrace,
     /// The current execution trace.
     exec_trace: ExecutionTrace,
     /// Number of actors that have been invoked in this message execution.
     invocation_count: u64,
+    /// Actor state writes not yet committed, one layer per open transaction. Only tracked when a
+    /// state write observer is configured.
+    state_writes: Vec<Vec<(ActorID, Cid)>>,
+    /// The actors currently being invoked, innermost last. Gas is attributed to the innermost
+    /// actor.
+    actor_stack: Vec<ActorID>,
+    /// The gas used when gas was last attributed to an actor.
+    gas_attributed: Gas,
+    /// The sender of the message, recorded by the top-level send. Gas used outside any actor is
+    /// attributed to it.
+    sender_id: Option<ActorID>,
+    /// The gas used by each actor so far.
+    gas_by_actor: BTreeMap<ActorID, Gas>,
+    /// The return codecs expected by the callers of the sends in progress, innermost last.
+    return_codecs: Vec<u64>,
+    /// The gas available to each invocation in progress when it started, innermost last.
+    gas_limits: Vec<Gas>,
+    /// The parameters of each invocation in progress, innermost last.
+    params: Vec<Option<Block>>,
+    /// Whether the current invocation is running in read-only mode.
+    read_only: bool,
+    /// The price list gas is charged according to.
+    price_list: &'static PriceList,
+    /// The total size of the debug artifacts stored in this message execution.
+    artifact_bytes: u64,
 }
 
 #[doc(hidden)]
@@ -72,16 +107,36 @@ where
     type Machine = M;
 
     fn new(machine: M, gas_limit: i64, origin: Address, nonce: u64) -> Self {
+        let price_list = machine.context().price_list;
+        let mut gas_tracker = GasTracker::new(Gas::new(gas_limit), Gas::zero());
+        if machine.context().gas_profiling {
+            gas_tracker.enable_profile();
+        }
+        if let Some(events) = &machine.context().event_sender {
+            gas_tracker.stream_events(events.clone());
+        }
         DefaultCallManager(Some(Box::new(InnerDefaultCallManager {
             machine,
-            gas_tracker: GasTracker::new(Gas::new(gas_limit), Gas::zero()),
+            gas_tracker,
             origin,
             nonce,
             num_actors_created: 0,
             call_stack_depth: 0,
+            send_count: 0,
             backtrace: Backtrace::default(),
             exec_trace: vec![],
             invocation_count: 0,
+            state_writes: Vec::new(),
+            actor_stack: Vec::new(),
+            gas_attributed: Gas::zero(),
+            sender_id: None,
+            gas_by_actor: BTreeMap::new(),
+            return_codecs: Vec::new(),
+            gas_limits: Vec::new(),
+            params: Vec::new(),
+            read_only: false,
+            price_list,
+            artifact_bytes: 0,
         })))
     }
 
@@ -92,12 +147,19 @@ where
         method: MethodNum,
         params: Option<Block>,
         value: &TokenAmount,
+        options: SendOptions,
     ) -> Result<InvocationResult>
     where
         K: Kernel<CallManager = Self>,
     {
-        if self.machine.context().tracing {
-            self.exec_trace.push(ExecutionEvent::Call {
+        let return_codec = options.return_codec.unwrap_or(DAG_CBOR);
+        let read_only = options.flags.contains(SendFlags::READ_ONLY) || self.read_only;
+        if self.actor_stack.is_empty() {
+            self.sender_id.get_or_insert(from);
+        }
+
+        if self.tracing() {
+            self.trace(ExecutionEvent::Call {
                 from,
                 to,
                 method,
@@ -124,18 +186,33 @@ where
         // `call_stack_depth` in lotus is 0 for the top-level call, unlike in the FVM where it's 1.
         if self.call_stack_depth > self.machine.context().max_call_depth {
             let sys_err = syscall_error!(LimitExceeded, "message execution exceeds call depth");
-            if self.machine.context().tracing {
-                self.exec_trace
-                    .push(ExecutionEvent::CallError(sys_err.clone()))
+            if self.tracing() {
+                self.trace(ExecutionEvent::CallError(sys_err.clone()))
             }
             return Err(sys_err.into());
         }
+        // As above, the top-level call isn't counted against the limit.
+        if self.send_count > self.machine.context().max_sends_per_message {
+            let sys_err = syscall_error!(LimitExceeded, "message execution exceeds send limit");
+            if self.tracing() {
+                self.trace(ExecutionEvent::CallError(sys_err.clone()))
+            }
+            return Err(sys_err.into());
+        }
+        self.send_count += 1;
         self.call_stack_depth += 1;
+        self.return_codecs.push(return_codec);
+        let gas_limit = self.gas_tracker.gas_available();
+        self.gas_limits.push(gas_limit);
+        let outer_read_only = std::mem::replace(&mut self.read_only, read_only);
         let result = self.send_unchecked::<K>(from, to, method, params, value);
+        self.read_only = outer_read_only;
+        self.gas_limits.pop();
+        self.return_codecs.pop();
         self.call_stack_depth -= 1;
 
-        if self.machine.context().tracing {
-            self.exec_trace.push(match &result {
+        if self.tracing() {
+            self.trace(match &result {
                 Ok(InvocationResult::Return(v)) => ExecutionEvent::CallReturn(
                     v.as_ref()
                         .map(|blk| RawBytes::from(blk.data().to_vec()))
@@ -162,22 +239,34 @@ where
         f: impl FnOnce(&mut Self) -> Result<InvocationResult>,
     ) -> Result<InvocationResult> {
         self.state_tree_mut().begin_transaction();
+        self.state_writes.push(Vec::new());
         let (revert, res) = match f(self) {
             Ok(v) => (!v.exit_code().is_success(), Ok(v)),
             Err(e) => (true, Err(e)),
         };
+        let writes = self.state_writes.pop().expect("state write layer missing");
         self.state_tree_mut().end_transaction(revert)?;
+        if !revert {
+            self.commit_state_writes(writes);
+        }
         res
     }
 
     fn finish(mut self) -> (FinishRet, Self::Machine) {
         // TODO: Having to check against zero here is fishy, but this is what lotus does.
         let gas_used = self.gas_tracker.gas_used().max(Gas::zero()).round_up();
+        self.attribute_gas();
 
         let inner = self.0.take().expect("call manager is poisoned");
         (
             FinishRet {
                 gas_used,
+                gas_by_actor: inner.gas_by_actor.into_iter().collect(),
+                gas_profile: inner
+                    .gas_tracker
+                    .profile()
+                    .map(|profile| profile.iter().map(|(k, v)| (k.clone(), *v)).collect())
+                    .unwrap_or_default(),
                 backtrace: inner.backtrace,
                 exec_trace: inner.exec_trace,
             },
@@ -224,6 +313,53 @@ where
     fn invocation_count(&self) -> u64 {
         self.invocation_count
     }
+
+    fn expected_return_codec(&self) -> u64 {
+        self.return_codecs.last().copied().unwrap_or(DAG_CBOR)
+    }
+
+    fn invocation_gas_limit(&self) -> Gas {
+        self.gas_limits
+            .last()
+            .copied()
+            .unwrap_or_else(|| self.gas_tracker.gas_limit())
+    }
+
+    fn invocation_params(&self) -> Option<&Block> {
+        self.params.last().and_then(Option::as_ref)
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn set_price_list(&mut self, price_list: &'static PriceList) {
+        self.price_list = price_list;
+    }
+
+    fn price_list(&self) -> &PriceList {
+        self.price_list
+    }
+
+    fn record_artifact(&mut self, size: u64) -> bool {
+        match self.artifact_bytes.checked_add(size) {
+            Some(total) if total <= self.context().max_artifact_bytes_per_message => {
+                self.artifact_bytes = total;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn record_state_write(&mut self, id: ActorID, state: Cid) {
+        if self.context().state_write_observer.is_none() {
+            return;
+        }
+        match self.state_writes.last_mut() {
+            Some(pending) => pending.push((id, state)),
+            None => self.commit_state_writes(vec![(id, state)]),
+        }
+    }
 }
 
 impl<M> DefaultCallManager<M>
@@ -236,6 +372,12 @@ where
     {
         self.charge_gas(self.price_list().on_create_actor())?;
 
+        if self.read_only {
+            return Err(
+                syscall_error!(IllegalOperation; "cannot create actors in read-only mode").into(),
+            );
+        }
+
         if addr.is_bls_zero_address() {
             return Err(
                 syscall_error!(IllegalArgument; "cannot create the bls zero address actor").into(),
@@ -275,6 +417,94 @@ where
         Ok(id)
     }
 
+    /// Returns the module for the given code CID, charging for instantiating it. Modules are never
+    /// compiled here: code must already be loaded into the engine (see [`Machine::precompile`]).
+    ///
+    /// Instantiation is charged by the length of the actor's wasm bytecode, never the size of a
+    /// precompiled module, so the charge doesn't depend on how the engine loaded the module.
+    ///
+    /// Code that failed to compile when it was loaded is returned as an "invalid actor code" exit,
+    /// attributed to the code CID. Any other code missing from the engine is a fatal abort.
+    fn load_module(&mut self, code: &Cid) -> Result<std::result::Result<wasmtime::Module, Abort>> {
+        let (module, code_size) = match self.engine().get_module(code) {
+            // The engine only knows the length of the bytecode of modules it compiled itself, and
+            // the code isn't read at all when it's already compiled. Read it anyway when the length
+            // is unknown, and for the benefit of anyone recording reads.
+            Some(module) => match self.engine().get_code_size(code) {
+                Some(size) if !self.machine.recording_reads() => (module, size),
+                _ => match self.blockstore().get(code).or_fatal()? {
+                    Some(wasm) => (module, wasm.len()),
+                    None => return Ok(Err(Abort::Fatal(anyhow!("actor code not found")))),
+                },
+            },
+            None if self.engine().is_invalid_code(code) => {
+                return Ok(Err(Abort::Exit(
+                    ExitCode::SYS_INVALID_METHOD,
+                    format!("invalid actor code {}", code),
+                )))
+            }
+            None => return Ok(Err(Abort::Fatal(anyhow!("actor code not found")))),
+        };
+        self.charge_gas(
+            self.price_list()
+                .on_actor_instantiation(code_size, self.context().actor_instantiation_cost),
+        )?;
+        Ok(Ok(module))
+    }
+
+    /// Returns true if execution events are being recorded in the trace or streamed to the event
+    /// sender.
+    fn tracing(&self) -> bool {
+        let ctx = self.machine.context();
+        ctx.tracing || ctx.event_sender.is_some()
+    }
+
+    /// Records an execution event in the trace, and streams it to the event sender, as configured.
+    fn trace(&mut self, event: ExecutionEvent) {
+        if let Some(sender) = &self.machine.context().event_sender {
+            sender.send(event.clone());
+        }
+        if self.machine.context().tracing {
+            self.exec_trace.push(event);
+        }
+    }
+
+    /// Commits state writes from a transaction, passing them up to the enclosing transaction if
+    /// there is one, and reporting them to the observer otherwise.
+    fn commit_state_writes(&mut self, writes: Vec<(ActorID, Cid)>) {
+        match self.state_writes.last_mut() {
+            Some(parent) => parent.extend(writes),
+            None => {
+                if let Some(observer) = &self.machine.context().state_write_observer {
+                    for (id, state) in writes {
+                        observer.on_actor_write(id, &state);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Attributes the gas used since the last call to the actor being invoked, or to the sender
+    /// of the message if no actor is being invoked (e.g., for the message inclusion cost). Until the
+    /// sender is known, the gas is left to be attributed later.
+    fn attribute_gas(&mut self) {
+        let actor = match self.actor_stack.last().or(self.sender_id.as_ref()) {
+            Some(&id) => id,
+            None => return,
+        };
+        let gas_used = self.gas_tracker.gas_used();
+        let gas = gas_used - self.gas_attributed;
+        self.gas_attributed = gas_used;
+        if gas.is_zero() {
+            return;
+        }
+
+        // Refunds for failed sub-calls lower the gas used, so they're deducted from the caller,
+        // without taking it below zero.
+        let total = self.gas_by_actor.entry(actor).or_default();
+        *total = (*total + gas).max(Gas::zero());
+    }
+
     /// Send without checking the call depth.
     fn send_unchecked<K>(
         &mut self,
@@ -336,13 +566,60 @@ where
             return Ok(InvocationResult::Return(Default::default()));
         }
 
-        // Store the parametrs, and initialize the block registry for the target actor.
+        // Fail if the actor has sent to itself too many times in a row. The caller is on top of
+        // the actor stack, so this counts the chain of self-sends leading to this one.
+        if from == to {
+            let self_sends = self
+                .actor_stack
+                .iter()
+                .rev()
+                .take_while(|&&id| id == to)
+                .count();
+            if self_sends > self.machine.context().max_self_recursion_depth as usize {
+                let sys_err =
+                    syscall_error!(LimitExceeded, "actor {} exceeds self-recursion limit", to);
+                if self.tracing() {
+                    self.trace(ExecutionEvent::CallError(sys_err.clone()))
+                }
+                return Err(sys_err.into());
+            }
+        }
+
+        // Attribute the gas used by the invocation to the recipient. The gas used by its sub-calls
+        // is attributed to their recipients, even if they fail.
+        self.attribute_gas();
+        self.actor_stack.push(to);
+        let ret = self.invoke::<K>(from, to, method, params, value, &state.code);
+        self.attribute_gas();
+        self.actor_stack.pop();
+        ret
+    }
+
+    /// Invokes the actor `to`, with the code `code`.
+    fn invoke<K>(
+        &mut self,
+        from: ActorID,
+        to: ActorID,
+        method: MethodNum,
+        params: Option<Block>,
+        value: &TokenAmount,
+        code: &Cid,
+    ) -> Result<InvocationResult>
+    where
+        K: Kernel<CallManager = Self>,
+    {
+        // Load the actor's module, charging for instantiating it.
+        let module = self.load_module(code)?;
+
+        // Store the parametrs, and initialize the block registry for the target actor. The
+        // parameters are also kept on the call manager, so they can be read without a handle.
         let mut block_registry = BlockRegistry::new();
-        let params_id = if let Some(blk) = params {
+        let params_id = if let Some(blk) = params.clone() {
             block_registry.put(blk)?
         } else {
             NO_DATA_BLOCK_ID
         };
+        self.params.push(params);
 
         // Increment invocation count
         self.invocation_count += 1;
@@ -352,7 +629,7 @@ where
         let engine = self.engine().clone();
 
         log::trace!("calling {} -> {}::{}", from, to, method);
-        self.map_mut(|cm| {
+        let ret = self.map_mut(|cm| {
             // Make the kernel.
             let kernel = K::new(cm, block_registry, from, to, method, value.clone());
 
@@ -362,10 +639,14 @@ where
             // From this point on, there are no more syscall errors, only aborts.
             let result: std::result::Result<BlockId, Abort> = (|| {
                 // Instantiate the module.
-                let instance = engine
-                    .get_instance(&mut store, &state.code)
-                    .and_then(|i| i.context("actor code not found"))
-                    .map_err(Abort::Fatal)?;
+                let instance = engine.instantiate(&mut store, &module?).map_err(|e| {
+                    // Failing to grow a table aborts the actor, instead of failing the message.
+                    store
+                        .data_mut()
+                        .limiter_abort
+                        .take()
+                        .unwrap_or(Abort::Fatal(e))
+                })?;
 
                 // Resolve and store a reference to the exported memory.
                 let memory = instance
@@ -381,7 +662,7 @@ where
                     .map_err(Abort::Fatal)?;
 
                 // Set the available gas.
-                update_gas_available(&mut store)?;
+                update_gas_available(&mut store, GasCheckpoint::Invoke)?;
 
                 // Invoke it.
                 let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
@@ -391,7 +672,12 @@ where
 
                 // Charge for any remaining uncharged execution gas, returning an error if we run
                 // out.
-                charge_for_exec(&mut store)?;
+                charge_for_exec(&mut store, GasCheckpoint::Invoke)?;
+
+                // Tables that failed to grow abort the actor, even if it handled the failure.
+                if let Some(abort) = store.data_mut().limiter_abort.take() {
+                    return Err(abort);
+                }
 
                 // If the invocation failed due to running out of exec_units, we have already
                 // detected it and returned OutOfGas above. Any other invocation failure is returned
@@ -468,7 +754,9 @@ where
             }
 
             (ret, cm)
-        })
+        });
+        self.params.pop();
+        ret
     }
 
     fn map_mut<F, T>(&mut self, f: F) -> T
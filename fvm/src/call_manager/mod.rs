@@ -1,10 +1,15 @@
+use std::fmt::Debug;
+
+use cid::Cid;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::{ActorID, MethodNum};
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
-use crate::gas::{GasCharge, GasTracker, PriceList};
-use crate::kernel::{self, Result};
+use crate::gas::{Gas, GasCharge, GasTracker, PriceList};
+use crate::kernel::{self, Result, SendOptions};
 use crate::machine::{Machine, MachineContext};
 use crate::state_tree::StateTree;
 use crate::Kernel;
@@ -46,6 +51,9 @@ pub trait CallManager: 'static {
 
     /// Send a message. The type parameter `K` specifies the the _kernel_ on top of which the target
     /// actor should execute.
+    ///
+    /// The call manager applies the `options`' return codec and flags. Their gas limit is applied
+    /// by the caller, which lowers the gas tracker's limit for the duration of the send.
     fn send<K: Kernel<CallManager = Self>>(
         &mut self,
         from: ActorID,
@@ -53,6 +61,7 @@ pub trait CallManager: 'static {
         method: MethodNum,
         params: Option<kernel::Block>,
         value: &TokenAmount,
+        options: SendOptions,
     ) -> Result<InvocationResult>;
 
     /// Execute some operation (usually a send) within a transaction.
@@ -86,6 +95,37 @@ pub trait CallManager: 'static {
     /// Gets the total invocations done on this call stack.
     fn invocation_count(&self) -> u64;
 
+    /// Returns the codec the caller of the current invocation expects the return value in, or
+    /// `DAG_CBOR` if it didn't specify one.
+    fn expected_return_codec(&self) -> u64;
+
+    /// Returns the gas the current invocation was granted: the gas available when it started,
+    /// after applying the gas limit of the send that invoked it. Outside of any invocation, this
+    /// is the gas limit of the message.
+    fn invocation_gas_limit(&self) -> Gas;
+
+    /// Returns the parameters the current invocation was called with, if any.
+    fn invocation_params(&self) -> Option<&kernel::Block>;
+
+    /// Returns `true` if the current invocation is running in read-only mode, i.e., it (or one of
+    /// its callers) was invoked with [`SendFlags::READ_ONLY`](fvm_shared::sys::SendFlags::READ_ONLY).
+    /// Sends made while in read-only mode are always read-only.
+    fn read_only(&self) -> bool;
+
+    /// Overrides the price list gas is charged according to, for the rest of this call stack. By
+    /// default, that's the machine's [`price_list`](crate::machine::NetworkConfig::price_list).
+    fn set_price_list(&mut self, price_list: &'static PriceList);
+
+    /// Records that `size` bytes of debug artifacts were stored, returning `false` and recording
+    /// nothing if that would take the total for this message past
+    /// [`NetworkConfig::max_artifact_bytes_per_message`](crate::machine::NetworkConfig::max_artifact_bytes_per_message).
+    fn record_artifact(&mut self, size: u64) -> bool;
+
+    /// Records that an actor's state root was changed to `state`. The write is reported to the
+    /// [`StateWriteObserver`] configured in the [`MachineContext`], if any, once it's committed.
+    /// Writes made in reverted transactions are never reported.
+    fn record_state_write(&mut self, id: ActorID, state: Cid);
+
     /// Returns the current price list.
     fn price_list(&self) -> &PriceList {
         self.machine().context().price_list
@@ -123,6 +163,52 @@ pub trait CallManager: 'static {
     }
 }
 
+/// Observes committed actor state writes, e.g. for incremental indexing. Observers are configured
+/// by the host with [`MachineContext::set_state_write_observer`], and can't affect execution.
+pub trait StateWriteObserver: Debug + Send + Sync {
+    /// Called when a change to an actor's state root is committed.
+    fn on_actor_write(&self, id: ActorID, new_state: &Cid);
+}
+
+/// How the gas consumed by a failed sub-call (one that aborted) is charged to the caller.
+/// Configured with [`NetworkConfig::failed_subcall_gas_policy`](crate::machine::NetworkConfig::failed_subcall_gas_policy).
+///
+/// No policy refunds more than half the consumed gas, so the computation a message can trigger
+/// stays bounded by (twice) its gas limit, however many failing sub-calls it makes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FailedSubcallGasPolicy {
+    /// Charge all the gas consumed by the sub-call.
+    #[default]
+    ChargeAll,
+    /// Charge half the gas consumed by the sub-call, refunding the rest.
+    CapAtHalf,
+    /// Charge all the gas consumed by the sub-call, plus a flat penalty.
+    FlatPenalty(Gas),
+}
+
+impl FailedSubcallGasPolicy {
+    /// Returns the gas to charge on top of the gas `consumed` by a failed sub-call. This is
+    /// negative when some of the consumed gas should be refunded.
+    pub fn adjustment(&self, consumed: Gas) -> Gas {
+        match self {
+            Self::ChargeAll => Gas::zero(),
+            Self::CapAtHalf => Gas::zero() - Gas::from_milligas(consumed.as_milligas() / 2),
+            Self::FlatPenalty(penalty) => *penalty,
+        }
+    }
+}
+
+/// What happens to debug artifacts stored past the per-message size cap. Configured with
+/// [`NetworkConfig::artifact_overflow`](crate::machine::NetworkConfig::artifact_overflow).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArtifactOverflow {
+    /// Silently drop the artifact.
+    #[default]
+    Drop,
+    /// Fail the `store_artifact` syscall with `LimitExceeded`.
+    Error,
+}
+
 /// The result of a method invocation.
 #[derive(Clone, Debug)]
 pub enum InvocationResult {
@@ -152,6 +238,14 @@ impl InvocationResult {
 /// The returned values upon finishing a call manager.
 pub struct FinishRet {
     pub gas_used: i64,
+    /// The gas used by each actor, sorted by actor ID. See [`ApplyRet::gas_by_actor`].
+    ///
+    /// [`ApplyRet::gas_by_actor`]: crate::executor::ApplyRet::gas_by_actor
+    pub gas_by_actor: Vec<(ActorID, Gas)>,
+    /// The gas charged under each charge name, sorted by name. See [`ApplyRet::gas_profile`].
+    ///
+    /// [`ApplyRet::gas_profile`]: crate::executor::ApplyRet::gas_profile
+    pub gas_profile: Vec<(String, Gas)>,
     pub backtrace: Backtrace,
     pub exec_trace: ExecutionTrace,
 }
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use fvm_shared::message::Message;
+use num_traits::Zero;
+
+use super::{ApplyKind, ApplyRet, DefaultExecutor, Executor};
+use crate::gas::Gas;
+use crate::machine::Machine;
+use crate::Kernel;
+
+/// The gas charged under a single charge name by the two executions compared by [`compare_gas`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChargeComparison {
+    pub name: String,
+    /// The gas charged under the first configuration.
+    pub a: Gas,
+    /// The gas charged under the second configuration.
+    pub b: Gas,
+}
+
+impl ChargeComparison {
+    /// Returns the change in gas from the first configuration to the second.
+    pub fn delta(&self) -> Gas {
+        self.b - self.a
+    }
+}
+
+/// The results of executing a message under two configurations with [`compare_gas`].
+#[derive(Clone, Debug)]
+pub struct GasComparison {
+    /// The result under the first configuration.
+    pub ret_a: ApplyRet,
+    /// The result under the second configuration.
+    pub ret_b: ApplyRet,
+    /// The gas charged under each charge name used by either execution, sorted by name. A name
+    /// used by only one of the executions is charged zero gas by the other.
+    pub charges: Vec<ChargeComparison>,
+}
+
+impl GasComparison {
+    /// Returns the change in gas charged under `name` from the first configuration to the second,
+    /// or zero if neither execution used it.
+    pub fn delta(&self, name: &str) -> Gas {
+        self.charges
+            .iter()
+            .find(|c| c.name == name)
+            .map_or_else(Gas::zero, ChargeComparison::delta)
+    }
+
+    /// Returns the change in the total gas used from the first configuration to the second, before
+    /// rounding.
+    pub fn total_delta(&self) -> Gas {
+        self.charges
+            .iter()
+            .fold(Gas::zero(), |total, c| total + c.delta())
+    }
+}
+
+/// Executes `msg` with both executors, comparing the gas charged under each charge name. This is
+/// meant for regression testing gas changes: the executors' machines would typically be created
+/// from the same state with different configurations (e.g., price lists or network versions).
+///
+/// Both machines must have [`gas_profiling`](crate::machine::MachineContext::gas_profiling)
+/// enabled. The message is applied to the state of both executors.
+pub fn compare_gas<KA: Kernel, KB: Kernel>(
+    a: &mut DefaultExecutor<KA>,
+    b: &mut DefaultExecutor<KB>,
+    msg: Message,
+    apply_kind: ApplyKind,
+    raw_length: usize,
+) -> anyhow::Result<GasComparison> {
+    if !a.context().gas_profiling || !b.context().gas_profiling {
+        return Err(anyhow!(
+            "comparing gas requires gas profiling to be enabled"
+        ));
+    }
+
+    let ret_a = a.execute_message(msg.clone(), apply_kind, raw_length)?;
+    let ret_b = b.execute_message(msg, apply_kind, raw_length)?;
+
+    let mut charges: BTreeMap<&str, (Gas, Gas)> = BTreeMap::new();
+    for (name, gas) in &ret_a.gas_profile {
+        charges.entry(name).or_default().0 += *gas;
+    }
+    for (name, gas) in &ret_b.gas_profile {
+        charges.entry(name).or_default().1 += *gas;
+    }
+    let charges = charges
+        .into_iter()
+        .map(|(name, (a, b))| ChargeComparison {
+            name: name.to_owned(),
+            a,
+            b,
+        })
+        .collect();
+
+    Ok(GasComparison {
+        ret_a,
+        ret_b,
+        charges,
+    })
+}
@@ -14,10 +14,10 @@ use fvm_shared::receipt::Receipt;
 use fvm_shared::ActorID;
 use num_traits::Zero;
 
-use super::{ApplyFailure, ApplyKind, ApplyRet, Executor};
+use super::{ApplyFailure, ApplyKind, ApplyRet, Executor, Witness};
 use crate::call_manager::{backtrace, CallManager, InvocationResult};
-use crate::gas::{Gas, GasCharge, GasOutputs};
-use crate::kernel::{Block, ClassifyResult, Context as _, ExecutionError, Kernel};
+use crate::gas::{Gas, GasCharge, GasOutputs, PriceList};
+use crate::kernel::{Block, ClassifyResult, Context as _, ExecutionError, Kernel, SendOptions};
 use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ADDR, REWARD_ACTOR_ADDR};
 
 /// The default [`Executor`].
@@ -27,21 +27,24 @@ use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ADDR, REWARD_ACTOR_ADDR};
 /// Message execution might run out of stack and crash (the entire process) if it doesn't have at
 /// least 64MiB of stacks space. If you can't guarantee 64MiB of stack space, wrap this executor in
 /// a [`ThreadedExecutor`][super::ThreadedExecutor].
-// If the inner value is `None` it means the machine got poisoned and is unusable.
-#[repr(transparent)]
-pub struct DefaultExecutor<K: Kernel>(Option<<K::CallManager as CallManager>::Machine>);
+pub struct DefaultExecutor<K: Kernel> {
+    // If this is `None` it means the machine got poisoned and is unusable.
+    machine: Option<<K::CallManager as CallManager>::Machine>,
+    /// The named save points, with the state roots they saved, oldest first.
+    save_points: Vec<(String, Cid)>,
+}
 
 impl<K: Kernel> Deref for DefaultExecutor<K> {
     type Target = <K::CallManager as CallManager>::Machine;
 
     fn deref(&self) -> &Self::Target {
-        &*self.0.as_ref().expect("machine poisoned")
+        &*self.machine.as_ref().expect("machine poisoned")
     }
 }
 
 impl<K: Kernel> DerefMut for DefaultExecutor<K> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut *self.0.as_mut().expect("machine poisoned")
+        &mut *self.machine.as_mut().expect("machine poisoned")
     }
 }
 
@@ -58,47 +61,191 @@ where
         apply_kind: ApplyKind,
         raw_length: usize,
     ) -> anyhow::Result<ApplyRet> {
+        let price_list = self.context().price_list;
+        self.execute_message_with_price_list(msg, apply_kind, raw_length, price_list)
+    }
+
+    /// Flush the state-tree to the underlying blockstore.
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        let k = (&mut **self).flush()?;
+        Ok(k)
+    }
+}
+
+impl<K> DefaultExecutor<K>
+where
+    K: Kernel,
+{
+    /// Create a new [`DefaultExecutor`] for executing messages on the [`Machine`].
+    pub fn new(m: <K::CallManager as CallManager>::Machine) -> Self {
+        Self {
+            machine: Some(m),
+            save_points: Vec::new(),
+        }
+    }
+
+    /// Replaces the receipt's exit code as configured by
+    /// [`NetworkConfig::exit_code_remap`](crate::machine::NetworkConfig::exit_code_remap).
+    fn remap_exit_code(&self, mut apply_ret: ApplyRet) -> ApplyRet {
+        if let Some((_, custom)) = self
+            .context()
+            .exit_code_remap
+            .iter()
+            .find(|(internal, _)| *internal == apply_ret.msg_receipt.exit_code)
+        {
+            apply_ret.msg_receipt.exit_code = *custom;
+        }
+        apply_ret
+    }
+
+    /// Consume consumes the executor and returns the Machine. If the Machine had
+    /// been poisoned during execution, the Option will be None.
+    pub fn into_machine(self) -> Option<<K::CallManager as CallManager>::Machine> {
+        self.machine
+    }
+
+    /// Executes a message against the state at `root` instead of the current state, then restores
+    /// the current state, discarding the message's changes. Only the state is rebased: the message
+    /// is executed with the machine's context (epoch, base fee, etc.).
+    ///
+    /// Pending changes to the current state are flushed first so that they can be restored. If the
+    /// message fails with a fatal error, the machine may be poisoned.
+    pub fn execute_at_root(
+        &mut self,
+        root: Cid,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> Result<ApplyRet> {
+        let current = self.flush()?;
+        self.state_tree_mut().reset(&root)?;
+        let ret = self.execute_message(msg, apply_kind, raw_length);
+        if self.machine.is_some() {
+            self.state_tree_mut().reset(&current)?;
+        }
+        ret
+    }
+
+    /// Flushes the state and saves the resulting root as a save point named `name`, returning the
+    /// root. Save points form a stack: a name may be reused, in which case
+    /// [`restore_point`](Self::restore_point) restores the most recent point with that name.
+    pub fn save_point(&mut self, name: impl Into<String>) -> Result<Cid> {
+        let root = self.flush()?;
+        self.save_points.push((name.into(), root));
+        Ok(root)
+    }
+
+    /// Resets the state to the most recent save point named `name`, returning its root. All
+    /// unflushed changes are discarded, and all points saved after it are invalidated. The point
+    /// itself is kept, so it can be restored again.
+    pub fn restore_point(&mut self, name: &str) -> Result<Cid> {
+        let idx = self
+            .save_points
+            .iter()
+            .rposition(|(n, _)| n == name)
+            .ok_or_else(|| anyhow!("no save point named {:?}", name))?;
+        let root = self.save_points[idx].1;
+        self.state_tree_mut().reset(&root)?;
+        self.save_points.truncate(idx + 1);
+        Ok(root)
+    }
+
+    /// Executes a message like [`Executor::execute_message`], also returning the [`Witness`] of the
+    /// state it read, e.g. for a prover. Pending changes to the current state are flushed first,
+    /// and the witness is taken against the flushed state. See [`Machine::record_reads`].
+    pub fn execute_message_with_witness(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> Result<(ApplyRet, Witness)> {
+        let root = self.record_reads()?;
+        let ret = self.execute_message(msg, apply_kind, raw_length);
+        let blocks = match self.machine.as_mut() {
+            Some(machine) => machine.take_reads(),
+            None => Default::default(),
+        };
+        Ok((ret?, Witness { root, blocks }))
+    }
+
+    /// Executes a message like [`Executor::execute_message`], but charges gas according to
+    /// `price_list` instead of the machine's
+    /// [`NetworkConfig::price_list`](crate::machine::NetworkConfig::price_list). This covers message
+    /// inclusion, every charge made during execution, and the return value.
+    ///
+    /// Note that wasm execution is charged according to the engine's instrumentation, which is
+    /// fixed when the machine is created.
+    pub fn execute_message_with_price_list(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+        price_list: &'static PriceList,
+    ) -> Result<ApplyRet> {
         // Validate if the message was correct, charge for it, and extract some preliminary data.
         let (sender_id, gas_cost, inclusion_cost) =
-            match self.preflight_message(&msg, apply_kind, raw_length)? {
+            match self.preflight_message(&msg, apply_kind, raw_length, price_list)? {
                 Ok(res) => res,
-                Err(apply_ret) => return Ok(apply_ret),
+                Err(apply_ret) => return Ok(self.remap_exit_code(apply_ret)),
             };
 
-        // Apply the message.
-        let (res, gas_used, mut backtrace, exec_trace) = self.map_machine(|machine| {
-            let mut cm = K::CallManager::new(machine, msg.gas_limit, msg.from, msg.sequence);
-            // This error is fatal because it should have already been accounted for inside
-            // preflight_message.
-            if let Err(e) = cm.charge_gas(inclusion_cost) {
-                return (Err(e), cm.finish().1);
-            }
-
-            let params = if msg.params.is_empty() {
-                None
-            } else {
-                Some(Block::new(DAG_CBOR, msg.params.bytes()))
-            };
-
-            let result = cm.with_transaction(|cm| {
-                // Invoke the message.
-                let ret = cm.send::<K>(sender_id, msg.to, msg.method_num, params, &msg.value)?;
+        // Unlimited implicit messages are applied by the host (e.g., cron), not users, so their
+        // gas is tracked but the gas limit isn't enforced.
+        let gas_limit = match apply_kind {
+            ApplyKind::Explicit | ApplyKind::Implicit => msg.gas_limit,
+            ApplyKind::ImplicitUnlimited => i64::MAX,
+        };
 
-                // Charge for including the result (before we end the transaction).
-                if let InvocationResult::Return(value) = &ret {
-                    cm.charge_gas(cm.context().price_list.on_chain_return_value(
-                        value.as_ref().map(|v| v.size() as usize).unwrap_or(0),
-                    ))?;
+        // Apply the message.
+        let (res, gas_used, gas_by_actor, gas_profile, mut backtrace, exec_trace) = self
+            .map_machine(|machine| {
+                let mut cm = K::CallManager::new(machine, gas_limit, msg.from, msg.sequence);
+                cm.set_price_list(price_list);
+                // This error is fatal because it should have already been accounted for inside
+                // preflight_message.
+                if let Err(e) = cm.charge_gas(inclusion_cost) {
+                    return (Err(e), cm.finish().1);
                 }
 
-                Ok(ret)
-            });
-            let (res, machine) = cm.finish();
-            (
-                Ok((result, res.gas_used, res.backtrace, res.exec_trace)),
-                machine,
-            )
-        })?;
+                let params = if msg.params.is_empty() {
+                    None
+                } else {
+                    Some(Block::new(DAG_CBOR, msg.params.bytes()))
+                };
+
+                let result = cm.with_transaction(|cm| {
+                    // Invoke the message.
+                    let ret = cm.send::<K>(
+                        sender_id,
+                        msg.to,
+                        msg.method_num,
+                        params,
+                        &msg.value,
+                        SendOptions::default(),
+                    )?;
+
+                    // Charge for including the result (before we end the transaction).
+                    if let InvocationResult::Return(value) = &ret {
+                        cm.charge_gas(cm.price_list().on_chain_return_value(
+                            value.as_ref().map(|v| v.size() as usize).unwrap_or(0),
+                        ))?;
+                    }
+
+                    Ok(ret)
+                });
+                let (res, machine) = cm.finish();
+                (
+                    Ok((
+                        result,
+                        res.gas_used,
+                        res.gas_by_actor,
+                        res.gas_profile,
+                        res.backtrace,
+                        res.exec_trace,
+                    )),
+                    machine,
+                )
+            })?;
 
         // Extract the exit code and build the result of the message application.
         let receipt = match res {
@@ -180,14 +327,16 @@ where
             Some(ApplyFailure::MessageBacktrace(backtrace))
         };
 
-        match apply_kind {
+        let apply_ret = match apply_kind {
             ApplyKind::Explicit => self
                 .finish_message(msg, receipt, failure_info, gas_cost)
                 .map(|mut apply_ret| {
                     apply_ret.exec_trace = exec_trace;
+                    apply_ret.gas_by_actor = gas_by_actor;
+                    apply_ret.gas_profile = gas_profile;
                     apply_ret
-                }),
-            ApplyKind::Implicit => Ok(ApplyRet {
+                })?,
+            ApplyKind::Implicit | ApplyKind::ImplicitUnlimited => ApplyRet {
                 msg_receipt: receipt,
                 penalty: TokenAmount::zero(),
                 miner_tip: TokenAmount::zero(),
@@ -198,30 +347,11 @@ where
                 gas_burned: 0,
                 failure_info,
                 exec_trace,
-            }),
-        }
-    }
-
-    /// Flush the state-tree to the underlying blockstore.
-    fn flush(&mut self) -> anyhow::Result<Cid> {
-        let k = (&mut **self).flush()?;
-        Ok(k)
-    }
-}
-
-impl<K> DefaultExecutor<K>
-where
-    K: Kernel,
-{
-    /// Create a new [`DefaultExecutor`] for executing messages on the [`Machine`].
-    pub fn new(m: <K::CallManager as CallManager>::Machine) -> Self {
-        Self(Some(m))
-    }
-
-    /// Consume consumes the executor and returns the Machine. If the Machine had
-    /// been poisoned during execution, the Option will be None.
-    pub fn into_machine(self) -> Option<<K::CallManager as CallManager>::Machine> {
-        self.0
+                gas_by_actor,
+                gas_profile,
+            },
+        };
+        Ok(self.remap_exit_code(apply_ret))
     }
 
     // TODO: The return type here is very strange because we have three cases:
@@ -234,20 +364,17 @@ where
         msg: &Message,
         apply_kind: ApplyKind,
         raw_length: usize,
+        price_list: &'static PriceList,
     ) -> Result<StdResult<(ActorID, TokenAmount, GasCharge<'static>), ApplyRet>> {
         msg.check().or_fatal()?;
 
-        // TODO We don't like having price lists _inside_ the FVM, but passing
-        //  these across the boundary is also a no-go.
-        let pl = &self.context().price_list;
-
         let (inclusion_cost, miner_penalty_amount) = match apply_kind {
-            ApplyKind::Implicit => (
+            ApplyKind::Implicit | ApplyKind::ImplicitUnlimited => (
                 GasCharge::new("none", Gas::zero(), Gas::zero()),
                 Default::default(),
             ),
             ApplyKind::Explicit => {
-                let inclusion_cost = pl.on_chain_message(raw_length);
+                let inclusion_cost = price_list.on_chain_message(raw_length);
                 let inclusion_total = inclusion_cost.total().round_up();
 
                 // Verify the cost of the message is not over the message gas limit.
@@ -280,7 +407,7 @@ where
             }
         };
 
-        if apply_kind == ApplyKind::Implicit {
+        if apply_kind != ApplyKind::Explicit {
             return Ok(Ok((sender_id, TokenAmount::zero(), inclusion_cost)));
         }
 
@@ -414,6 +541,8 @@ where
             gas_burned,
             failure_info,
             exec_trace: vec![],
+            gas_by_actor: vec![],
+            gas_profile: vec![],
         })
     }
 
@@ -424,7 +553,7 @@ where
         ) -> (T, <K::CallManager as CallManager>::Machine),
     {
         replace_with::replace_with_and_return(
-            &mut self.0,
+            &mut self.machine,
             || None,
             |m| {
                 let (ret, machine) = f(m.unwrap());
@@ -1,20 +1,34 @@
+mod compare;
 mod default;
+mod parallel;
+mod selftest;
 mod threaded;
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 
 use cid::Cid;
+pub use compare::{compare_gas, ChargeComparison, GasComparison};
 pub use default::DefaultExecutor;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared::bigint::{BigInt, Sign};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::message::Message;
 use fvm_shared::receipt::Receipt;
+use fvm_shared::ActorID;
 use num_traits::Zero;
+pub use parallel::ParallelExecutor;
+pub use selftest::{CorpusMessage, DeterminismFailure, DeterminismReport};
+#[cfg(feature = "chrome-trace")]
+use serde_json::json;
 pub use threaded::ThreadedExecutor;
 
 use crate::call_manager::Backtrace;
+use crate::gas::Gas;
+#[cfg(feature = "chrome-trace")]
+use crate::trace::ExecutionEvent;
 use crate::trace::ExecutionTrace;
 use crate::Kernel;
 
@@ -89,6 +103,16 @@ pub struct ApplyRet {
     pub failure_info: Option<ApplyFailure>,
     /// Execution trace information, for debugging.
     pub exec_trace: ExecutionTrace,
+    /// The gas used by each actor invoked by the message, excluding the gas used by the actors it
+    /// called, sorted by actor ID. Gas used outside any actor, such as the message inclusion cost,
+    /// is attributed to the sender. Gas refunded for a failed sub-call is deducted from its caller,
+    /// without going below zero, so the gas adds up to at least the total gas used, before
+    /// rounding.
+    pub gas_by_actor: Vec<(ActorID, Gas)>,
+    /// The gas charged under each charge name (e.g., `OnChainMessage`), sorted by name. The gas
+    /// adds up to the total gas used, before rounding. Only recorded if
+    /// [`MachineContext::gas_profiling`](crate::machine::MachineContext::gas_profiling) is enabled.
+    pub gas_profile: Vec<(String, Gas)>,
 }
 
 impl ApplyRet {
@@ -113,12 +137,122 @@ impl ApplyRet {
             gas_burned: 0,
             failure_info: Some(ApplyFailure::PreValidation(message.into())),
             exec_trace: vec![],
+            gas_by_actor: vec![],
+            gas_profile: vec![],
         }
     }
 
     pub fn assign_from_slice(&mut self, sign: Sign, slice: &[u32]) {
         self.miner_tip.assign_from_slice(sign, slice)
     }
+
+    /// Compares this result against `other`, returning the fields that differ (in declaration
+    /// order). Debugging information (the failure info, execution trace, gas used by each actor and
+    /// gas profile) is not compared.
+    pub fn diff(&self, other: &ApplyRet) -> Vec<ApplyRetFieldDiff> {
+        use ApplyRetFieldDiff::*;
+
+        let mut diffs = Vec::new();
+        macro_rules! compare {
+            ($variant:ident, $($field:ident).+) => {
+                if self.$($field).+ != other.$($field).+ {
+                    diffs.push($variant(self.$($field).+.clone(), other.$($field).+.clone()));
+                }
+            };
+        }
+
+        compare!(ExitCode, msg_receipt.exit_code);
+        compare!(ReturnData, msg_receipt.return_data);
+        compare!(GasUsed, msg_receipt.gas_used);
+        compare!(Penalty, penalty);
+        compare!(MinerTip, miner_tip);
+        compare!(BaseFeeBurn, base_fee_burn);
+        compare!(OverEstimationBurn, over_estimation_burn);
+        compare!(Refund, refund);
+        compare!(GasRefund, gas_refund);
+        compare!(GasBurned, gas_burned);
+        diffs
+    }
+
+    /// Serializes the execution trace in the [Chrome trace event format][format], to be viewed with
+    /// `chrome://tracing` or a compatible viewer. Each call is a span, opened by a begin (`"B"`)
+    /// event and closed by a matching end (`"E"`) event, nested within its caller's span.
+    ///
+    /// The trace doesn't record time, so each event is placed at a logical timestamp: its index
+    /// in the trace, in microseconds. Only the nesting and order of the spans are meaningful.
+    /// Syscalls aren't recorded in the trace, so only calls have spans. The trace is empty unless
+    /// tracing was enabled.
+    ///
+    /// Requires the `chrome-trace` feature.
+    ///
+    /// [format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+    #[cfg(feature = "chrome-trace")]
+    pub fn to_chrome_trace(&self) -> String {
+        let events: Vec<_> = self
+            .exec_trace
+            .iter()
+            .enumerate()
+            .map(|(ts, event)| {
+                let (name, ph, args) = match event {
+                    ExecutionEvent::Call {
+                        from,
+                        to,
+                        method,
+                        params,
+                        value,
+                    } => (
+                        format!("{} method {}", to, method),
+                        "B",
+                        json!({
+                            "from": from,
+                            "to": to.to_string(),
+                            "method": method,
+                            "params_len": params.len(),
+                            "value": value.to_string(),
+                        }),
+                    ),
+                    ExecutionEvent::CallReturn(ret) => {
+                        ("return".into(), "E", json!({ "return_len": ret.len() }))
+                    }
+                    ExecutionEvent::CallAbort(code) => {
+                        ("abort".into(), "E", json!({ "exit_code": code.value() }))
+                    }
+                    ExecutionEvent::CallError(err) => {
+                        ("error".into(), "E", json!({ "error": err.to_string() }))
+                    }
+                    ExecutionEvent::GasCharge { name, gas } => {
+                        (name.clone(), "i", json!({ "milligas": gas.as_milligas() }))
+                    }
+                };
+                json!({
+                    "name": name,
+                    "cat": "call",
+                    "ph": ph,
+                    "ts": ts,
+                    "pid": 0,
+                    "tid": 0,
+                    "args": args,
+                })
+            })
+            .collect();
+        serde_json::Value::Array(events).to_string()
+    }
+}
+
+/// A single field that differs between two [`ApplyRet`]s, as reported by [`ApplyRet::diff`]. Each
+/// variant holds the value from `self` followed by the value from `other`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApplyRetFieldDiff {
+    ExitCode(ExitCode, ExitCode),
+    ReturnData(RawBytes, RawBytes),
+    GasUsed(i64, i64),
+    Penalty(BigInt, BigInt),
+    MinerTip(BigInt, BigInt),
+    BaseFeeBurn(TokenAmount, TokenAmount),
+    OverEstimationBurn(TokenAmount, TokenAmount),
+    Refund(TokenAmount, TokenAmount),
+    GasRefund(i64, i64),
+    GasBurned(i64, i64),
 }
 
 /// The kind of message being applied:
@@ -127,8 +261,50 @@ impl ApplyRet {
 /// consumed.
 /// 2. Implicit messages may come from any actor, ignore the nonce, and charge no gas (but still
 /// account for it).
+/// 3. Unlimited implicit messages are implicit messages whose gas limit isn't enforced, so they
+/// can't run out of gas. They're intended for trusted system messages (e.g., cron).
+///
+/// Only the host can apply implicit messages; actors can't send them.
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
 pub enum ApplyKind {
     Explicit,
     Implicit,
+    ImplicitUnlimited,
+}
+
+/// The state blocks read while executing a message, as recorded by
+/// [`DefaultExecutor::execute_message_with_witness`]. Together with the machine's context and
+/// externs, this is enough to construct a machine at [`root`](Self::root) and re-execute the
+/// message, producing the same result.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Witness {
+    /// The state root the message was executed against.
+    pub root: Cid,
+    /// The blocks read, keyed by CID.
+    pub blocks: BTreeMap<Cid, Vec<u8>>,
+}
+
+impl Witness {
+    /// Returns a blockstore holding the witness' blocks.
+    pub fn to_blockstore(&self) -> anyhow::Result<MemoryBlockstore> {
+        let bs = MemoryBlockstore::default();
+        bs.put_many_keyed(self.blocks.iter().map(|(k, v)| (*k, v)))?;
+        Ok(bs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_differing_fields() {
+        let ret = ApplyRet::prevalidation_fail(ExitCode::OK, "", BigInt::zero());
+        assert!(ret.diff(&ret.clone()).is_empty());
+
+        let mut other = ret.clone();
+        other.msg_receipt.gas_used = 10;
+        assert_eq!(ret.diff(&other), vec![ApplyRetFieldDiff::GasUsed(0, 10)]);
+    }
 }
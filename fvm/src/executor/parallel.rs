@@ -0,0 +1,261 @@
+use std::collections::BTreeSet;
+use std::mem;
+
+use anyhow::{anyhow, Context as _};
+use cid::Cid;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::{ActorID, METHOD_SEND};
+use lazy_static::lazy_static;
+
+use super::{ApplyKind, ApplyRet, DefaultExecutor, Executor};
+use crate::call_manager::CallManager;
+use crate::kernel::Context as _;
+use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ADDR, REWARD_ACTOR_ADDR};
+use crate::state_tree::{ActorState, StateTree};
+use crate::Kernel;
+
+lazy_static! {
+    static ref PARALLEL_POOL: yastl::Pool = yastl::Pool::with_config(
+        8,
+        yastl::ThreadConfig::new()
+            .prefix("fvm-parallel-executor")
+            // Same as the threaded executor: messages may need up to ~36MiB of stack.
+            .stack_size(64 << 20),
+    );
+}
+
+/// The actors paid by every explicit message (gas burn and miner tip). Messages may touch these
+/// concurrently, so they're merged by summing the balance changes.
+const PAYEES: [Address; 2] = [BURNT_FUNDS_ACTOR_ADDR, REWARD_ACTOR_ADDR];
+
+type MachineOf<K> = <<K as Kernel>::CallManager as CallManager>::Machine;
+
+/// An executor that applies independent messages concurrently.
+///
+/// Messages are applied in batches of messages touching disjoint sets of actors. Only plain
+/// transfers ([`METHOD_SEND`]) between existing actors are batched: they can't invoke actor code,
+/// so the sender and the recipient are the only actors they touch (other than the gas payees).
+/// Transfers to or from the reward and burnt funds actors, and all other messages, are applied
+/// serially.
+///
+/// Each message in a batch is applied on its own machine, created with `new_machine` from the state
+/// root before the batch. The touched actors are then copied back and the gas payments summed.
+/// Machines returned by `new_machine` must share the blockstore underlying the executor's machine.
+pub struct ParallelExecutor<K: Kernel, F> {
+    executor: DefaultExecutor<K>,
+    new_machine: F,
+}
+
+/// A message pending application in a batch.
+struct Pending {
+    msg: Message,
+    apply_kind: ApplyKind,
+    raw_length: usize,
+    actors: BTreeSet<ActorID>,
+}
+
+/// The result of applying a message on its own machine.
+struct Applied {
+    ret: ApplyRet,
+    /// The final states of the actors touched by the message.
+    actors: Vec<(ActorID, ActorState)>,
+    /// The amounts paid to each of the [`PAYEES`].
+    payments: Vec<TokenAmount>,
+}
+
+impl<K, F> ParallelExecutor<K, F>
+where
+    K: Kernel,
+    MachineOf<K>: Send,
+    F: Fn(Cid) -> anyhow::Result<MachineOf<K>> + Sync,
+{
+    /// Create a new [`ParallelExecutor`] for executing messages on the [`Machine`], creating a new
+    /// machine for the given state root with `new_machine` for each message applied concurrently.
+    pub fn new(machine: MachineOf<K>, new_machine: F) -> Self {
+        Self {
+            executor: DefaultExecutor::new(machine),
+            new_machine,
+        }
+    }
+
+    /// Consumes the executor and returns the Machine. If the Machine had been poisoned during
+    /// execution, the Option will be None.
+    pub fn into_machine(self) -> Option<MachineOf<K>> {
+        self.executor.into_machine()
+    }
+
+    /// Applies the messages in order, returning the results of each message. The final state is the
+    /// same as applying the messages serially.
+    pub fn execute_messages(
+        &mut self,
+        msgs: Vec<(Message, ApplyKind, usize)>,
+    ) -> anyhow::Result<Vec<ApplyRet>> {
+        let mut rets = Vec::with_capacity(msgs.len());
+        let mut batch = Vec::new();
+        let mut touched = BTreeSet::new();
+        for (msg, apply_kind, raw_length) in msgs {
+            // Applying the batch can't affect the result: transfers neither create nor delete
+            // actors.
+            let actors = self.transfer_actors(&msg)?;
+            if !matches!(&actors, Some(actors) if actors.is_disjoint(&touched)) {
+                rets.extend(self.execute_batch(mem::take(&mut batch))?);
+                touched.clear();
+            }
+            match actors {
+                Some(actors) => {
+                    touched.extend(&actors);
+                    batch.push(Pending {
+                        msg,
+                        apply_kind,
+                        raw_length,
+                        actors,
+                    });
+                }
+                None => rets.push(self.executor.execute_message(msg, apply_kind, raw_length)?),
+            }
+        }
+        rets.extend(self.execute_batch(batch)?);
+        Ok(rets)
+    }
+
+    /// Returns the actors touched by `msg` if it's a transfer that may be applied concurrently, or
+    /// `None` if it must be applied serially.
+    fn transfer_actors(&self, msg: &Message) -> anyhow::Result<Option<BTreeSet<ActorID>>> {
+        if msg.method_num != METHOD_SEND {
+            return Ok(None);
+        }
+
+        let state_tree = self.executor.state_tree();
+        let mut actors = BTreeSet::new();
+        for addr in [&msg.from, &msg.to] {
+            let id = match state_tree.lookup_id(addr)? {
+                Some(id) => id,
+                None => return Ok(None),
+            };
+            if PAYEES.contains(&Address::new_id(id)) || state_tree.get_actor_id(id)?.is_none() {
+                return Ok(None);
+            }
+            actors.insert(id);
+        }
+        Ok(Some(actors))
+    }
+
+    fn execute_batch(&mut self, batch: Vec<Pending>) -> anyhow::Result<Vec<ApplyRet>> {
+        if batch.len() <= 1 {
+            return batch
+                .into_iter()
+                .map(|p| {
+                    self.executor
+                        .execute_message(p.msg, p.apply_kind, p.raw_length)
+                })
+                .collect();
+        }
+
+        let root = self.executor.flush()?;
+        let mut results: Vec<Option<anyhow::Result<Applied>>> =
+            batch.iter().map(|_| None).collect();
+        let new_machine = &self.new_machine;
+        PARALLEL_POOL.scoped(|scope| {
+            for (pending, result) in batch.iter().zip(&mut results) {
+                scope.execute(move || {
+                    *result = Some(
+                        new_machine(root).and_then(|machine| apply_alone::<K>(machine, pending)),
+                    )
+                });
+            }
+        });
+
+        let mut rets = Vec::with_capacity(batch.len());
+        let mut payments = vec![TokenAmount::from(0); PAYEES.len()];
+        let state_tree = self.executor.state_tree_mut();
+        for result in results {
+            let applied = result.ok_or_else(|| anyhow!("failed to execute"))??;
+            for (id, actor) in applied.actors {
+                state_tree.set_actor_id(id, actor)?;
+            }
+            for (total, amt) in payments.iter_mut().zip(&applied.payments) {
+                *total += amt;
+            }
+            rets.push(applied.ret);
+        }
+        for (addr, amt) in PAYEES.iter().zip(&payments) {
+            state_tree
+                .mutate_actor(addr, |act| {
+                    act.deposit_funds(amt);
+                    Ok(())
+                })
+                .context("failed to lookup actor for transfer")?;
+        }
+        Ok(rets)
+    }
+}
+
+/// Applies a message on its own machine, flushing the machine so that the resulting state is
+/// available to the executor's machine.
+fn apply_alone<K: Kernel>(machine: MachineOf<K>, pending: &Pending) -> anyhow::Result<Applied> {
+    fn balances<S: fvm_ipld_blockstore::Blockstore>(
+        state_tree: &StateTree<S>,
+    ) -> anyhow::Result<Vec<TokenAmount>> {
+        PAYEES
+            .iter()
+            .map(|addr| {
+                Ok(state_tree
+                    .get_actor(addr)?
+                    .with_context(|| format!("failed to lookup actor {}", addr))?
+                    .balance)
+            })
+            .collect()
+    }
+
+    let mut executor = DefaultExecutor::<K>::new(machine);
+    let before = balances(executor.state_tree())?;
+    let ret =
+        executor.execute_message(pending.msg.clone(), pending.apply_kind, pending.raw_length)?;
+    executor.flush()?;
+
+    let state_tree = executor.state_tree();
+    let payments = balances(state_tree)?
+        .into_iter()
+        .zip(before)
+        .map(|(after, before)| after - before)
+        .collect();
+    let actors = pending
+        .actors
+        .iter()
+        .map(|&id| {
+            let actor = state_tree
+                .get_actor_id(id)?
+                .with_context(|| format!("failed to lookup actor {}", id))?;
+            Ok((id, actor))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(Applied {
+        ret,
+        actors,
+        payments,
+    })
+}
+
+impl<K, F> Executor for ParallelExecutor<K, F>
+where
+    K: Kernel,
+{
+    type Kernel = K;
+
+    /// Applies a single message serially.
+    fn execute_message(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        self.executor.execute_message(msg, apply_kind, raw_length)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        self.executor.flush()
+    }
+}
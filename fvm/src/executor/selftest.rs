@@ -0,0 +1,149 @@
+use std::fmt::Display;
+
+use cid::Cid;
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+
+use super::{ApplyKind, ApplyRet, ApplyRetFieldDiff, DefaultExecutor, Executor};
+use crate::machine::Machine;
+use crate::Kernel;
+
+/// A message in a determinism self-test corpus.
+#[derive(Clone, Debug)]
+pub struct CorpusMessage {
+    pub msg: Message,
+    pub apply_kind: ApplyKind,
+    /// The length of the message as it appears on-chain.
+    pub raw_length: usize,
+    /// The known-good ("golden") receipt for the message, if any.
+    pub expected_receipt: Option<Receipt>,
+}
+
+/// A failure detected by [`DefaultExecutor::determinism_selftest`].
+#[derive(Clone, Debug)]
+pub enum DeterminismFailure {
+    /// The two executions of the message at `index` produced different results.
+    Execution {
+        index: usize,
+        diffs: Vec<ApplyRetFieldDiff>,
+    },
+    /// The receipt of the message at `index` differs from its golden receipt.
+    Golden {
+        index: usize,
+        expected: Receipt,
+        actual: Receipt,
+    },
+    /// The two executions of the corpus produced different state roots.
+    StateRoot(Cid, Cid),
+    /// Executing the corpus failed with a fatal error, so the self-test couldn't complete.
+    Fatal(String),
+}
+
+/// The failures detected by a determinism self-test.
+#[derive(Clone, Debug, Default)]
+pub struct DeterminismReport {
+    pub failures: Vec<DeterminismFailure>,
+}
+
+impl Display for DeterminismReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "determinism self-test failed:")?;
+        for failure in &self.failures {
+            match failure {
+                DeterminismFailure::Execution { index, diffs } => {
+                    writeln!(f, "message {} executed differently: {:?}", index, diffs)?;
+                }
+                DeterminismFailure::Golden {
+                    index,
+                    expected,
+                    actual,
+                } => {
+                    writeln!(
+                        f,
+                        "message {} receipt {:?} doesn't match golden receipt {:?}",
+                        index, actual, expected
+                    )?;
+                }
+                DeterminismFailure::StateRoot(first, second) => {
+                    writeln!(f, "state roots differ: {} != {}", first, second)?;
+                }
+                DeterminismFailure::Fatal(err) => {
+                    writeln!(f, "fatal error: {}", err)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DeterminismReport {}
+
+impl<K: Kernel> DefaultExecutor<K> {
+    /// Checks that the machine executes the corpus deterministically: the corpus is executed twice
+    /// from the current state, and the results of each message and the final state roots are
+    /// cross-checked. Each message's receipt is also checked against its golden receipt, if any.
+    ///
+    /// The state is reset to the initial state afterwards, but any pending changes are flushed
+    /// first. If executing the corpus fails with a fatal error, the machine may be poisoned.
+    pub fn determinism_selftest(
+        &mut self,
+        corpus: &[CorpusMessage],
+    ) -> Result<(), DeterminismReport> {
+        let mut report = DeterminismReport::default();
+        if let Err(e) = self.run_selftest(corpus, &mut report) {
+            report
+                .failures
+                .push(DeterminismFailure::Fatal(e.to_string()));
+        }
+        if report.failures.is_empty() {
+            Ok(())
+        } else {
+            Err(report)
+        }
+    }
+
+    fn run_selftest(
+        &mut self,
+        corpus: &[CorpusMessage],
+        report: &mut DeterminismReport,
+    ) -> anyhow::Result<()> {
+        let initial_root = self.flush()?;
+        let (first, first_root) = self.run_corpus(corpus)?;
+        self.state_tree_mut().reset(&initial_root)?;
+        let (second, second_root) = self.run_corpus(corpus)?;
+        self.state_tree_mut().reset(&initial_root)?;
+
+        for (index, ((first, second), msg)) in first.iter().zip(&second).zip(corpus).enumerate() {
+            let diffs = first.diff(second);
+            if !diffs.is_empty() {
+                report
+                    .failures
+                    .push(DeterminismFailure::Execution { index, diffs });
+            }
+            match &msg.expected_receipt {
+                Some(expected) if expected != &first.msg_receipt => {
+                    report.failures.push(DeterminismFailure::Golden {
+                        index,
+                        expected: expected.clone(),
+                        actual: first.msg_receipt.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        if first_root != second_root {
+            report
+                .failures
+                .push(DeterminismFailure::StateRoot(first_root, second_root));
+        }
+        Ok(())
+    }
+
+    fn run_corpus(&mut self, corpus: &[CorpusMessage]) -> anyhow::Result<(Vec<ApplyRet>, Cid)> {
+        let rets = corpus
+            .iter()
+            .map(|m| self.execute_message(m.msg.clone(), m.apply_kind, m.raw_length))
+            .collect::<anyhow::Result<_>>()?;
+        Ok((rets, self.flush()?))
+    }
+}
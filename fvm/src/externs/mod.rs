@@ -2,6 +2,8 @@
 
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::consensus::ConsensusFault;
+use serde::{Deserialize, Serialize};
+
 pub trait Externs: Rand + Consensus {}
 
 /// Consensus related methods.
@@ -34,4 +36,73 @@ pub trait Rand {
         round: ChainEpoch,
         entropy: &[u8],
     ) -> anyhow::Result<[u8; 32]>;
+
+    /// Like [`Rand::get_beacon_randomness`], but drawing from a specific beacon `source`, as
+    /// selected by the network's beacon schedule for the round. By default, all sources are the
+    /// same beacon.
+    fn get_beacon_randomness_from_source(
+        &self,
+        _source: u64,
+        pers: i64,
+        round: ChainEpoch,
+        entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        self.get_beacon_randomness(pers, round, entropy)
+    }
+}
+
+/// What happens when drawing ticket or beacon randomness from the [`Rand`] externs fails (e.g.,
+/// because a beacon entry is missing). Configured with
+/// [`NetworkConfig::randomness_fallback`](crate::machine::NetworkConfig::randomness_fallback).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RandomnessFallback {
+    /// Fail the randomness syscall with `IllegalArgument`.
+    #[default]
+    Propagate,
+    /// Return randomness derived from the request's inputs instead. This is only meant for
+    /// tooling and replay where the real randomness isn't available, as the fallback is
+    /// predictable.
+    DeterministicFallback,
+}
+
+/// Externs for tests, with no real randomness: all randomness is zero, and consensus faults are
+/// never found.
+#[cfg(any(test, feature = "testing"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TestExterns;
+
+#[cfg(any(test, feature = "testing"))]
+impl Externs for TestExterns {}
+
+#[cfg(any(test, feature = "testing"))]
+impl Rand for TestExterns {
+    fn get_chain_randomness(
+        &self,
+        _pers: i64,
+        _round: ChainEpoch,
+        _entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        Ok([0; 32])
+    }
+
+    fn get_beacon_randomness(
+        &self,
+        _pers: i64,
+        _round: ChainEpoch,
+        _entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        Ok([0; 32])
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Consensus for TestExterns {
+    fn verify_consensus_fault(
+        &self,
+        _h1: &[u8],
+        _h2: &[u8],
+        _extra: &[u8],
+    ) -> anyhow::Result<(Option<ConsensusFault>, i64)> {
+        Ok((None, 0))
+    }
 }
@@ -1,13 +1,17 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
 use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 
+use serde::{Deserialize, Serialize};
+
 pub use self::charge::GasCharge;
 pub(crate) use self::outputs::GasOutputs;
-pub use self::price_list::{price_list_by_network_version, PriceList, WasmGasPrices};
+pub use self::price_list::{price_list_by_network_version, PriceList, ScalingCost, WasmGasPrices};
 use crate::kernel::{ExecutionError, Result};
+use crate::trace::{EventSender, ExecutionEvent};
 
 mod charge;
 mod outputs;
@@ -21,7 +25,8 @@ pub const MILLIGAS_PRECISION: i64 = 1000;
 /// - Enforces correct units by making it impossible to, e.g., get gas squared (by multiplying gas
 ///   by gas).
 /// - Makes it harder to confuse gas and milligas.
-#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Gas(i64 /* milligas */);
 
 impl Debug for Gas {
@@ -151,6 +156,10 @@ impl Mul<i32> for Gas {
 pub struct GasTracker {
     gas_limit: Gas,
     gas_used: Gas,
+    /// The gas charged under each charge name, if profiling is enabled.
+    profile: Option<BTreeMap<String, Gas>>,
+    /// Where to stream gas charges to, if anywhere.
+    events: Option<EventSender>,
 }
 
 impl GasTracker {
@@ -160,6 +169,8 @@ impl GasTracker {
         Self {
             gas_limit,
             gas_used,
+            profile: None,
+            events: None,
         }
     }
 
@@ -167,15 +178,33 @@ impl GasTracker {
     /// enough gas remaining for charge.
     pub fn charge_gas(&mut self, name: &str, to_use: Gas) -> Result<()> {
         log::trace!("charging gas: {} {}", name, to_use);
+        let before = self.gas_used;
         // The gas type uses saturating math.
         self.gas_used += to_use;
-        if self.gas_used > self.gas_limit {
+        let res = if self.gas_used > self.gas_limit {
             log::trace!("gas limit reached");
             self.gas_used = self.gas_limit;
             Err(ExecutionError::OutOfGas)
         } else {
             Ok(())
+        };
+        // Record the gas actually charged, so that the profile adds up to the gas used.
+        let charged = self.gas_used - before;
+        if let Some(events) = &self.events {
+            events.send(ExecutionEvent::GasCharge {
+                name: name.to_owned(),
+                gas: charged,
+            });
+        }
+        if let Some(profile) = &mut self.profile {
+            match profile.get_mut(name) {
+                Some(total) => *total += charged,
+                None => {
+                    profile.insert(name.to_owned(), charged);
+                }
+            }
         }
+        res
     }
 
     /// Applies the specified gas charge, where quantities are supplied in milligas.
@@ -188,6 +217,12 @@ impl GasTracker {
         self.gas_limit
     }
 
+    /// Sets the maximum gas usable by this message. Lowering the limit below the gas used makes
+    /// the next charge fail.
+    pub fn set_gas_limit(&mut self, gas_limit: Gas) {
+        self.gas_limit = gas_limit;
+    }
+
     /// Getter for gas used.
     pub fn gas_used(&self) -> Gas {
         self.gas_used
@@ -197,6 +232,22 @@ impl GasTracker {
     pub fn gas_available(&self) -> Gas {
         self.gas_limit - self.gas_used
     }
+
+    /// Starts recording the gas charged under each charge name. See [`GasTracker::profile`].
+    pub fn enable_profile(&mut self) {
+        self.profile.get_or_insert_with(BTreeMap::new);
+    }
+
+    /// Streams each gas charge to `events` as an [`ExecutionEvent::GasCharge`], as it's charged.
+    pub fn stream_events(&mut self, events: EventSender) {
+        self.events = Some(events);
+    }
+
+    /// Returns the gas charged under each charge name since profiling was enabled, or `None` if
+    /// it wasn't.
+    pub fn profile(&self) -> Option<&BTreeMap<String, Gas>> {
+        self.profile.as_ref()
+    }
 }
 
 /// Converts the specified fractional gas units into gas units
@@ -231,6 +282,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn gas_tracker_profile() -> Result<()> {
+        let mut t = GasTracker::new(Gas::new(20), Gas::zero());
+        t.charge_gas("a", Gas::new(5))?;
+        assert!(t.profile().is_none());
+
+        t.enable_profile();
+        t.charge_gas("b", Gas::new(3))?;
+        t.charge_gas("c", Gas::new(4))?;
+        t.charge_gas("b", Gas::new(2))?;
+        // only the gas actually charged is recorded when running out of gas
+        assert!(t.charge_gas("c", Gas::new(100)).is_err());
+
+        let profile: Vec<_> = t.profile().unwrap().iter().collect();
+        let (b, c) = ("b".to_owned(), "c".to_owned());
+        assert_eq!(profile, vec![(&b, &Gas::new(5)), (&c, &Gas::new(10))]);
+        Ok(())
+    }
+
+    #[test]
+    fn memcopy_charge_scales_with_len() {
+        let prices = price_list_by_network_version(fvm_shared::version::NetworkVersion::V16);
+        assert_eq!(prices.on_memcopy(0).total(), Gas::zero());
+        let charge = prices.on_memcopy(1024);
+        assert_eq!(charge.total(), prices.block_memcpy_per_byte_cost * 1024);
+        assert!(charge.total() > prices.on_memcopy(512).total());
+        assert_eq!(charge.storage_gas, Gas::zero());
+    }
+
+    #[test]
+    fn memzero_charge_scales_with_len() {
+        let prices = price_list_by_network_version(fvm_shared::version::NetworkVersion::V16);
+        assert_eq!(prices.on_memzero(0).total(), Gas::zero());
+        let charge = prices.on_memzero(1024);
+        assert_eq!(charge.total(), prices.block_memcpy_per_byte_cost * 1024);
+        assert_eq!(charge.total(), prices.on_memzero(512).total() * 2);
+        assert_eq!(charge.storage_gas, Gas::zero());
+    }
+
+    #[test]
+    fn verify_cid_charge_scales_with_len() {
+        use fvm_shared::crypto::hash::SupportedHashes;
+
+        let prices = price_list_by_network_version(fvm_shared::version::NetworkVersion::V16);
+        let base = prices.on_verify_cid(SupportedHashes::Sha2_256, 0).total();
+        let charge = prices.on_verify_cid(SupportedHashes::Sha2_256, 1024);
+        assert!(charge.total() > base);
+        assert_eq!(
+            charge.total() - base,
+            (prices.on_verify_cid(SupportedHashes::Sha2_256, 512).total() - base) * 2
+        );
+        assert_eq!(charge.storage_gas, Gas::zero());
+    }
+
     #[test]
     fn milligas_to_gas_round() {
         assert_eq!(milligas_to_gas(100, false), 0);
@@ -3,6 +3,7 @@
 
 use std::collections::HashMap;
 
+use fvm_shared::crypto::hash::SupportedHashes;
 use fvm_shared::crypto::signature::SignatureType;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::piece::PieceInfo;
@@ -34,15 +35,37 @@ lazy_static! {
         send_transfer_funds: Gas::new(27500),
         send_transfer_only_premium: Gas::new(159672),
         send_invoke_method: Gas::new(-5377),
+        actor_instantiation: ScalingCost::default(),
 
         create_actor_compute: Gas::new(1108454),
         create_actor_storage: Gas::new(36 + 40),
         delete_actor: Gas::new(-(36 + 40)),
 
+        actor_lookup: Gas::new(114617),
+        address_resolution: Zero::zero(),
+
         bls_sig_cost: Gas::new(16598605),
         secp256k1_sig_cost: Gas::new(1637292),
-
-        hashing_base: Gas::new(31355),
+        bls_pubkey_to_address_cost: Gas::new(4000),
+        secp256k1_pubkey_to_address_cost: Gas::new(31355),
+
+        hashing_cost: [
+            SupportedHashes::Sha2_256,
+            SupportedHashes::Keccak256,
+            SupportedHashes::Blake3_256,
+            SupportedHashes::Blake2b256,
+        ]
+        .iter()
+        .map(|&hasher| {
+            (
+                hasher,
+                ScalingCost {
+                    flat: Gas::new(31355),
+                    scale: Gas::zero(),
+                },
+            )
+        })
+        .collect(),
         compute_unsealed_sector_cid_base: Gas::new(98647),
         verify_seal_base: Gas::new(2000), // TODO revisit potential removal of this
 
@@ -136,6 +159,7 @@ lazy_static! {
         block_create_memret_per_byte_cost: Zero::zero(),
 
         block_read_base: Zero::zero(),
+        block_read_field_per_byte_cost: Gas::new(1),
         block_stat_base: Zero::zero(),
 
         syscall_cost: Zero::zero(),
@@ -159,15 +183,37 @@ lazy_static! {
         send_transfer_funds: Gas::new(27500),
         send_transfer_only_premium: Gas::new(159672),
         send_invoke_method: Gas::new(-5377),
+        actor_instantiation: ScalingCost::default(),
 
         create_actor_compute: Gas::new(1108454),
         create_actor_storage: Gas::new(36 + 40),
         delete_actor: Gas::new(-(36 + 40)),
 
+        actor_lookup: Gas::new(114617),
+        address_resolution: Zero::zero(),
+
         bls_sig_cost: Gas::new(16598605),
         secp256k1_sig_cost: Gas::new(1637292),
-
-        hashing_base: Gas::new(31355),
+        bls_pubkey_to_address_cost: Gas::new(4000),
+        secp256k1_pubkey_to_address_cost: Gas::new(31355),
+
+        hashing_cost: [
+            SupportedHashes::Sha2_256,
+            SupportedHashes::Keccak256,
+            SupportedHashes::Blake3_256,
+            SupportedHashes::Blake2b256,
+        ]
+        .iter()
+        .map(|&hasher| {
+            (
+                hasher,
+                ScalingCost {
+                    flat: Gas::new(31355),
+                    scale: Gas::zero(),
+                },
+            )
+        })
+        .collect(),
         compute_unsealed_sector_cid_base: Gas::new(98647),
         verify_seal_base: Gas::new(2000), // TODO revisit potential removal of this
 
@@ -261,6 +307,7 @@ lazy_static! {
         block_create_memret_per_byte_cost: Gas::new(10),
 
         block_read_base: Zero::zero(),
+        block_read_field_per_byte_cost: Gas::new(1),
         block_stat_base: Zero::zero(),
 
         syscall_cost: Gas::new(14000),
@@ -272,10 +319,13 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
-pub(crate) struct ScalingCost {
-    flat: Gas,
-    scale: Gas,
+/// A cost that scales linearly with some quantity: `flat + scale * n`.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq)]
+pub struct ScalingCost {
+    /// The fixed part of the cost.
+    pub flat: Gas,
+    /// The cost per unit.
+    pub scale: Gas,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -347,6 +397,10 @@ pub struct PriceList {
     /// Accounts for the cost of loading receiver code and method dispatch.
     pub(crate) send_invoke_method: Gas,
 
+    /// Gas cost for instantiating an actor's module, scaled by the size of the actor's code in
+    /// bytes.
+    pub(crate) actor_instantiation: ScalingCost,
+
     /// Gas cost for creating a new actor (via InitActor's Exec method).
     /// Note: this costs assume that the extra will be partially or totally refunded while
     /// the base is covering for the put.
@@ -357,12 +411,25 @@ pub struct PriceList {
     /// Note: this partially refunds the create cost to incentivise the deletion of the actors.
     pub(crate) delete_actor: Gas,
 
+    /// Gas cost for looking up an actor in the state tree. This is roughly the cost of opening a
+    /// block.
+    pub(crate) actor_lookup: Gas,
+
+    /// Gas cost for resolving a non-ID address to an ID address. Charged in full whether or not
+    /// the resolution is cached, as gas must not depend on the state of the cache.
+    pub(crate) address_resolution: Gas,
+
     /// Gas cost for verifying bls signature
     pub(crate) bls_sig_cost: Gas,
     /// Gas cost for verifying secp256k1 signature
     pub(crate) secp256k1_sig_cost: Gas,
+    /// Gas cost for computing the address of a bls public key
+    pub(crate) bls_pubkey_to_address_cost: Gas,
+    /// Gas cost for computing the address of a secp256k1 public key (hashing the key)
+    pub(crate) secp256k1_pubkey_to_address_cost: Gas,
 
-    pub(crate) hashing_base: Gas,
+    /// Gas cost for hashing data, by hash function, scaled by the length of the data.
+    pub(crate) hashing_cost: HashMap<SupportedHashes, ScalingCost>,
 
     pub(crate) compute_unsealed_sector_cid_base: Gas,
     pub(crate) verify_seal_base: Gas,
@@ -400,6 +467,8 @@ pub struct PriceList {
 
     /// Gas cost for reading a block into actor space.
     pub(crate) block_read_base: Gas,
+    /// Gas cost for every byte traversed when reading a field from a block.
+    pub(crate) block_read_field_per_byte_cost: Gas,
     /// Gas cost for statting a block.
     pub(crate) block_stat_base: Gas,
 
@@ -465,6 +534,22 @@ impl PriceList {
         GasCharge::new("OnSyscall", self.syscall_cost, Zero::zero())
     }
 
+    /// Returns the gas required for instantiating an actor's module, given the size of its code.
+    /// If specified, `cost` overrides the instantiation cost from the price list.
+    #[inline]
+    pub fn on_actor_instantiation(
+        &self,
+        code_size: usize,
+        cost: Option<ScalingCost>,
+    ) -> GasCharge<'static> {
+        let cost = cost.unwrap_or(self.actor_instantiation);
+        GasCharge::new(
+            "OnActorInstantiation",
+            cost.flat + cost.scale * code_size as i64,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for creating an actor.
     #[inline]
     pub fn on_create_actor(&self) -> GasCharge<'static> {
@@ -485,6 +570,24 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for looking up an actor in the state tree.
+    #[inline]
+    pub fn on_actor_lookup(&self) -> GasCharge<'static> {
+        GasCharge::new("OnActorLookup", self.actor_lookup, Zero::zero())
+    }
+
+    /// Returns the gas required for resolving a non-ID address, cached or not.
+    #[inline]
+    pub fn on_address_resolution(&self) -> GasCharge<'static> {
+        GasCharge::new("OnAddressResolution", self.address_resolution, Zero::zero())
+    }
+
+    /// Sets the gas charged for resolving a non-ID address.
+    pub fn set_address_resolution_cost(&mut self, cost: Gas) -> &mut Self {
+        self.address_resolution = cost;
+        self
+    }
+
     /// Returns gas required for signature verification.
     #[inline]
     pub fn on_verify_signature(&self, sig_type: SignatureType) -> GasCharge<'static> {
@@ -495,10 +598,47 @@ impl PriceList {
         GasCharge::new("OnVerifySignature", val, Zero::zero())
     }
 
+    /// Returns gas required for computing the address of a public key.
+    #[inline]
+    pub fn on_pubkey_to_address(&self, sig_type: SignatureType) -> GasCharge<'static> {
+        let val = match sig_type {
+            SignatureType::BLS => self.bls_pubkey_to_address_cost,
+            SignatureType::Secp256k1 => self.secp256k1_pubkey_to_address_cost,
+        };
+        GasCharge::new("OnPubkeyToAddress", val, Zero::zero())
+    }
+
     /// Returns gas required for hashing data.
     #[inline]
-    pub fn on_hashing(&self, _: usize) -> GasCharge<'static> {
-        GasCharge::new("OnHashing", self.hashing_base, Zero::zero())
+    pub fn on_hashing(&self, hasher: SupportedHashes, data_len: usize) -> GasCharge<'static> {
+        let cost = self.hashing_cost.get(&hasher).unwrap_or_else(|| {
+            self.hashing_cost
+                .get(&SupportedHashes::Blake2b256)
+                .expect("blake2b hashing cost not found")
+        });
+        GasCharge::new(
+            "OnHashing",
+            cost.flat + cost.scale * data_len as i64,
+            Zero::zero(),
+        )
+    }
+
+    /// Returns the gas required for checking that `data_len` bytes of an actor's memory match a
+    /// CID: hashing the data with `hasher`, and reading it from memory.
+    #[inline]
+    pub fn on_verify_cid(&self, hasher: SupportedHashes, data_len: usize) -> GasCharge<'static> {
+        GasCharge::new(
+            "OnVerifyCid",
+            self.on_hashing(hasher, data_len).compute_gas
+                + self.block_memcpy_per_byte_cost * data_len as i64,
+            Zero::zero(),
+        )
+    }
+
+    /// Sets the gas charged for hashing data with `hasher`, scaled by the length of the data.
+    pub fn set_hashing_cost(&mut self, hasher: SupportedHashes, cost: ScalingCost) -> &mut Self {
+        self.hashing_cost.insert(hasher, cost);
+        self
     }
 
     /// Returns gas required for computing unsealed sector Cid.
@@ -640,6 +780,17 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for finding a field in a loaded object, traversing `traversed`
+    /// bytes of the object. Creating the block holding the field is charged separately.
+    #[inline]
+    pub fn on_block_read_field(&self, traversed: usize) -> GasCharge<'static> {
+        GasCharge::new(
+            "OnBlockReadField",
+            self.block_read_base + (self.block_read_field_per_byte_cost * traversed as i64),
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for adding an object to the FVM cache.
     #[inline]
     pub fn on_block_create(&self, data_size: usize) -> GasCharge<'static> {
@@ -653,6 +804,13 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for measuring the size of a block without creating it. Unlike
+    /// [`PriceList::on_block_create`], the data isn't copied, so the charge is flat.
+    #[inline]
+    pub fn on_block_measure(&self) -> GasCharge<'static> {
+        GasCharge::new("OnBlockMeasure", self.block_stat_base, Zero::zero())
+    }
+
     /// Returns the gas required for committing an object to the state blockstore.
     #[inline]
     pub fn on_block_link(&self, data_size: usize) -> GasCharge<'static> {
@@ -669,6 +827,26 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for copying `len` bytes within an actor's memory.
+    #[inline]
+    pub fn on_memcopy(&self, len: usize) -> GasCharge<'static> {
+        GasCharge::new(
+            "OnMemCopy",
+            self.block_memcpy_per_byte_cost * len as i64,
+            Zero::zero(),
+        )
+    }
+
+    /// Returns the gas required for zeroing `len` bytes of an actor's memory.
+    #[inline]
+    pub fn on_memzero(&self, len: usize) -> GasCharge<'static> {
+        GasCharge::new(
+            "OnMemZero",
+            self.block_memcpy_per_byte_cost * len as i64,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for storing an object.
     #[inline]
     pub fn on_block_stat(&self) -> GasCharge<'static> {
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::rc::Rc;
 
@@ -11,6 +12,8 @@ use crate::syscall_error;
 #[derive(Default)]
 pub struct BlockRegistry {
     blocks: Vec<Block>,
+    /// The blocks added with [`BlockRegistry::put_dedup`], keyed by codec and content digest.
+    dedup_index: HashMap<(u64, [u8; 32]), BlockId>,
 }
 
 /// Blocks in the block registry are addressed by an ordinal, starting from 1 (`FIRST_ID`).
@@ -18,7 +21,7 @@ pub struct BlockRegistry {
 /// receive or return no data.
 pub type BlockId = u32;
 
-const FIRST_ID: BlockId = 1;
+pub(crate) const FIRST_ID: BlockId = 1;
 const MAX_BLOCKS: u32 = i32::MAX as u32; // TODO(M2): Limit
 
 /// Codecs allowed by the IPLD subsytem.
@@ -81,6 +84,14 @@ pub enum BlockPutError {
     InvalidCodec(u64),
 }
 
+/// Checks that blocks may be created with the given codec.
+pub(crate) fn check_codec(codec: u64) -> Result<(), BlockPutError> {
+    if !ALLOWED_CODECS.contains(&codec) {
+        return Err(BlockPutError::InvalidCodec(codec));
+    }
+    Ok(())
+}
+
 impl From<BlockPutError> for super::SyscallError {
     fn from(e: BlockPutError) -> Self {
         match e {
@@ -114,7 +125,7 @@ impl From<InvalidHandleError> for ExecutionError {
 
 impl BlockRegistry {
     pub(crate) fn new() -> Self {
-        Self { blocks: Vec::new() }
+        Self::default()
     }
 }
 
@@ -125,15 +136,34 @@ impl BlockRegistry {
             return Err(BlockPutError::TooManyBlocks);
         }
 
-        if !ALLOWED_CODECS.contains(&block.codec) {
-            return Err(BlockPutError::InvalidCodec(block.codec));
-        }
+        check_codec(block.codec)?;
 
         let id = FIRST_ID + self.blocks.len() as u32;
         self.blocks.push(block);
         Ok(id)
     }
 
+    /// Like [`BlockRegistry::put`], but returns the handle of an existing block if a block with the
+    /// same codec and content has already been added with `put_dedup`.
+    ///
+    /// Blocks are looked up by a digest of their content, so this takes time linear in the size of
+    /// the block, not in the number of blocks.
+    pub fn put_dedup(&mut self, block: Block) -> Result<BlockId, BlockPutError> {
+        let digest = blake2b_simd::Params::new()
+            .hash_length(32)
+            .hash(block.data());
+        let key = (
+            block.codec,
+            digest.as_bytes().try_into().expect("digest is 32 bytes"),
+        );
+        if let Some(&id) = self.dedup_index.get(&key) {
+            return Ok(id);
+        }
+        let id = self.put(block)?;
+        self.dedup_index.insert(key, id);
+        Ok(id)
+    }
+
     /// Gets the block associated with a block handle.
     pub fn get(&self, id: BlockId) -> Result<&Block, InvalidHandleError> {
         if id < FIRST_ID {
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::{TryFrom, TryInto};
 use std::panic::{self, UnwindSafe};
 use std::path::PathBuf;
@@ -8,26 +8,34 @@ use byteorder::{BigEndian, WriteBytesExt};
 use cid::Cid;
 use filecoin_proofs_api::{self as proofs, ProverId, PublicReplicaInfo, SectorId};
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::{bytes_32, from_slice, to_vec};
+use fvm_ipld_encoding::{
+    bytes_32, cbor_links, check_canonical_cbor, check_cbor_depth, find_cbor_path, from_slice,
+    to_vec, PathSegment, DAG_CBOR,
+};
 use fvm_shared::actor::builtin::Type;
 use fvm_shared::address::Protocol;
+use fvm_shared::bigint::bigint_ser::BigIntSer;
 use fvm_shared::bigint::{BigInt, Zero};
 use fvm_shared::consensus::ConsensusFault;
+use fvm_shared::crypto::hash::SupportedHashes;
 use fvm_shared::crypto::signature;
-use fvm_shared::econ::TokenAmount;
+use fvm_shared::econ::{CircSupplyDetail, TokenAmount};
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::piece::{zero_piece_commitment, PaddedPieceSize};
 use fvm_shared::sector::SectorInfo;
 use fvm_shared::version::NetworkVersion;
-use fvm_shared::{commcid, ActorID, FILECOIN_PRECISION};
+use fvm_shared::{
+    commcid, ActorID, FILECOIN_PRECISION, IDENTITY_HASH, MAX_COUNTER_SLOTS, METHOD_SEND,
+};
 use lazy_static::lazy_static;
+use num_traits::FromPrimitive;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
 use super::blocks::{Block, BlockRegistry};
 use super::error::Result;
 use super::*;
-use crate::call_manager::{CallManager, InvocationResult, NO_DATA_BLOCK_ID};
-use crate::externs::{Consensus, Rand};
+use crate::call_manager::{ArtifactOverflow, CallManager, InvocationResult, NO_DATA_BLOCK_ID};
+use crate::externs::{Consensus, Rand, RandomnessFallback};
 use crate::gas::GasCharge;
 use crate::state_tree::ActorState;
 use crate::{syscall_error, EMPTY_ARR_CID};
@@ -39,7 +47,10 @@ lazy_static! {
 
 const BLAKE2B_256: u64 = 0xb220;
 const ENV_ARTIFACT_DIR: &str = "FVM_STORE_ARTIFACT_DIR";
-const MAX_ARTIFACT_NAME_LEN: usize = 256;
+const LOCAL_RANDOMNESS_PERSONALIZATION: &[u8] = b"fvm-local-prng";
+
+/// The blake2b personalization of the randomness returned when the externs fail to provide it.
+const FALLBACK_RANDOMNESS_PERSONALIZATION: &[u8] = b"fvm-fallback-rng";
 
 /// The "default" [`Kernel`] implementation.
 pub struct DefaultKernel<C> {
@@ -58,8 +69,16 @@ pub struct DefaultKernel<C> {
     ///
     /// This does not yet reason about reachability.
     blocks: BlockRegistry,
+
+    /// Ticket and beacon randomness already drawn by this invocation, so repeated requests don't
+    /// go back to the externs. The caches are dropped with the kernel at the end of the invocation.
+    chain_randomness: RandomnessCache,
+    beacon_randomness: RandomnessCache,
 }
 
+/// Randomness keyed by the personalization, epoch, and entropy it was drawn with.
+type RandomnessCache = HashMap<(i64, ChainEpoch, Vec<u8>), [u8; RANDOMNESS_LENGTH]>;
+
 // Even though all children traits are implemented, Rust needs to know that the
 // supertrait is implemented too.
 impl<C> Kernel for DefaultKernel<C>
@@ -90,6 +109,8 @@ where
             actor_id,
             method,
             value_received,
+            chain_randomness: HashMap::new(),
+            beacon_randomness: HashMap::new(),
         }
     }
 }
@@ -98,6 +119,71 @@ impl<C> DefaultKernel<C>
 where
     C: CallManager,
 {
+    /// Handles the `result` of drawing randomness from the externs according to the network's
+    /// [`RandomnessFallback`] policy. On failure, the fallback randomness is derived from the
+    /// `source` of randomness and the request's inputs.
+    fn randomness_or_fallback(
+        &self,
+        result: anyhow::Result<[u8; RANDOMNESS_LENGTH]>,
+        source: &str,
+        personalization: i64,
+        rand_epoch: ChainEpoch,
+        entropy: &[u8],
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        match (result, self.call_manager.context().randomness_fallback) {
+            (Ok(randomness), _) => Ok(randomness),
+            (Err(e), RandomnessFallback::Propagate) => Err(e).or_illegal_argument(),
+            (Err(e), RandomnessFallback::DeterministicFallback) => {
+                log::debug!("using fallback {} randomness: {}", source, e);
+                let digest = blake2b_simd::Params::new()
+                    .hash_length(RANDOMNESS_LENGTH)
+                    .personal(FALLBACK_RANDOMNESS_PERSONALIZATION)
+                    .to_state()
+                    .update(source.as_bytes())
+                    .update(&personalization.to_be_bytes())
+                    .update(&rand_epoch.to_be_bytes())
+                    .update(entropy)
+                    .finalize()
+                    .as_bytes()
+                    .try_into()
+                    .expect("fixed array size");
+                Ok(digest)
+            }
+        }
+    }
+
+    /// Fails with `IllegalOperation` if the current invocation is running in read-only mode.
+    fn check_writable(&self, op: &str) -> Result<()> {
+        if self.call_manager.read_only() {
+            return Err(syscall_error!(IllegalOperation; "cannot {} in read-only mode", op).into());
+        }
+        Ok(())
+    }
+
+    /// Fails with `NotFound` if the DAG-CBOR `data` links to a block that isn't in the blockstore,
+    /// looking inside links with identity hashes. Each lookup in the blockstore is charged as a
+    /// block open. See
+    /// [`NetworkConfig::strict_block_links`](crate::machine::NetworkConfig::strict_block_links).
+    fn check_links_present(&mut self, data: &[u8]) -> Result<()> {
+        let mut pending = cbor_links(data).or_illegal_argument()?;
+        while let Some(link) = pending.pop() {
+            if link.hash().code() == IDENTITY_HASH {
+                if link.codec() == DAG_CBOR {
+                    pending.extend(cbor_links(link.hash().digest()).or_illegal_argument()?);
+                }
+                continue;
+            }
+            self.call_manager
+                .charge_gas(self.call_manager.price_list().on_block_open_base())?;
+            if !self.call_manager.blockstore().has(&link).or_fatal()? {
+                return Err(
+                    syscall_error!(NotFound; "block links to missing block {}", link).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn resolve_to_key_addr(&mut self, addr: &Address, charge_gas: bool) -> Result<Address> {
         if addr.protocol() == Protocol::BLS || addr.protocol() == Protocol::Secp256k1 {
             return Ok(*addr);
@@ -197,10 +283,17 @@ where
     }
 
     fn set_root(&mut self, new: Cid) -> Result<()> {
+        self.check_writable("set the state root")?;
+        let mut changed = false;
         self.mutate_self(|actor_state| {
+            changed = actor_state.state != new;
             actor_state.state = new;
             Ok(())
-        })
+        })?;
+        if changed {
+            self.call_manager.record_state_write(self.actor_id, new);
+        }
+        Ok(())
     }
 
     fn current_balance(&self) -> Result<TokenAmount> {
@@ -208,7 +301,100 @@ where
         Ok(self.get_self()?.map(|a| a.balance).unwrap_or_default())
     }
 
+    fn state_commitment_with_balance(&mut self) -> Result<[u8; 32]> {
+        let actor = self
+            .get_self()?
+            .context("state commitment requested after actor deletion")
+            .or_error(ErrorNumber::IllegalOperation)?;
+
+        let data = to_vec(&(actor.state, BigIntSer(&actor.balance), actor.sequence))
+            .or_fatal()
+            .context("failed to encode state commitment")?;
+        self.hash(SupportedHashes::Blake2b256 as u64, &data)
+    }
+
+    fn bump_counter(&mut self, slot: u64) -> Result<u64> {
+        use multihash::MultihashDigest;
+
+        self.check_writable("bump counters")?;
+
+        if slot >= MAX_COUNTER_SLOTS {
+            return Err(
+                syscall_error!(IllegalArgument; "counter slot {} out of range", slot).into(),
+            );
+        }
+
+        let root = self.root()?;
+        if root.codec() != DAG_CBOR {
+            return Err(syscall_error!(IllegalOperation; "actor state isn't DAG-CBOR").into());
+        }
+
+        self.call_manager
+            .charge_gas(self.call_manager.price_list().on_block_open_base())?;
+        let data = self
+            .call_manager
+            .blockstore()
+            .get(&root)
+            .or_fatal()?
+            .context("actor state not found")
+            .or_fatal()?;
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_open_per_byte(data.len()),
+        )?;
+
+        // Charge for the bytes traversed, or the whole state if the traversal failed.
+        let lookup = find_cbor_path(&data, &[PathSegment::Index(0)]);
+        let traversed = match &lookup {
+            Ok(lookup) => lookup.traversed,
+            Err(_) => data.len(),
+        };
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_read_field(traversed),
+        )?;
+
+        let range = lookup
+            .or_error(ErrorNumber::Serialization)?
+            .value
+            .ok_or_else(|| syscall_error!(IllegalOperation; "actor state has no counter area"))?;
+        let mut counters: Vec<u64> = from_slice(&data[range.clone()])
+            .or_error(ErrorNumber::IllegalOperation)
+            .context("invalid counter area")?;
+
+        let slot = slot as usize;
+        if counters.len() <= slot {
+            counters.resize(slot + 1, 0);
+        }
+        let value = counters[slot]
+            .checked_add(1)
+            .ok_or_else(|| syscall_error!(LimitExceeded; "counter {} overflowed", slot))?;
+        counters[slot] = value;
+
+        // Splice the new counter area into the state, leaving the rest of it untouched.
+        let counters = to_vec(&counters).or_fatal()?;
+        let mut state = Vec::with_capacity(data.len() - range.len() + counters.len());
+        state.extend_from_slice(&data[..range.start]);
+        state.extend_from_slice(&counters);
+        state.extend_from_slice(&data[range.end..]);
+
+        self.call_manager
+            .charge_gas(self.call_manager.price_list().on_block_link(state.len()))?;
+        let new_root = Cid::new_v1(DAG_CBOR, multihash::Code::Blake2b256.digest(&state));
+        self.call_manager
+            .blockstore()
+            .put_keyed(&new_root, &state)
+            .or_fatal()?;
+        self.set_root(new_root)?;
+
+        Ok(value)
+    }
+
     fn self_destruct(&mut self, beneficiary: &Address) -> Result<()> {
+        self.check_writable("self-destruct")?;
+
         // Idempotentcy: If the actor doesn't exist, this won't actually do anything. The current
         // balance will be zero, and `delete_actor_id` will be a no-op.
         self.call_manager
@@ -273,6 +459,14 @@ where
                 .on_block_open_per_byte(block.size() as usize),
         )?;
 
+        if block.codec() == DAG_CBOR {
+            let context = self.call_manager.context();
+            if context.strict_cbor {
+                check_canonical_cbor(block.data()).or_illegal_argument()?;
+            }
+            check_cbor_depth(block.data(), context.max_cbor_depth).or_illegal_argument()?;
+        }
+
         let stat = block.stat();
         let id = self.blocks.put(block)?;
         Ok((id, stat))
@@ -282,7 +476,20 @@ where
         self.call_manager
             .charge_gas(self.call_manager.price_list().on_block_create(data.len()))?;
 
-        Ok(self.blocks.put(Block::new(codec, data))?)
+        let block = Block::new(codec, data);
+        Ok(if self.call_manager.context().dedup_block_create {
+            self.blocks.put_dedup(block)?
+        } else {
+            self.blocks.put(block)?
+        })
+    }
+
+    fn block_measure(&mut self, codec: u64, data: &[u8]) -> Result<u32> {
+        self.call_manager
+            .charge_gas(self.call_manager.price_list().on_block_measure())?;
+
+        super::blocks::check_codec(codec)?;
+        Ok(data.len() as u32)
     }
 
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
@@ -291,7 +498,7 @@ where
         }
 
         use multihash::MultihashDigest;
-        let block = self.blocks.get(id)?;
+        let block = self.blocks.get(id)?.clone();
         let code = multihash::Code::try_from(hash_fun)
             .map_err(|_| syscall_error!(IllegalCid; "invalid CID codec"))?;
 
@@ -306,6 +513,9 @@ where
             return Err(syscall_error!(IllegalCid; "invalid hash length: {}", hash_len).into());
         }
         let k = Cid::new_v1(block.codec(), hash.truncate(hash_len as u8));
+        if block.codec() == DAG_CBOR && self.call_manager.context().strict_block_links {
+            self.check_links_present(block.data())?;
+        }
         // TODO(M2): Add the block to the reachable set.
         self.call_manager
             .blockstore()
@@ -355,6 +565,51 @@ where
 
         Ok(self.blocks.stat(id)?)
     }
+
+    fn block_read_field(&mut self, id: BlockId, path: &[PathSegment]) -> Result<BlockId> {
+        let block = self.blocks.get(id)?.clone();
+        if block.codec() != DAG_CBOR {
+            return Err(syscall_error!(IllegalCodec; "block {} isn't DAG-CBOR", id).into());
+        }
+
+        // Charge for the bytes traversed, or the whole block if the traversal failed.
+        let lookup = find_cbor_path(block.data(), path);
+        let traversed = match &lookup {
+            Ok(lookup) => lookup.traversed,
+            Err(_) => block.data().len(),
+        };
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_read_field(traversed),
+        )?;
+
+        let range = lookup
+            .or_error(ErrorNumber::Serialization)?
+            .value
+            .ok_or_else(|| syscall_error!(NotFound; "no value at path {:?}", path))?;
+        self.block_create(block.codec(), &block.data()[range])
+    }
+
+    fn verify_cid(&mut self, cid: &Cid, data: &[u8]) -> Result<bool> {
+        use multihash::MultihashDigest;
+
+        let code = cid.hash().code();
+        let hasher = SupportedHashes::from_u64(code)
+            .ok_or_else(|| syscall_error!(IllegalCid; "unsupported hash code {}", code))?;
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_verify_cid(hasher, data.len()),
+        )?;
+
+        let hash = multihash::Code::try_from(code)
+            .or_fatal()
+            .context("supported hash function not implemented")?
+            .digest(data);
+        // Compares the full digest, so truncated (or empty) digests never match.
+        Ok(*cid.hash() == hash)
+    }
 }
 
 impl<C> MessageOps for DefaultKernel<C>
@@ -376,6 +631,31 @@ where
     fn msg_value_received(&self) -> TokenAmount {
         self.value_received.clone()
     }
+
+    fn msg_read_params(&mut self, buf: &mut [u8]) -> Result<u32> {
+        let params = self.call_manager.invocation_params().cloned();
+        let data = params.as_ref().map(Block::data).unwrap_or_default();
+        let to_read = std::cmp::min(data.len(), buf.len());
+        self.call_manager
+            .charge_gas(self.call_manager.price_list().on_block_read(to_read))?;
+        buf[..to_read].copy_from_slice(&data[..to_read]);
+        Ok(data.len() as u32)
+    }
+
+    fn msg_params_codec(&self) -> u64 {
+        self.call_manager
+            .invocation_params()
+            .map(Block::codec)
+            .unwrap_or_default()
+    }
+
+    fn msg_origin_nonce(&self) -> u64 {
+        self.call_manager.nonce()
+    }
+
+    fn msg_expected_return_codec(&self) -> u64 {
+        self.call_manager.expected_return_codec()
+    }
 }
 
 impl<C> SendOps for DefaultKernel<C>
@@ -388,14 +668,58 @@ where
         method: MethodNum,
         params_id: BlockId,
         value: &TokenAmount,
+        options: SendOptions,
     ) -> Result<SendResult> {
         let from = self.actor_id;
+        let flags = options.flags;
+
+        if !value.is_zero() {
+            self.check_writable("transfer value")?;
+            if flags.contains(SendFlags::READ_ONLY) {
+                return Err(
+                    syscall_error!(IllegalArgument; "cannot transfer value in a read-only send")
+                        .into(),
+                );
+            }
+        }
 
-        // Load parameters.
+        // Value-only sends never invoke the recipient, so they can't carry parameters.
+        let method = if flags.contains(SendFlags::VALUE_ONLY) {
+            if params_id != NO_DATA_BLOCK_ID {
+                return Err(
+                    syscall_error!(IllegalArgument; "value-only sends can't have parameters")
+                        .into(),
+                );
+            }
+            METHOD_SEND
+        } else {
+            method
+        };
+
+        // Load parameters, rejecting disallowed codecs and oversized or deeply nested parameters
+        // before invoking the recipient.
         let params = if params_id == NO_DATA_BLOCK_ID {
             None
         } else {
-            Some(self.blocks.get(params_id)?.clone())
+            let params = self.blocks.get(params_id)?;
+            let context = self.call_manager.context();
+            if let Some(codecs) = &context.send_param_codecs {
+                if !codecs.contains(&params.codec()) {
+                    return Err(syscall_error!(IllegalCodec;
+                        "send parameters codec {} not allowed", params.codec())
+                    .into());
+                }
+            }
+            if params.size() > context.max_send_params_size {
+                return Err(syscall_error!(IllegalArgument;
+                    "send parameters too large ({} > {} bytes)",
+                    params.size(), context.max_send_params_size)
+                .into());
+            }
+            if params.codec() == DAG_CBOR {
+                check_cbor_depth(params.data(), context.max_cbor_depth).or_illegal_argument()?;
+            }
+            Some(params.clone())
         };
 
         // Make sure we can actually store the return block.
@@ -403,10 +727,46 @@ where
             return Err(syscall_error!(LimitExceeded; "cannot store return block").into());
         }
 
-        // Send.
-        let result = self
-            .call_manager
-            .with_transaction(|cm| cm.send::<Self>(from, *recipient, method, params, value))?;
+        // Send, lowering the gas limit for the duration of the call if the caller asked for less
+        // than the gas available.
+        let gas_before = self.call_manager.gas_tracker().gas_used();
+        let outer_limit = self.call_manager.gas_tracker().gas_limit();
+        let inner_limit = options
+            .gas_limit
+            .map(|limit| (gas_before + limit).min(outer_limit))
+            .unwrap_or(outer_limit);
+        self.call_manager
+            .gas_tracker_mut()
+            .set_gas_limit(inner_limit);
+        let result = self.call_manager.with_transaction(|cm| {
+            cm.send::<Self>(from, *recipient, method, params, value, options)
+        });
+        self.call_manager
+            .gas_tracker_mut()
+            .set_gas_limit(outer_limit);
+
+        // Running out of the gas given to the recipient only aborts the send.
+        let result = match result {
+            Err(ExecutionError::OutOfGas) if inner_limit < outer_limit => {
+                InvocationResult::Failure(ExitCode::SYS_OUT_OF_GAS)
+            }
+            result => result?,
+        };
+
+        // Adjust the gas charged for a failed send according to the network's policy.
+        if let InvocationResult::Failure(_) = result {
+            let consumed = self.call_manager.gas_tracker().gas_used() - gas_before;
+            let adjustment = self
+                .call_manager
+                .context()
+                .failed_subcall_gas_policy
+                .adjustment(consumed);
+            if !adjustment.is_zero() {
+                self.call_manager
+                    .gas_tracker_mut()
+                    .charge_gas("OnFailedSubcall", adjustment)?;
+            }
+        }
 
         // Store result and return.
         Ok(match result {
@@ -437,6 +797,17 @@ where
         // the FVM simply reports that value to actors.
         Ok(self.call_manager.context().circ_supply.clone())
     }
+
+    fn circ_supply_detail(&self) -> Result<CircSupplyDetail> {
+        self.call_manager
+            .context()
+            .circ_supply_detail
+            .clone()
+            .ok_or_else(|| {
+                syscall_error!(IllegalOperation; "circulating supply breakdown not available")
+                    .into()
+            })
+    }
 }
 
 impl<C> CryptoOps for DefaultKernel<C>
@@ -463,21 +834,36 @@ where
         })
     }
 
-    fn hash(&mut self, code: u64, data: &[u8]) -> Result<[u8; 32]> {
-        self.call_manager
-            .charge_gas(self.call_manager.price_list().on_hashing(data.len()))?;
+    fn pubkey_to_address(&mut self, sig_type: SignatureType, pubkey: &[u8]) -> Result<Address> {
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_pubkey_to_address(sig_type),
+        )?;
 
-        // We only support blake2b for now, but want to support others in the future.
-        if code != BLAKE2B_256 {
-            return Err(syscall_error!(IllegalArgument; "unsupported hash code {}", code).into());
+        match sig_type {
+            SignatureType::Secp256k1 => Address::new_secp256k1(pubkey),
+            SignatureType::BLS => Address::new_bls(pubkey),
         }
+        .or_illegal_argument()
+    }
 
-        let digest = blake2b_simd::Params::new()
-            .hash_length(32)
-            .to_state()
-            .update(data)
-            .finalize()
-            .as_bytes()
+    fn hash(&mut self, code: u64, data: &[u8]) -> Result<[u8; 32]> {
+        use multihash::MultihashDigest;
+
+        let hasher = SupportedHashes::from_u64(code)
+            .ok_or_else(|| syscall_error!(IllegalArgument; "unsupported hash code {}", code))?;
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_hashing(hasher, data.len()),
+        )?;
+
+        let digest = multihash::Code::try_from(code)
+            .or_fatal()
+            .context("supported hash function not implemented")?
+            .digest(data)
+            .digest()
             .try_into()
             .expect("fixed array size");
         Ok(digest)
@@ -625,6 +1011,10 @@ where
         self.call_manager.gas_tracker().gas_available()
     }
 
+    fn gas_limit(&self) -> Gas {
+        self.call_manager.invocation_gas_limit()
+    }
+
     fn charge_gas(&mut self, name: &str, compute: Gas) -> Result<()> {
         self.call_manager
             .gas_tracker_mut()
@@ -669,12 +1059,26 @@ where
                 .on_get_randomness(entropy.len()),
         )?;
 
+        let key = (personalization, rand_epoch, entropy.to_vec());
+        if let Some(randomness) = self.chain_randomness.get(&key) {
+            return Ok(*randomness);
+        }
+
         // TODO(M2): Check error code
         // Specifically, lookback length?
-        self.call_manager
-            .externs()
-            .get_chain_randomness(personalization, rand_epoch, entropy)
-            .or_illegal_argument()
+        let randomness =
+            self.call_manager
+                .externs()
+                .get_chain_randomness(personalization, rand_epoch, entropy);
+        let randomness = self.randomness_or_fallback(
+            randomness,
+            "tickets",
+            personalization,
+            rand_epoch,
+            entropy,
+        )?;
+        self.chain_randomness.insert(key, randomness);
+        Ok(randomness)
     }
 
     fn get_randomness_from_beacon(
@@ -689,12 +1093,54 @@ where
                 .on_get_randomness(entropy.len()),
         )?;
 
+        let key = (personalization, rand_epoch, entropy.to_vec());
+        if let Some(randomness) = self.beacon_randomness.get(&key) {
+            return Ok(*randomness);
+        }
+
         // TODO(M2): Check error code
         // Specifically, lookback length?
-        self.call_manager
+        let source = self.call_manager.context().beacon_source(rand_epoch);
+        let randomness = self
+            .call_manager
             .externs()
-            .get_beacon_randomness(personalization, rand_epoch, entropy)
-            .or_illegal_argument()
+            .get_beacon_randomness_from_source(source, personalization, rand_epoch, entropy);
+        let randomness = self.randomness_or_fallback(
+            randomness,
+            "beacon",
+            personalization,
+            rand_epoch,
+            entropy,
+        )?;
+        self.beacon_randomness.insert(key, randomness);
+        Ok(randomness)
+    }
+
+    fn get_local_randomness(&mut self, counter: u64) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        let origin = self.call_manager.origin().to_bytes();
+        let nonce = self.call_manager.nonce();
+
+        let seed_len = origin.len() + 4 * std::mem::size_of::<u64>();
+        self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_hashing(SupportedHashes::Blake2b256, seed_len),
+        )?;
+
+        let digest = blake2b_simd::Params::new()
+            .hash_length(RANDOMNESS_LENGTH)
+            .personal(LOCAL_RANDOMNESS_PERSONALIZATION)
+            .to_state()
+            .update(&origin)
+            .update(&nonce.to_be_bytes())
+            .update(&self.actor_id.to_be_bytes())
+            .update(&self.method.to_be_bytes())
+            .update(&counter.to_be_bytes())
+            .finalize()
+            .as_bytes()
+            .try_into()
+            .expect("fixed array size");
+        Ok(digest)
     }
 }
 
@@ -702,7 +1148,12 @@ impl<C> ActorOps for DefaultKernel<C>
 where
     C: CallManager,
 {
-    fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>> {
+    fn resolve_address(&mut self, address: &Address) -> Result<Option<ActorID>> {
+        // Charge before the lookup, so cache hits and misses cost the same.
+        if address.protocol() != Protocol::ID {
+            self.call_manager
+                .charge_gas(self.call_manager.price_list().on_address_resolution())?;
+        }
         self.call_manager.state_tree().lookup_id(address)
     }
 
@@ -716,6 +1167,39 @@ where
             .map(|act| act.code))
     }
 
+    fn is_account(&mut self, address: &Address) -> Result<bool> {
+        self.call_manager
+            .charge_gas(self.call_manager.price_list().on_actor_lookup())?;
+
+        let code = match self.resolve_address(address)? {
+            Some(id) => self.get_actor_code_cid(id)?,
+            None => None,
+        }
+        .ok_or_else(|| syscall_error!(NotFound; "actor {} not found", address))?;
+        Ok(self.get_builtin_actor_type(&code) == Some(Type::Account))
+    }
+
+    fn nonce_of(&mut self, address: &Address) -> Result<u64> {
+        self.call_manager
+            .charge_gas(self.call_manager.price_list().on_actor_lookup())?;
+
+        let actor = match self.resolve_address(address)? {
+            Some(id) => self
+                .call_manager
+                .state_tree()
+                .get_actor_id(id)
+                .context("failed to lookup actor to get nonce")
+                .or_fatal()?,
+            None => None,
+        };
+        Ok(match actor {
+            Some(act) if self.get_builtin_actor_type(&act.code) == Some(Type::Account) => {
+                act.sequence
+            }
+            _ => 0,
+        })
+    }
+
     // TODO(M2) merge new_actor_address and create_actor into a single syscall.
     fn new_actor_address(&mut self) -> Result<Address> {
         let oa = self
@@ -738,6 +1222,8 @@ where
 
     // TODO(M2) merge new_actor_address and create_actor into a single syscall.
     fn create_actor(&mut self, code_id: Cid, actor_id: ActorID) -> Result<()> {
+        self.check_writable("create actors")?;
+
         // TODO https://github.com/filecoin-project/builtin-actors/issues/492
         let singleton = self
             .get_builtin_actor_type(&code_id)
@@ -806,11 +1292,19 @@ where
         self.call_manager.context().actor_debugging
     }
 
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+    fn max_artifact_name_len(&self) -> u32 {
+        self.call_manager.context().max_artifact_name_len
+    }
+
+    fn max_artifact_size(&self) -> u32 {
+        self.call_manager.context().max_artifact_size
+    }
+
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<()> {
         // Ensure well formed artifact name
         {
-            if name.len() > MAX_ARTIFACT_NAME_LEN {
-                Err("debug artifact name should not exceed 256 bytes")
+            if name.len() > self.max_artifact_name_len() as usize {
+                Err("debug artifact name is too long")
             } else if name.chars().any(std::path::is_separator) {
                 Err("debug artifact name should not include any path separators")
             } else if name
@@ -827,6 +1321,19 @@ where
         }
         .or_error(fvm_shared::error::ErrorNumber::IllegalArgument)?;
 
+        // Enforce the per-message size cap
+        if !self.call_manager.record_artifact(data.len() as u64) {
+            return match self.call_manager.context().artifact_overflow {
+                ArtifactOverflow::Drop => {
+                    log::warn!("debug artifact {} dropped, message size cap reached", name);
+                    Ok(())
+                }
+                ArtifactOverflow::Error => Err(syscall_error!(LimitExceeded;
+                    "debug artifact {} exceeds the message size cap", name)
+                .into()),
+            };
+        }
+
         // Write to disk
         if let Ok(dir) = std::env::var(ENV_ARTIFACT_DIR).as_deref() {
             let dir: PathBuf = [
@@ -1,10 +1,11 @@
 pub use blocks::{Block, BlockId, BlockRegistry, BlockStat};
 use cid::Cid;
+use fvm_ipld_encoding::PathSegment;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::consensus::ConsensusFault;
 use fvm_shared::crypto::signature::SignatureType;
-use fvm_shared::econ::TokenAmount;
+use fvm_shared::econ::{CircSupplyDetail, TokenAmount};
 use fvm_shared::error::ExitCode;
 use fvm_shared::piece::PieceInfo;
 use fvm_shared::randomness::{Randomness, RANDOMNESS_LENGTH};
@@ -12,6 +13,7 @@ use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
     WindowPoStVerifyInfo,
 };
+pub use fvm_shared::sys::SendFlags;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{actor, ActorID, MethodNum};
 
@@ -31,6 +33,21 @@ pub enum SendResult {
     Abort(ExitCode),
 }
 
+/// Options controlling how a send invokes its recipient. The default gives the recipient all the
+/// remaining gas, doesn't hint at a return codec, and sets no flags.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SendOptions {
+    /// The most gas the recipient may use. If it runs out, the send aborts with `SYS_OUT_OF_GAS`
+    /// and the caller keeps the rest of its gas.
+    pub gas_limit: Option<Gas>,
+    /// The codec the caller expects the return value in, exposed to the recipient as a hint. It
+    /// isn't enforced.
+    pub return_codec: Option<u64>,
+    /// Flags controlling how the recipient is invoked; see [`SendFlags`]. In read-only mode, sends
+    /// may not transfer value.
+    pub flags: SendFlags,
+}
+
 /// The "kernel" implements the FVM interface as presented to the actors. It:
 ///
 /// - Manages the Actor's state.
@@ -104,9 +121,33 @@ pub trait MessageOps {
 
     /// The value received from the caller (constant).
     fn msg_value_received(&self) -> TokenAmount;
+
+    /// Reads the raw parameters (constant) into `buf` without opening a block, returning the total
+    /// size of the parameters. Reads at most `buf.len()` bytes, and nothing if there are no
+    /// parameters.
+    fn msg_read_params(&mut self, buf: &mut [u8]) -> Result<u32>;
+
+    /// The codec of the raw parameters (constant), or zero if there are no parameters.
+    fn msg_params_codec(&self) -> u64;
+
+    /// The nonce of the top-level message that started this call stack (constant).
+    fn msg_origin_nonce(&self) -> u64;
+
+    /// The codec the caller expects the return value in (constant), or `DAG_CBOR` if the caller
+    /// didn't specify one.
+    fn msg_expected_return_codec(&self) -> u64;
 }
 
 /// The IPLD subset of the kernel.
+///
+/// Block handles are local to an invocation: each invocation starts with a fresh block registry
+/// containing only its parameters, and never sees the handles of its caller or callees. Across a
+/// [`send`](SendOps::send):
+///
+/// - the return value of a successful send is registered in the caller under a new handle,
+///   returned by the send;
+/// - nothing is registered in the caller when the send aborts, so blocks created by the callee
+///   are dropped.
 pub trait IpldBlockOps {
     /// Open a block.
     ///
@@ -120,11 +161,20 @@ pub trait IpldBlockOps {
     /// (SPEC_AUDIT).
     fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId>;
 
+    /// Returns the size of the block that [`block_create`](Self::block_create) would create from
+    /// `data`, without creating it. That's the length of the data: only the codec is checked, and
+    /// a flat compute charge applies.
+    ///
+    /// This method will fail if the codec is not allowed.
+    fn block_measure(&mut self, codec: u64, data: &[u8]) -> Result<u32>;
+
     /// Computes a CID for a block.
     ///
     /// This is the only way to add a new block to the "reachable" set.
     ///
-    /// This method will fail if the block handle is invalid.
+    /// This method will fail if the block handle is invalid or, if
+    /// [`strict_block_links`](crate::machine::NetworkConfig::strict_block_links) is enabled, the
+    /// block links to blocks missing from the blockstore.
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid>;
 
     /// Read data from a block.
@@ -136,6 +186,20 @@ pub trait IpldBlockOps {
     ///
     /// This method will fail if the block handle is invalid.
     fn block_stat(&mut self, id: BlockId) -> Result<BlockStat>;
+
+    /// Finds the value at `path` within a DAG-CBOR block, and creates a new block containing just
+    /// that value, with the same codec.
+    ///
+    /// This method will fail if the block handle is invalid, the block isn't DAG-CBOR, or there's
+    /// no value at the path.
+    fn block_read_field(&mut self, id: BlockId, path: &[PathSegment]) -> Result<BlockId>;
+
+    /// Returns whether `data` hashes to the digest in `cid`, using the CID's hash function. The
+    /// CID's codec isn't checked, and the CID must carry the hash function's full digest: truncated
+    /// digests never match.
+    ///
+    /// This method will fail if the CID's hash function isn't supported.
+    fn verify_cid(&mut self, cid: &Cid, data: &[u8]) -> Result<bool>;
 }
 
 /// Actor state access and manipulation.
@@ -152,6 +216,24 @@ pub trait SelfOps: IpldBlockOps {
     /// The balance of the receiver.
     fn current_balance(&self) -> Result<TokenAmount>;
 
+    /// A Blake2b-256 commitment over the receiver's state root, balance, and sequence number,
+    /// computed over the DAG-CBOR encoding of `(state_root, balance, sequence)`.
+    ///
+    /// Unlike the state root alone, this commitment changes whenever the actor's balance or
+    /// nonce changes, making it suitable for proving the full actor to an external verifier.
+    fn state_commitment_with_balance(&mut self) -> Result<[u8; 32]>;
+
+    /// Increments the counter in the given slot of the actor's counter area, returning the new
+    /// value. The counter area is the first field of the actor's state, which must be a DAG-CBOR
+    /// array whose first element is an array of counters (missing counters are zero).
+    ///
+    /// Counters are an opt-in convention: no part of actor state is reserved for them, and actors
+    /// whose state doesn't follow it get an error, or a corrupted state if their first field
+    /// happens to be an array of integers.
+    ///
+    /// This replaces the state root, so the actor must reload its state afterwards.
+    fn bump_counter(&mut self, slot: u64) -> Result<u64>;
+
     /// Deletes the executing actor from the state tree, transferring any balance to beneficiary.
     /// Aborts if the beneficiary does not exist.
     /// May only be called by the actor itself.
@@ -164,11 +246,21 @@ pub trait ActorOps {
     /// Resolves an address of any protocol to an ID address (via the Init actor's table).
     /// This allows resolution of externally-provided SECP, BLS, or actor addresses to the canonical form.
     /// If the argument is an ID address it is returned directly.
-    fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>>;
+    ///
+    /// Resolving any other address charges a fixed amount of gas, even if the resolution is
+    /// cached.
+    fn resolve_address(&mut self, address: &Address) -> Result<Option<ActorID>>;
 
     /// Look up the code CID of an actor.
     fn get_actor_code_cid(&self, id: ActorID) -> Result<Option<Cid>>;
 
+    /// Resolves an address, and returns whether the actor is an account actor.
+    fn is_account(&mut self, address: &Address) -> Result<bool>;
+
+    /// Resolves an address, and returns the actor's nonce (sequence number). Returns 0 if the
+    /// actor doesn't exist or isn't an account actor, as only account actors send messages.
+    fn nonce_of(&mut self, address: &Address) -> Result<u64>;
+
     /// Computes an address for a new actor. The returned address is intended to uniquely refer to
     /// the actor even in the event of a chain re-org (whereas an ID-address might refer to a
     /// different actor after messages are re-ordered).
@@ -192,12 +284,19 @@ pub trait ActorOps {
 
 /// Operations to send messages to other actors.
 pub trait SendOps {
+    /// Sends a message to another actor.
+    ///
+    /// On success, the return value (if any) is registered as a new block. See [`IpldBlockOps`]
+    /// for how blocks are shared across sends.
+    ///
+    /// The `options` control how the recipient is invoked; see [`SendOptions`].
     fn send(
         &mut self,
         recipient: &Address,
         method: u64,
         params: BlockId,
         value: &TokenAmount,
+        options: SendOptions,
     ) -> Result<SendResult>;
 }
 
@@ -212,6 +311,10 @@ pub trait CircSupplyOps {
     /// - pledge collateral locked in storage miner actors (recorded in the storage power actor)
     /// - deal collateral locked by the storage market actor
     fn total_fil_circ_supply(&self) -> Result<TokenAmount>;
+
+    /// Returns the components of the circulating supply, which sum to the total reported by
+    /// [`CircSupplyOps::total_fil_circ_supply`].
+    fn circ_supply_detail(&self) -> Result<CircSupplyDetail>;
 }
 
 /// Operations for explicit gas charging.
@@ -222,6 +325,10 @@ pub trait GasOps {
     /// Returns the remaining gas for the transaction.
     fn gas_available(&self) -> Gas;
 
+    /// Returns the gas the current invocation was granted. At any point, the limit less the gas
+    /// available is the gas the invocation has used so far.
+    fn gas_limit(&self) -> Gas;
+
     /// ChargeGas charges specified amount of `gas` for execution.
     /// `name` provides information about gas charging point.
     fn charge_gas(&mut self, name: &str, compute: Gas) -> Result<()>;
@@ -241,6 +348,9 @@ pub trait CryptoOps {
         plaintext: &[u8],
     ) -> Result<bool>;
 
+    /// Computes the key address (f1 for secp256k1, f3 for BLS) of a public key.
+    fn pubkey_to_address(&mut self, sig_type: SignatureType, pubkey: &[u8]) -> Result<Address>;
+
     /// Hashes input `data_in` using with the specified hash function, writing the output to
     /// `digest_out`, returning the size of the digest written to `digest_out`. If `digest_out` is
     /// to small to fit the entire digest, it will be truncated. If too large, the leftover space
@@ -310,12 +420,22 @@ pub trait RandomnessOps {
     /// Randomness returns a (pseudo)random byte array drawing from the latest
     /// beacon from a given epoch and incorporating requisite entropy.
     /// This randomness is not tied to any fork of the chain, and is unbiasable.
+    ///
+    /// The beacon is selected by epoch, according to the network's beacon schedule.
     fn get_randomness_from_beacon(
         &mut self,
         personalization: i64,
         rand_epoch: ChainEpoch,
         entropy: &[u8],
     ) -> Result<[u8; RANDOMNESS_LENGTH]>;
+
+    /// Returns deterministic pseudo-randomness local to the current invocation, derived from the
+    /// message (its origin and nonce), the receiving actor, the method, and the supplied `counter`.
+    /// The same invocation and counter always produce the same output.
+    ///
+    /// This randomness is entirely predictable by anyone who knows the message and must not be
+    /// used for anything security sensitive.
+    fn get_local_randomness(&mut self, counter: u64) -> Result<[u8; RANDOMNESS_LENGTH]>;
 }
 
 /// Debugging APIs.
@@ -326,7 +446,16 @@ pub trait DebugOps {
     /// Returns whether debug mode is enabled.
     fn debug_enabled(&self) -> bool;
 
+    /// Returns the maximum length, in bytes, of an artifact's name.
+    fn max_artifact_name_len(&self) -> u32;
+
+    /// Returns the maximum size, in bytes, of an artifact.
+    fn max_artifact_size(&self) -> u32;
+
     /// Store an artifact.
     /// Returns error on malformed name, returns Ok and logs the error on system/os errors.
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()>;
+    ///
+    /// Artifacts past the size cap of the message are dropped or rejected with
+    /// `LimitExceeded`, depending on the configured [`ArtifactOverflow`](crate::call_manager::ArtifactOverflow).
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<()>;
 }
@@ -52,15 +52,20 @@ lazy_static::lazy_static! {
 #[cfg(test)]
 mod test {
     use fvm_ipld_blockstore::MemoryBlockstore;
-    use fvm_ipld_encoding::CborStore;
-    use fvm_shared::actor::builtin::Manifest;
+    use fvm_ipld_encoding::{CborStore, RawBytes};
+    use fvm_shared::actor::builtin::{Manifest, Type};
+    use fvm_shared::address::Address;
+    use fvm_shared::error::ExitCode;
+    use fvm_shared::message::Message;
     use fvm_shared::state::StateTreeVersion;
+    use fvm_shared::METHOD_SEND;
     use multihash::Code;
 
     use crate::call_manager::DefaultCallManager;
+    use crate::executor::{ApplyKind, Executor};
     use crate::externs::{Consensus, Externs, Rand};
-    use crate::machine::{DefaultMachine, Engine, NetworkConfig};
-    use crate::state_tree::StateTree;
+    use crate::machine::{DefaultMachine, Engine, Machine, NetworkConfig};
+    use crate::state_tree::{ActorChange, ActorField, ActorState, StateTree};
     use crate::{executor, DefaultKernel};
 
     struct DummyExterns;
@@ -132,4 +137,131 @@ mod test {
             machine,
         ));
     }
+
+    #[test]
+    fn test_default_machine() {
+        let mut machine = DefaultMachine::test_default().unwrap();
+
+        // add a funded account to send from, and another account to send to
+        let account_code = *machine
+            .builtin_actors()
+            .get_by_right(&Type::Account)
+            .unwrap();
+        let state = machine
+            .blockstore()
+            .put_cbor(&(), Code::Blake2b256)
+            .unwrap();
+        for (id, balance) in [(100, 1_000_000_000), (101, 0)] {
+            machine
+                .state_tree_mut()
+                .set_actor(
+                    &Address::new_id(id),
+                    ActorState::new(account_code, state, balance.into(), 0),
+                )
+                .unwrap();
+        }
+
+        let msg = Message {
+            version: 0,
+            from: Address::new_id(100),
+            to: Address::new_id(101),
+            sequence: 0,
+            value: 1000.into(),
+            method_num: METHOD_SEND,
+            params: RawBytes::default(),
+            gas_limit: 10_000_000,
+            gas_fee_cap: 1.into(),
+            gas_premium: 1.into(),
+        };
+        let mut executor =
+            executor::DefaultExecutor::<DefaultKernel<DefaultCallManager<_>>>::new(machine);
+        let ret = executor
+            .execute_message(msg, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(ret.msg_receipt.exit_code, ExitCode::OK);
+
+        let recipient = executor
+            .state_tree()
+            .get_actor(&Address::new_id(101))
+            .unwrap()
+            .unwrap();
+        assert_eq!(recipient.balance, 1000.into());
+    }
+
+    #[test]
+    fn test_diff_state() {
+        let mut machine = DefaultMachine::test_default().unwrap();
+
+        let account_code = *machine
+            .builtin_actors()
+            .get_by_right(&Type::Account)
+            .unwrap();
+        let state = machine
+            .blockstore()
+            .put_cbor(&(), Code::Blake2b256)
+            .unwrap();
+        let genesis = machine.flush().unwrap();
+        for (id, balance) in [(100, 1_000_000), (101, 0)] {
+            machine
+                .state_tree_mut()
+                .set_actor(
+                    &Address::new_id(id),
+                    ActorState::new(account_code, state, balance.into(), 0),
+                )
+                .unwrap();
+        }
+        let before = machine.flush().unwrap();
+
+        let added = machine.diff_state(&genesis, &before).unwrap();
+        assert_eq!(
+            added.iter().map(ActorChange::id).collect::<Vec<_>>(),
+            [100, 101]
+        );
+        assert!(matches!(added[0], ActorChange::Added(_, _)));
+        assert!(machine.diff_state(&before, &before).unwrap().is_empty());
+
+        // send without paying for gas, so only the sender and recipient change
+        let msg = Message {
+            version: 0,
+            from: Address::new_id(100),
+            to: Address::new_id(101),
+            sequence: 0,
+            value: 1000.into(),
+            method_num: METHOD_SEND,
+            params: RawBytes::default(),
+            gas_limit: 10_000_000,
+            gas_fee_cap: 0.into(),
+            gas_premium: 0.into(),
+        };
+        let mut executor =
+            executor::DefaultExecutor::<DefaultKernel<DefaultCallManager<_>>>::new(machine);
+        let ret = executor
+            .execute_message(msg, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(ret.msg_receipt.exit_code, ExitCode::OK);
+        let after = executor.flush().unwrap();
+
+        let changes = executor.diff_state(&before, &after).unwrap();
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| match change {
+                ActorChange::Modified {
+                    id, new, fields, ..
+                } => (*id, new.sequence, new.balance.clone(), fields.clone()),
+                change => panic!("unexpected change: {:?}", change),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    100,
+                    1,
+                    999_000.into(),
+                    vec![ActorField::Sequence, ActorField::Balance]
+                ),
+                (101, 0, 1000.into(), vec![ActorField::Balance]),
+            ]
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use cid::Cid;
 use fvm_shared::actor::builtin::Manifest;
 use fvm_shared::address::Address;
@@ -69,6 +71,31 @@ impl<M: Machine> Machine for Box<M> {
         (**self).flush()
     }
 
+    #[inline(always)]
+    fn discard_buffered_writes(&mut self) -> Result<()> {
+        (**self).discard_buffered_writes()
+    }
+
+    #[inline(always)]
+    fn pending_writes(&self) -> Vec<(Cid, usize)> {
+        (**self).pending_writes()
+    }
+
+    #[inline(always)]
+    fn record_reads(&mut self) -> Result<Cid> {
+        (**self).record_reads()
+    }
+
+    #[inline(always)]
+    fn recording_reads(&self) -> bool {
+        (**self).recording_reads()
+    }
+
+    #[inline(always)]
+    fn take_reads(&mut self) -> BTreeMap<Cid, Vec<u8>> {
+        (**self).take_reads()
+    }
+
     #[inline(always)]
     fn machine_id(&self) -> &str {
         (&**self).machine_id()
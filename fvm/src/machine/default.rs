@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::ops::RangeInclusive;
 
 use anyhow::{anyhow, Context as _};
@@ -13,7 +14,7 @@ use fvm_shared::ActorID;
 use log::debug;
 use num_traits::Signed;
 
-use super::{Engine, Machine, MachineContext};
+use super::{Engine, Machine, MachineContext, MachineExport};
 use crate::blockstore::BufferedBlockstore;
 use crate::externs::Externs;
 #[cfg(feature = "m2-native")]
@@ -37,6 +38,8 @@ pub struct DefaultMachine<B, E> {
     ///
     /// Owned.
     state_tree: StateTree<BufferedBlockstore<B>>,
+    /// The last flushed state-root (initially, the initial state-root).
+    flushed_root: Cid,
     /// Mapping of CIDs to builtin actor types.
     builtin_actors: Manifest,
     /// Somewhat unique ID of the machine consisting of (epoch, randomness)
@@ -92,26 +95,14 @@ where
 
         // Create a new state tree from the supplied root.
         let state_tree = {
-            let bstore = BufferedBlockstore::new(blockstore);
+            let mut bstore = BufferedBlockstore::new(blockstore);
+            if context.check_block_writes {
+                bstore = bstore.with_write_checks();
+            }
             StateTree::new_from_root(bstore, &context.initial_state_root)?
         };
 
-        // Load the built-in actors manifest.
-        let (builtin_actors_cid, manifest_version) = match context.builtin_actors_override {
-            Some(manifest_cid) => {
-                let (version, cid): (u32, Cid) = state_tree
-                    .store()
-                    .get_cbor(&manifest_cid)?
-                    .context("failed to load actor manifest")?;
-                (cid, version)
-            }
-            None => {
-                let (state, _) = SystemActorState::load(&state_tree)?;
-                (state.builtin_actors, 1)
-            }
-        };
-        let builtin_actors =
-            load_manifest(state_tree.store(), &builtin_actors_cid, manifest_version)?;
+        let builtin_actors = Self::load_builtin_actors(context, &state_tree)?;
 
         // Preload any uncached modules.
         // This interface works for now because we know all actor CIDs
@@ -145,6 +136,7 @@ where
             engine: engine.clone(),
             externs,
             state_tree,
+            flushed_root: context.initial_state_root,
             builtin_actors,
             id: format!(
                 "{}-{}",
@@ -153,6 +145,95 @@ where
             ),
         })
     }
+
+    /// Loads the built-in actors manifest, either the override from the context or the one
+    /// referenced by the system actor.
+    fn load_builtin_actors(
+        context: &MachineContext,
+        state_tree: &StateTree<BufferedBlockstore<B>>,
+    ) -> anyhow::Result<Manifest> {
+        let (builtin_actors_cid, manifest_version) = match context.builtin_actors_override {
+            Some(manifest_cid) => {
+                let (version, cid): (u32, Cid) = state_tree
+                    .store()
+                    .get_cbor(&manifest_cid)?
+                    .context("failed to load actor manifest")?;
+                (cid, version)
+            }
+            None => {
+                let (state, _) = SystemActorState::load(state_tree)?;
+                (state.builtin_actors, 1)
+            }
+        };
+        load_manifest(state_tree.store(), &builtin_actors_cid, manifest_version)
+    }
+
+    /// Reconstructs a machine exported with [`Machine::export_state`], at the exported state root
+    /// and with the exported configuration. The blockstore must contain the exported state.
+    pub fn import_state(
+        engine: &Engine,
+        export: &MachineExport,
+        blockstore: B,
+        externs: E,
+    ) -> anyhow::Result<Self> {
+        Self::new(engine, &export.context()?, blockstore, externs)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl DefaultMachine<fvm_ipld_blockstore::MemoryBlockstore, crate::externs::TestExterns> {
+    /// The code of the account actor in machines created with
+    /// [`test_default`](Self::test_default): an empty wasm module, as account actors aren't
+    /// invoked by plain transfers.
+    pub const TEST_ACCOUNT_CODE: &'static [u8] = b"\0asm\x01\0\0\0";
+
+    /// Creates a machine for tests, backed by a
+    /// [`MemoryBlockstore`](fvm_ipld_blockstore::MemoryBlockstore), at epoch 0 of the latest
+    /// supported network version, with actor debugging enabled and
+    /// [`TestExterns`](crate::externs::TestExterns).
+    ///
+    /// The state tree holds only the reward and burnt funds actors, and the built-in actors
+    /// manifest only the account actor, with [`TEST_ACCOUNT_CODE`](Self::TEST_ACCOUNT_CODE).
+    pub fn test_default() -> anyhow::Result<Self> {
+        use fvm_ipld_blockstore::MemoryBlockstore;
+        use fvm_shared::state::StateTreeVersion;
+        use fvm_shared::IPLD_RAW;
+        use multihash::{Code, MultihashDigest};
+        use num_traits::Zero;
+
+        use super::{NetworkConfig, BURNT_FUNDS_ACTOR_ADDR, REWARD_ACTOR_ADDR};
+        use crate::externs::TestExterns;
+
+        let bs = MemoryBlockstore::default();
+        let mut state_tree = StateTree::new(bs, StateTreeVersion::V4)?;
+
+        let account_code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(Self::TEST_ACCOUNT_CODE));
+        state_tree
+            .store()
+            .put_keyed(&account_code, Self::TEST_ACCOUNT_CODE)?;
+        let placeholder_code = state_tree.store().put_cbor(&"reward", Code::Blake2b256)?;
+        let empty_state = state_tree.store().put_cbor(&(), Code::Blake2b256)?;
+        for (addr, code) in [
+            (REWARD_ACTOR_ADDR, placeholder_code),
+            (BURNT_FUNDS_ACTOR_ADDR, account_code),
+        ] {
+            state_tree.set_actor(
+                &addr,
+                ActorState::new(code, empty_state, TokenAmount::zero(), 0),
+            )?;
+        }
+        let root = state_tree.flush()?;
+        let bs = state_tree.into_store();
+
+        let manifest = vec![("account".to_string(), account_code)];
+        let manifest_cid = bs.put_cbor(&manifest, Code::Blake2b256)?;
+        let actors_cid = bs.put_cbor(&(1, manifest_cid), Code::Blake2b256)?;
+
+        let mut config = NetworkConfig::new(NetworkVersion::V16);
+        config.override_actors(actors_cid).enable_actor_debugging();
+        let engine = Engine::new_default((&config).into())?;
+        Self::new(&engine, &config.for_epoch(0, root), bs, TestExterns)
+    }
 }
 
 impl<B, E> Machine for DefaultMachine<B, E>
@@ -199,9 +280,45 @@ where
     fn flush(&mut self) -> Result<Cid> {
         let root = self.state_tree_mut().flush()?;
         self.blockstore().flush(&root).or_fatal()?;
+        self.flushed_root = root;
         Ok(root)
     }
 
+    fn discard_buffered_writes(&mut self) -> Result<()> {
+        self.blockstore().discard();
+        let root = self.flushed_root;
+        self.state_tree.reset(&root)
+    }
+
+    fn pending_writes(&self) -> Vec<(Cid, usize)> {
+        self.blockstore().pending()
+    }
+
+    /// Starts recording reads. Outside of tests, this includes the code of all the built-in
+    /// actors, which is preloaded when a machine is constructed.
+    fn record_reads(&mut self) -> Result<Cid> {
+        let root = self.flush()?;
+        self.blockstore().record_reads();
+        self.state_tree.reset(&root)?;
+        let builtin_actors =
+            Self::load_builtin_actors(&self.context, &self.state_tree).or_fatal()?;
+        #[cfg(not(any(test, feature = "testing")))]
+        for code in builtin_actors.left_values() {
+            self.blockstore().get(code).or_fatal()?;
+        }
+        #[cfg(any(test, feature = "testing"))]
+        let _ = builtin_actors;
+        Ok(root)
+    }
+
+    fn recording_reads(&self) -> bool {
+        self.blockstore().recording_reads()
+    }
+
+    fn take_reads(&mut self) -> BTreeMap<Cid, Vec<u8>> {
+        self.blockstore().take_reads()
+    }
+
     /// Creates an uninitialized actor.
     fn create_actor(&mut self, addr: &Address, act: ActorState) -> Result<ActorID> {
         let state_tree = self.state_tree_mut();
@@ -1,6 +1,7 @@
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Context};
@@ -9,11 +10,11 @@ use fvm_ipld_blockstore::Blockstore;
 use fvm_wasm_instrument::gas_metering::GAS_COUNTER_NAME;
 use fvm_wasm_instrument::parity_wasm::elements;
 use wasmtime::OptLevel::Speed;
-use wasmtime::{Global, GlobalType, Linker, Memory, MemoryType, Module, Mutability, Val, ValType};
+use wasmtime::{Linker, Memory, MemoryType, Module, ResourceLimiter};
 
-use crate::gas::WasmGasPrices;
+use crate::gas::{Gas, WasmGasPrices};
 use crate::machine::NetworkConfig;
-use crate::syscalls::{bind_syscalls, InvocationData};
+use crate::syscalls::{bind_syscalls, GasGlobal, InvocationData};
 use crate::Kernel;
 
 /// A caching wasmtime engine.
@@ -28,6 +29,8 @@ pub struct MultiEngine(Arc<Mutex<HashMap<EngineConfig, Engine>>>);
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub struct EngineConfig {
     pub max_wasm_stack: u32,
+    pub max_table_elements: u32,
+    pub table_element_cost: Gas,
     pub wasm_prices: &'static WasmGasPrices,
     pub actor_redirect: Vec<(Cid, Cid)>,
 }
@@ -36,6 +39,8 @@ impl From<&NetworkConfig> for EngineConfig {
     fn from(nc: &NetworkConfig) -> Self {
         EngineConfig {
             max_wasm_stack: nc.max_wasm_stack,
+            max_table_elements: nc.max_table_elements,
+            table_element_cost: nc.table_element_cost,
             wasm_prices: &nc.price_list.wasm_rules,
             actor_redirect: nc.actor_redirect.clone(),
         }
@@ -154,10 +159,16 @@ struct EngineInner {
     /// `InvocationData` before we can make the store.
     ///
     /// Alternatively, we could use `Option`s. But then we need to unwrap everywhere.
-    dummy_gas_global: Global,
+    dummy_gas_global: GasGlobal,
     dummy_memory: Memory,
 
     module_cache: Mutex<HashMap<Cid, Module>>,
+    /// The size of the code each cached module was loaded from.
+    code_size_cache: Mutex<HashMap<Cid, usize>>,
+    /// The number of wasm modules compiled by this engine.
+    compilations: AtomicU64,
+    /// Code that failed to compile when loaded into this engine.
+    invalid_code: Mutex<HashSet<Cid>>,
     instance_cache: Mutex<anymap::Map<dyn anymap::any::Any + Send>>,
     config: EngineConfig,
 
@@ -182,9 +193,7 @@ impl Engine {
         let engine = wasmtime::Engine::new(c)?;
 
         let mut dummy_store = wasmtime::Store::new(&engine, ());
-        let gg_type = GlobalType::new(ValType::I64, Mutability::Var);
-        let dummy_gg = Global::new(&mut dummy_store, gg_type, Val::I64(0))
-            .expect("failed to create dummy gas global");
+        let dummy_gg = GasGlobal::new(&mut dummy_store).expect("failed to create dummy gas global");
 
         let dummy_memory = Memory::new(&mut dummy_store, MemoryType::new(0, Some(0)))
             .expect("failed to create dummy memory");
@@ -196,6 +205,9 @@ impl Engine {
             dummy_memory,
             dummy_gas_global: dummy_gg,
             module_cache: Default::default(),
+            code_size_cache: Default::default(),
+            compilations: Default::default(),
+            invalid_code: Default::default(),
             instance_cache: Mutex::new(anymap::Map::new()),
             config: ec,
             actor_redirect,
@@ -229,8 +241,15 @@ impl Engine {
                     &cid.to_string()
                 )
             })?;
-            let module = self.load_raw(wasm.as_slice())?;
+            let module = match self.load_raw(wasm.as_slice()) {
+                Ok(module) => module,
+                Err(e) => {
+                    self.mark_invalid(cid);
+                    return Err(e);
+                }
+            };
             cache.insert(*cid, module);
+            self.cache_code_size(cid, wasm.len());
         }
         Ok(())
     }
@@ -249,8 +268,15 @@ impl Engine {
         let module = match cache.get(k) {
             Some(module) => module.clone(),
             None => {
-                let module = self.load_raw(wasm)?;
+                let module = match self.load_raw(wasm) {
+                    Ok(module) => module,
+                    Err(e) => {
+                        self.mark_invalid(k);
+                        return Err(e);
+                    }
+                };
                 cache.insert(*k, module.clone());
+                self.cache_code_size(k, wasm.len());
                 module
             }
         };
@@ -294,6 +320,7 @@ impl Engine {
 
         let wasm = m.to_bytes()?;
         let module = Module::from_binary(&self.0.engine, wasm.as_slice())?;
+        self.0.compilations.fetch_add(1, Ordering::Relaxed);
 
         Ok(module)
     }
@@ -328,6 +355,50 @@ impl Engine {
             .cloned()
     }
 
+    /// Lookup the length of the wasm bytecode a loaded module was compiled from. This is unknown
+    /// for modules loaded with [`Engine::load_compiled`].
+    pub fn get_code_size(&self, k: &Cid) -> Option<usize> {
+        let k = self.with_redirect(k);
+        self.0
+            .code_size_cache
+            .lock()
+            .expect("code_size_cache poisoned")
+            .get(k)
+            .copied()
+    }
+
+    /// Returns the number of wasm modules this engine has compiled from bytecode. Modules loaded
+    /// with [`Engine::load_compiled`] aren't counted.
+    pub fn compilations(&self) -> u64 {
+        self.0.compilations.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the given code failed to compile when it was loaded into this engine.
+    pub fn is_invalid_code(&self, k: &Cid) -> bool {
+        let k = self.with_redirect(k);
+        self.0
+            .invalid_code
+            .lock()
+            .expect("invalid_code poisoned")
+            .contains(k)
+    }
+
+    fn mark_invalid(&self, k: &Cid) {
+        self.0
+            .invalid_code
+            .lock()
+            .expect("invalid_code poisoned")
+            .insert(*k);
+    }
+
+    fn cache_code_size(&self, k: &Cid, size: usize) {
+        self.0
+            .code_size_cache
+            .lock()
+            .expect("code_size_cache poisoned")
+            .insert(*k, size);
+    }
+
     /// Lookup and instantiate a loaded wasmtime module with the given store. This will cache the
     /// linker, syscalls, "pre" isntance, etc.
     pub fn get_instance<K: Kernel>(
@@ -335,7 +406,19 @@ impl Engine {
         store: &mut wasmtime::Store<InvocationData<K>>,
         k: &Cid,
     ) -> anyhow::Result<Option<wasmtime::Instance>> {
-        let k = self.with_redirect(k);
+        match self.get_module(k) {
+            Some(module) => self.instantiate(store, &module).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Instantiate a loaded wasmtime module with the given store. This will cache the linker,
+    /// syscalls, etc.
+    pub fn instantiate<K: Kernel>(
+        &self,
+        store: &mut wasmtime::Store<InvocationData<K>>,
+        module: &Module,
+    ) -> anyhow::Result<wasmtime::Instance> {
         let mut instance_cache = self.0.instance_cache.lock().expect("cache poisoned");
 
         let cache = match instance_cache.entry() {
@@ -352,14 +435,9 @@ impl Engine {
             .linker
             .define("gas", GAS_COUNTER_NAME, store.data_mut().avail_gas_global)?;
 
-        let module_cache = self.0.module_cache.lock().expect("module_cache poisoned");
-        let module = match module_cache.get(k) {
-            Some(module) => module,
-            None => return Ok(None),
-        };
         let instance = cache.linker.instantiate(&mut *store, module)?;
 
-        Ok(Some(instance))
+        Ok(instance)
     }
 
     /// Construct a new wasmtime "store" from the given kernel.
@@ -368,14 +446,16 @@ impl Engine {
             kernel,
             last_error: None,
             avail_gas_global: self.0.dummy_gas_global,
-            last_milligas_available: 0,
+            last_gas_available: Gas::new(0),
             memory: self.0.dummy_memory,
+            max_table_elements: self.0.config.max_table_elements,
+            table_element_cost: self.0.config.table_element_cost,
+            limiter_abort: None,
         };
 
         let mut store = wasmtime::Store::new(&self.0.engine, id);
-        let ggtype = GlobalType::new(ValType::I64, Mutability::Var);
-        let gg = Global::new(&mut store, ggtype, Val::I64(0))
-            .expect("failed to create available_gas global");
+        store.limiter(|data| data as &mut dyn ResourceLimiter);
+        let gg = GasGlobal::new(&mut store).expect("failed to create available_gas global");
         store.data_mut().avail_gas_global = gg;
 
         store
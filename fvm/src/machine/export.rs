@@ -0,0 +1,155 @@
+use std::convert::TryFrom;
+
+use anyhow::anyhow;
+use cid::Cid;
+use fvm_ipld_encoding::tuple::{Deserialize_tuple, Serialize_tuple};
+use fvm_shared::bigint::bigint_ser;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::{CircSupplyDetail, TokenAmount};
+use fvm_shared::error::ExitCode;
+use fvm_shared::version::NetworkVersion;
+
+use super::{MachineContext, NetworkConfig};
+use crate::call_manager::{ArtifactOverflow, FailedSubcallGasPolicy};
+use crate::externs::RandomnessFallback;
+use crate::gas::{Gas, ScalingCost};
+
+/// A serializable snapshot of a machine: its state root and configuration, as returned by
+/// [`Machine::export_state`](super::Machine::export_state). A machine may be reconstructed from it
+/// with [`DefaultMachine::import_state`](super::DefaultMachine::import_state).
+///
+/// Only the configuration is exported, not the machine's caches, which are rebuilt lazily. Some of
+/// the configuration can't be exported:
+///
+/// - the price list is always the default price list for the network version;
+/// - the state write observer and event sender aren't set.
+#[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, PartialEq)]
+pub struct MachineExport {
+    /// The state root, from which the imported machine starts executing.
+    pub state_root: Cid,
+    pub epoch: ChainEpoch,
+    #[serde(with = "bigint_ser")]
+    pub base_fee: TokenAmount,
+    #[serde(with = "bigint_ser")]
+    pub circ_supply: TokenAmount,
+    pub circ_supply_detail: Option<CircSupplyDetail>,
+    pub tracing: bool,
+    pub check_block_writes: bool,
+    pub gas_profiling: bool,
+    pub network_version: u32,
+    pub max_call_depth: u32,
+    pub max_self_recursion_depth: u32,
+    pub max_sends_per_message: u64,
+    pub max_send_params_size: u32,
+    pub send_param_codecs: Option<Vec<u64>>,
+    pub max_wasm_stack: u32,
+    pub max_table_elements: u32,
+    /// The gas charged per wasm table element, in milligas.
+    pub table_element_cost: i64,
+    pub builtin_actors_override: Option<Cid>,
+    pub actor_debugging: bool,
+    pub max_artifact_name_len: u32,
+    pub max_artifact_size: u32,
+    pub max_artifact_bytes_per_message: u64,
+    pub artifact_overflow: ArtifactOverflow,
+    pub actor_redirect: Vec<(Cid, Cid)>,
+    pub dedup_block_create: bool,
+    /// The flat and scaling instantiation costs, in milligas.
+    pub actor_instantiation_cost: Option<(i64, i64)>,
+    pub strict_cbor: bool,
+    pub strict_block_links: bool,
+    pub max_cbor_depth: u32,
+    pub exit_code_remap: Vec<(ExitCode, ExitCode)>,
+    pub failed_subcall_gas_policy: FailedSubcallGasPolicy,
+    pub beacon_schedule: Vec<(ChainEpoch, u64)>,
+    pub randomness_fallback: RandomnessFallback,
+}
+
+impl MachineExport {
+    /// Exports the given context, replacing its initial state root with `state_root`.
+    pub fn new(context: &MachineContext, state_root: Cid) -> Self {
+        MachineExport {
+            state_root,
+            epoch: context.epoch,
+            base_fee: context.base_fee.clone(),
+            circ_supply: context.circ_supply.clone(),
+            circ_supply_detail: context.circ_supply_detail.clone(),
+            tracing: context.tracing,
+            check_block_writes: context.check_block_writes,
+            gas_profiling: context.gas_profiling,
+            network_version: context.network_version as u32,
+            max_call_depth: context.max_call_depth,
+            max_self_recursion_depth: context.max_self_recursion_depth,
+            max_sends_per_message: context.max_sends_per_message,
+            max_send_params_size: context.max_send_params_size,
+            send_param_codecs: context.send_param_codecs.clone(),
+            max_wasm_stack: context.max_wasm_stack,
+            max_table_elements: context.max_table_elements,
+            table_element_cost: context.table_element_cost.as_milligas(),
+            builtin_actors_override: context.builtin_actors_override,
+            actor_debugging: context.actor_debugging,
+            max_artifact_name_len: context.max_artifact_name_len,
+            max_artifact_size: context.max_artifact_size,
+            max_artifact_bytes_per_message: context.max_artifact_bytes_per_message,
+            artifact_overflow: context.artifact_overflow,
+            actor_redirect: context.actor_redirect.clone(),
+            dedup_block_create: context.dedup_block_create,
+            actor_instantiation_cost: context
+                .actor_instantiation_cost
+                .map(|cost| (cost.flat.as_milligas(), cost.scale.as_milligas())),
+            strict_cbor: context.strict_cbor,
+            strict_block_links: context.strict_block_links,
+            max_cbor_depth: context.max_cbor_depth,
+            exit_code_remap: context.exit_code_remap.clone(),
+            failed_subcall_gas_policy: context.failed_subcall_gas_policy,
+            beacon_schedule: context.beacon_schedule.clone(),
+            randomness_fallback: context.randomness_fallback,
+        }
+    }
+
+    /// Reconstructs the machine context, starting at the exported state root.
+    pub fn context(&self) -> anyhow::Result<MachineContext> {
+        let network_version = NetworkVersion::try_from(self.network_version)
+            .map_err(|v| anyhow!("unknown network version: {}", v))?;
+
+        let mut network = NetworkConfig::new(network_version);
+        network.max_call_depth = self.max_call_depth;
+        network.max_self_recursion_depth = self.max_self_recursion_depth;
+        network.max_sends_per_message = self.max_sends_per_message;
+        network.max_send_params_size = self.max_send_params_size;
+        network.send_param_codecs = self.send_param_codecs.clone();
+        network.max_wasm_stack = self.max_wasm_stack;
+        network.max_table_elements = self.max_table_elements;
+        network.table_element_cost = Gas::from_milligas(self.table_element_cost);
+        network.builtin_actors_override = self.builtin_actors_override;
+        network.actor_debugging = self.actor_debugging;
+        network.max_artifact_name_len = self.max_artifact_name_len;
+        network.max_artifact_size = self.max_artifact_size;
+        network.max_artifact_bytes_per_message = self.max_artifact_bytes_per_message;
+        network.artifact_overflow = self.artifact_overflow;
+        network.actor_redirect = self.actor_redirect.clone();
+        network.dedup_block_create = self.dedup_block_create;
+        network.actor_instantiation_cost =
+            self.actor_instantiation_cost
+                .map(|(flat, scale)| ScalingCost {
+                    flat: Gas::from_milligas(flat),
+                    scale: Gas::from_milligas(scale),
+                });
+        network.strict_cbor = self.strict_cbor;
+        network.strict_block_links = self.strict_block_links;
+        network.max_cbor_depth = self.max_cbor_depth;
+        network.exit_code_remap = self.exit_code_remap.clone();
+        network.failed_subcall_gas_policy = self.failed_subcall_gas_policy;
+        network.beacon_schedule = self.beacon_schedule.clone();
+        network.randomness_fallback = self.randomness_fallback;
+
+        let mut context = network.for_epoch(self.epoch, self.state_root);
+        context.base_fee = self.base_fee.clone();
+        context.circ_supply = self.circ_supply.clone();
+        context.circ_supply_detail = self.circ_supply_detail.clone();
+        context.tracing = self.tracing;
+        context.check_block_writes = self.check_block_writes;
+        context.gas_profiling = self.gas_profiling;
+        Ok(context)
+    }
+}
@@ -1,18 +1,24 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_shared::actor::builtin::Manifest;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
-use fvm_shared::econ::TokenAmount;
+use fvm_shared::econ::{CircSupplyDetail, TokenAmount};
+use fvm_shared::error::ExitCode;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::ActorID;
 use num_traits::Zero;
 
-use crate::externs::Externs;
-use crate::gas::{price_list_by_network_version, PriceList};
-use crate::kernel::Result;
-use crate::state_tree::{ActorState, StateTree};
+use crate::call_manager::{ArtifactOverflow, FailedSubcallGasPolicy, StateWriteObserver};
+use crate::externs::{Externs, RandomnessFallback};
+use crate::gas::{price_list_by_network_version, Gas, PriceList, ScalingCost};
+use crate::kernel::{ClassifyResult, Result};
+use crate::state_tree::{ActorChange, ActorState, StateTree};
+use crate::trace::EventSender;
 
 mod default;
 
@@ -24,6 +30,10 @@ pub use engine::{Engine, EngineConfig, MultiEngine};
 
 mod boxed;
 
+mod export;
+
+pub use export::MachineExport;
+
 pub const REWARD_ACTOR_ADDR: Address = Address::new_id(2);
 
 /// Distinguished Account actor that is the destination of all burnt funds.
@@ -81,11 +91,58 @@ pub trait Machine: 'static {
         self.state_tree_mut().flush()
     }
 
+    /// Discards all buffered (unflushed) writes, reverting the state-tree to the last flushed
+    /// state-root (or the initial state-root if the machine has never been flushed). This can be
+    /// used to reclaim memory after abandoning a speculative computation. Blocks that have already
+    /// been flushed are unaffected.
+    fn discard_buffered_writes(&mut self) -> Result<()>;
+
+    /// Returns the CIDs and sizes of the blocks written since the last flush, sorted by CID. This
+    /// doesn't flush anything, and is intended for debugging memory growth and flush issues.
+    fn pending_writes(&self) -> Vec<(Cid, usize)>;
+
+    /// Starts recording the blocks read from the underlying blockstore, e.g. to build a witness
+    /// for a message, discarding any previous recording. The state-tree is flushed first, and
+    /// cached state is dropped so that all state used from here on is read (and recorded) again.
+    /// The blocks needed to construct a machine at the flushed state-root, which is returned, are
+    /// recorded immediately.
+    fn record_reads(&mut self) -> Result<Cid>;
+
+    /// Returns whether reads are being recorded (see [`record_reads`](Self::record_reads)).
+    fn recording_reads(&self) -> bool;
+
+    /// Stops recording reads, returning the blocks read from the underlying blockstore since
+    /// [`record_reads`](Self::record_reads), or an empty map if reads weren't being recorded.
+    fn take_reads(&mut self) -> BTreeMap<Cid, Vec<u8>>;
+
     /// Consumes the machine and returns the owned blockstore.
     fn into_store(self) -> Self::Blockstore;
 
+    /// Returns the actors that changed between two state roots, which must both be in the
+    /// machine's blockstore (e.g., flushed before and after applying a message). See
+    /// [`diff_state`](crate::state_tree::diff_state).
+    fn diff_state(&self, old_root: &Cid, new_root: &Cid) -> Result<Vec<ActorChange>> {
+        crate::state_tree::diff_state(self.blockstore(), old_root, new_root)
+    }
+
+    /// Compiles the wasm modules for the given actor code CIDs and caches them in the engine.
+    /// Modules are never compiled during a message, so code must be loaded this way (or with
+    /// [`Engine::preload`]) before it's sent to. Code that's already cached is skipped. Fails if
+    /// any of the code is missing from the blockstore or fails to compile; code that fails to
+    /// compile is remembered, and sends to it fail with `SYS_INVALID_METHOD`.
+    fn precompile(&self, code: &[Cid]) -> Result<()> {
+        self.engine().preload(self.blockstore(), code).or_fatal()
+    }
+
     /// Returns a generated ID of a machine
     fn machine_id(&self) -> &str;
+
+    /// Flushes the state-tree and exports the new state root along with the machine's
+    /// configuration, e.g. to persist a machine to disk. See [`MachineExport`].
+    fn export_state(&mut self) -> Result<MachineExport> {
+        let root = self.flush()?;
+        Ok(MachineExport::new(self.context(), root))
+    }
 }
 
 /// Network-level settings. Except when testing locally, changing any of these likely requires a
@@ -100,10 +157,50 @@ pub struct NetworkConfig {
     /// DEFAULT: 4096
     pub max_call_depth: u32,
 
+    /// The maximum number of consecutive sends an actor may make to itself (A → A → A ...).
+    /// Re-entering an actor through other actors (A → B → A) doesn't count. Sends beyond the limit
+    /// fail with `LimitExceeded`, independently of the
+    /// [`max_call_depth`](NetworkConfig::max_call_depth).
+    ///
+    /// DEFAULT: 1024 (no tighter than the call depth limit)
+    pub max_self_recursion_depth: u32,
+
+    /// The maximum number of sends a message may make, across all the actors it invokes. The
+    /// message itself isn't counted, so a limit of 0 still lets the message be sent.
+    ///
+    /// DEFAULT: 1,000,000
+    pub max_sends_per_message: u64,
+
+    /// The maximum size, in bytes, of the parameters an actor may pass when sending to another
+    /// actor. Larger parameters are rejected before the recipient is invoked.
+    ///
+    /// DEFAULT: 1MiB (the maximum size of an IPLD block)
+    pub max_send_params_size: u32,
+
+    /// The codecs of the parameters an actor may pass when sending to another actor, if
+    /// restricted. Parameters with other codecs are rejected with `IllegalCodec` before the
+    /// recipient is invoked. Sends without parameters are always allowed.
+    ///
+    /// DEFAULT: `None` (any codec is allowed)
+    pub send_param_codecs: Option<Vec<u64>>,
+
     /// The maximum number of elements on wasm stack
     /// DEFAULT: 64Ki (512KiB of u64 elements)
     pub max_wasm_stack: u32,
 
+    /// The maximum number of elements in an actor's wasm table. Instantiating an actor with a
+    /// larger table, or growing a table past this size, aborts the actor with
+    /// `SYS_ILLEGAL_INSTRUCTION`.
+    ///
+    /// DEFAULT: 64Ki elements
+    pub max_table_elements: u32,
+
+    /// The gas charged per element added to an actor's wasm tables, including the elements
+    /// allocated when the actor is instantiated.
+    ///
+    /// DEFAULT: 0 (table growth is free)
+    pub table_element_cost: Gas,
+
     /// An override for builtin-actors. If specified, this should be the CID of a builtin-actors
     /// "manifest".
     ///
@@ -115,6 +212,28 @@ pub struct NetworkConfig {
     /// DEFAULT: `false`
     pub actor_debugging: bool,
 
+    /// The maximum length, in bytes, of the name of a debug artifact stored by an actor.
+    ///
+    /// DEFAULT: 256 bytes
+    pub max_artifact_name_len: u32,
+
+    /// The maximum size, in bytes, of a debug artifact stored by an actor.
+    ///
+    /// DEFAULT: 16MiB
+    pub max_artifact_size: u32,
+
+    /// The maximum total size, in bytes, of the debug artifacts stored while executing a single
+    /// message. The total starts over with each message. Artifacts past the cap are handled
+    /// according to `artifact_overflow`.
+    ///
+    /// DEFAULT: 256MiB
+    pub max_artifact_bytes_per_message: u64,
+
+    /// What happens to debug artifacts stored past `max_artifact_bytes_per_message`.
+    ///
+    /// DEFAULT: [`ArtifactOverflow::Drop`]
+    pub artifact_overflow: ArtifactOverflow,
+
     /// The price list.
     ///
     /// DEFAULT: The price-list for the current network version.
@@ -122,6 +241,64 @@ pub struct NetworkConfig {
 
     /// Actor redirects for debug execution
     pub actor_redirect: Vec<(Cid, Cid)>,
+
+    /// Return the existing handle when an actor creates a block with the same codec and content
+    /// as a block it already created, instead of allocating a new handle.
+    ///
+    /// DEFAULT: `false`
+    pub dedup_block_create: bool,
+
+    /// Overrides the gas charged for instantiating an actor's module, scaled by the size of the
+    /// actor's code in bytes. This is charged on every send that invokes an actor.
+    ///
+    /// DEFAULT: `None` (use the instantiation cost from the price list)
+    pub actor_instantiation_cost: Option<ScalingCost>,
+
+    /// Reject DAG-CBOR blocks that aren't canonically encoded when actors open them, to avoid
+    /// malleable encodings.
+    ///
+    /// DEFAULT: `false`
+    pub strict_cbor: bool,
+
+    /// Reject DAG-CBOR blocks linked by actors if they link to blocks that aren't in the
+    /// blockstore, so that the store always holds the full DAG under every linked block. Links
+    /// with identity hashes are checked recursively, as their content is inline. Every other link
+    /// is charged as a block open.
+    ///
+    /// DEFAULT: `false`
+    pub strict_block_links: bool,
+
+    /// The maximum nesting depth of the DAG-CBOR blocks opened by actors and the DAG-CBOR
+    /// parameters they send, counting each array, map and tag as a level. Deeper blocks are
+    /// rejected, as decoding them could exhaust the stack.
+    ///
+    /// DEFAULT: 256
+    pub max_cbor_depth: u32,
+
+    /// Exit codes to replace in message receipts, as `(internal, custom)` pairs, for chains whose
+    /// actors number exit codes differently. Only receipts are affected: actors and the FVM still
+    /// see the internal exit codes during execution.
+    ///
+    /// DEFAULT: empty (exit codes are unchanged)
+    pub exit_code_remap: Vec<(ExitCode, ExitCode)>,
+
+    /// How the gas consumed by a failed sub-call is charged to the caller. Only sends made by
+    /// actors are affected, not the top-level message.
+    ///
+    /// DEFAULT: [`FailedSubcallGasPolicy::ChargeAll`]
+    pub failed_subcall_gas_policy: FailedSubcallGasPolicy,
+
+    /// The epochs at which beacon randomness switches to another source (e.g., drand network), as
+    /// `(epoch, source)` pairs sorted by epoch. Randomness for a round is drawn from the source
+    /// of the last switch at or before that round, or from source 0 before the first switch.
+    ///
+    /// DEFAULT: empty (all beacon randomness is drawn from source 0)
+    pub beacon_schedule: Vec<(ChainEpoch, u64)>,
+
+    /// What happens when drawing ticket or beacon randomness from the externs fails.
+    ///
+    /// DEFAULT: [`RandomnessFallback::Propagate`]
+    pub randomness_fallback: RandomnessFallback,
 }
 
 impl NetworkConfig {
@@ -130,11 +307,30 @@ impl NetworkConfig {
         NetworkConfig {
             network_version,
             max_call_depth: 1024,
+            max_self_recursion_depth: 1024,
+            max_sends_per_message: 1_000_000,
+            max_send_params_size: 1 << 20,
+            send_param_codecs: None,
             max_wasm_stack: 2048,
+            max_table_elements: 64 << 10,
+            table_element_cost: Gas::zero(),
             actor_debugging: false,
+            max_artifact_name_len: 256,
+            max_artifact_size: 16 << 20,
+            max_artifact_bytes_per_message: 256 << 20,
+            artifact_overflow: ArtifactOverflow::Drop,
             builtin_actors_override: None,
             price_list: price_list_by_network_version(network_version),
             actor_redirect: vec![],
+            dedup_block_create: false,
+            actor_instantiation_cost: None,
+            strict_cbor: false,
+            strict_block_links: false,
+            max_cbor_depth: 256,
+            exit_code_remap: vec![],
+            failed_subcall_gas_policy: FailedSubcallGasPolicy::ChargeAll,
+            beacon_schedule: vec![],
+            randomness_fallback: RandomnessFallback::Propagate,
         }
     }
 
@@ -158,6 +354,96 @@ impl NetworkConfig {
         self
     }
 
+    /// Enable block deduplication in `block_create`. [`NetworkConfig::dedup_block_create`].
+    pub fn enable_block_create_dedup(&mut self) -> &mut Self {
+        self.dedup_block_create = true;
+        self
+    }
+
+    /// Override the actor instantiation cost. [`NetworkConfig::actor_instantiation_cost`].
+    pub fn override_actor_instantiation_cost(&mut self, cost: ScalingCost) -> &mut Self {
+        self.actor_instantiation_cost = Some(cost);
+        self
+    }
+
+    /// Override the maximum number of elements in an actor's wasm table.
+    /// [`NetworkConfig::max_table_elements`].
+    pub fn override_max_table_elements(&mut self, max: u32) -> &mut Self {
+        self.max_table_elements = max;
+        self
+    }
+
+    /// Override the gas charged per wasm table element. [`NetworkConfig::table_element_cost`].
+    pub fn override_table_element_cost(&mut self, cost: Gas) -> &mut Self {
+        self.table_element_cost = cost;
+        self
+    }
+
+    /// Enable strict CBOR validation in `block_open`. [`NetworkConfig::strict_cbor`].
+    pub fn enable_strict_cbor(&mut self) -> &mut Self {
+        self.strict_cbor = true;
+        self
+    }
+
+    /// Enable link validation in `block_link`. [`NetworkConfig::strict_block_links`].
+    pub fn enable_strict_block_links(&mut self) -> &mut Self {
+        self.strict_block_links = true;
+        self
+    }
+
+    /// Restrict the codecs of the parameters actors may send. [`NetworkConfig::send_param_codecs`].
+    pub fn restrict_send_param_codecs(&mut self, codecs: Vec<u64>) -> &mut Self {
+        self.send_param_codecs = Some(codecs);
+        self
+    }
+
+    /// Set the exit codes to replace in message receipts. [`NetworkConfig::exit_code_remap`].
+    pub fn remap_exit_codes(&mut self, remap: Vec<(ExitCode, ExitCode)>) -> &mut Self {
+        self.exit_code_remap = remap;
+        self
+    }
+
+    /// Override the gas policy for failed sub-calls. [`NetworkConfig::failed_subcall_gas_policy`].
+    pub fn override_failed_subcall_gas_policy(
+        &mut self,
+        policy: FailedSubcallGasPolicy,
+    ) -> &mut Self {
+        self.failed_subcall_gas_policy = policy;
+        self
+    }
+
+    /// Override what happens to debug artifacts stored past the per-message size cap.
+    /// [`NetworkConfig::artifact_overflow`].
+    pub fn override_artifact_overflow(&mut self, overflow: ArtifactOverflow) -> &mut Self {
+        self.artifact_overflow = overflow;
+        self
+    }
+
+    /// Override the beacon sources to draw randomness from by epoch, sorting the switches by
+    /// epoch. [`NetworkConfig::beacon_schedule`].
+    pub fn override_beacon_schedule(&mut self, mut schedule: Vec<(ChainEpoch, u64)>) -> &mut Self {
+        schedule.sort_by_key(|(epoch, _)| *epoch);
+        self.beacon_schedule = schedule;
+        self
+    }
+
+    /// Override what happens when drawing randomness from the externs fails.
+    /// [`NetworkConfig::randomness_fallback`].
+    pub fn override_randomness_fallback(&mut self, fallback: RandomnessFallback) -> &mut Self {
+        self.randomness_fallback = fallback;
+        self
+    }
+
+    /// Returns the beacon source to draw randomness for `round` from, according to the
+    /// [`NetworkConfig::beacon_schedule`].
+    pub fn beacon_source(&self, round: ChainEpoch) -> u64 {
+        self.beacon_schedule
+            .iter()
+            .take_while(|(epoch, _)| *epoch <= round)
+            .last()
+            .map_or(0, |(_, source)| *source)
+    }
+
     /// Create a [`MachineContext`] for a given `epoch` with the specified `initial_state`.
     pub fn for_epoch(&self, epoch: ChainEpoch, initial_state: Cid) -> MachineContext {
         MachineContext {
@@ -166,7 +452,12 @@ impl NetworkConfig {
             initial_state_root: initial_state,
             base_fee: TokenAmount::zero(),
             circ_supply: fvm_shared::TOTAL_FILECOIN.clone(),
+            circ_supply_detail: None,
             tracing: false,
+            check_block_writes: false,
+            gas_profiling: false,
+            state_write_observer: None,
+            event_sender: None,
         }
     }
 }
@@ -197,9 +488,41 @@ pub struct MachineContext {
     /// DEFAULT: Total FIL supply (likely not what you want).
     pub circ_supply: TokenAmount,
 
+    /// The components of the circulating supply, for actors that need the breakdown. When set,
+    /// these components sum to [`MachineContext::circ_supply`].
+    ///
+    /// DEFAULT: `None` (the breakdown isn't available to actors).
+    pub circ_supply_detail: Option<CircSupplyDetail>,
+
     /// Whether or not to produce execution traces in the returned result.
     /// Not consensus-critical, but has a performance impact.
     pub tracing: bool,
+
+    /// Whether or not to reject writes that would replace an existing block with different bytes
+    /// under the same CID. This is a debugging aid for catching non-determinism and serialization
+    /// bugs.
+    ///
+    /// DEFAULT: `false`
+    pub check_block_writes: bool,
+
+    /// Whether or not to record the gas charged under each charge name in the returned result.
+    /// Not consensus-critical, but has a performance impact.
+    ///
+    /// DEFAULT: `false`
+    pub gas_profiling: bool,
+
+    /// An observer notified of committed actor state writes, e.g. for indexing. Not
+    /// consensus-critical.
+    ///
+    /// DEFAULT: `None`
+    pub state_write_observer: Option<Arc<dyn StateWriteObserver>>,
+
+    /// A channel to stream execution events to as they occur, independently of
+    /// [`MachineContext::tracing`]. Unlike the trace, this includes every gas charge. Not consensus-critical, but may slow down execution depending
+    /// on its [`Backpressure`](crate::trace::Backpressure).
+    ///
+    /// DEFAULT: `None`
+    pub event_sender: Option<EventSender>,
 }
 
 impl MachineContext {
@@ -215,9 +538,41 @@ impl MachineContext {
         self
     }
 
+    /// Set [`MachineContext::circ_supply_detail`], along with [`MachineContext::circ_supply`] (the
+    /// sum of the components).
+    pub fn set_circulating_supply_detail(&mut self, detail: CircSupplyDetail) -> &mut Self {
+        self.circ_supply = detail.total();
+        self.circ_supply_detail = Some(detail);
+        self
+    }
+
     /// Enable execution traces. [`MachineContext::tracing`].
     pub fn enable_tracing(&mut self) -> &mut Self {
         self.tracing = true;
         self
     }
+
+    /// Enable block write checks. [`MachineContext::check_block_writes`].
+    pub fn enable_block_write_checks(&mut self) -> &mut Self {
+        self.check_block_writes = true;
+        self
+    }
+
+    /// Enable gas profiling. [`MachineContext::gas_profiling`].
+    pub fn enable_gas_profiling(&mut self) -> &mut Self {
+        self.gas_profiling = true;
+        self
+    }
+
+    /// Set [`MachineContext::state_write_observer`].
+    pub fn set_state_write_observer(&mut self, observer: Arc<dyn StateWriteObserver>) -> &mut Self {
+        self.state_write_observer = Some(observer);
+        self
+    }
+
+    /// Set [`MachineContext::event_sender`].
+    pub fn set_event_sender(&mut self, sender: EventSender) -> &mut Self {
+        self.event_sender = Some(sender);
+        self
+    }
 }
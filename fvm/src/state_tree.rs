@@ -9,7 +9,7 @@ use cid::{multihash, Cid};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::tuple::*;
 use fvm_ipld_encoding::CborStore;
-use fvm_ipld_hamt::Hamt;
+use fvm_ipld_hamt::{BytesKey, Change, Hamt};
 use fvm_shared::address::{Address, Payload};
 use fvm_shared::bigint::bigint_ser;
 use fvm_shared::econ::TokenAmount;
@@ -228,6 +228,41 @@ where
 
     /// Constructor for a hamt state tree given an IPLD store
     pub fn new_from_root(store: S, c: &Cid) -> Result<Self> {
+        let (version, info, actors) = Self::load_root(&store, c)?;
+        let hamt = Hamt::load_with_bit_width(&actors, store, HAMT_BIT_WIDTH)
+            .context("failed to load state tree")
+            .or_fatal()?;
+
+        Ok(Self {
+            hamt,
+            version,
+            info,
+            snaps: StateSnapshots::new(),
+        })
+    }
+
+    /// Resets the state tree to the given root, discarding all unflushed changes.
+    pub fn reset(&mut self, c: &Cid) -> Result<()> {
+        if self.snaps.layers.len() != 1 {
+            return Err(ExecutionError::Fatal(anyhow!(
+                "tried to reset state tree with snapshots on the stack: {:?}",
+                self.snaps.layers.len()
+            )));
+        }
+
+        let (version, info, actors) = Self::load_root(self.store(), c)?;
+        self.hamt
+            .set_root(&actors)
+            .context("failed to load state tree")
+            .or_fatal()?;
+        self.version = version;
+        self.info = info;
+        self.snaps = StateSnapshots::new();
+        Ok(())
+    }
+
+    /// Loads a versioned state root, returning the version, info, and the root of the actors HAMT.
+    fn load_root(store: &S, c: &Cid) -> Result<(StateTreeVersion, Option<Cid>, Cid)> {
         // Try to load state root, if versioned
         let (version, info, actors) = match store.get_cbor(c) {
             Ok(Some(StateRoot {
@@ -251,24 +286,10 @@ where
         };
 
         match version {
-            StateTreeVersion::V0 | StateTreeVersion::V1 | StateTreeVersion::V2 => {
-                return Err(ExecutionError::Fatal(anyhow!(
-                    "unsupported state tree version: {:?}",
-                    version
-                )))
-            }
-            StateTreeVersion::V3 | StateTreeVersion::V4 => {
-                let hamt = Hamt::load_with_bit_width(&actors, store, HAMT_BIT_WIDTH)
-                    .context("failed to load state tree")
-                    .or_fatal()?;
-
-                Ok(Self {
-                    hamt,
-                    version,
-                    info,
-                    snaps: StateSnapshots::new(),
-                })
-            }
+            StateTreeVersion::V0 | StateTreeVersion::V1 | StateTreeVersion::V2 => Err(
+                ExecutionError::Fatal(anyhow!("unsupported state tree version: {:?}", version)),
+            ),
+            StateTreeVersion::V3 | StateTreeVersion::V4 => Ok((version, info, actors)),
         }
     }
 
@@ -553,6 +574,104 @@ impl ActorState {
     }
 }
 
+/// A field of an [`ActorState`], as reported by [`ActorChange::Modified`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ActorField {
+    Code,
+    State,
+    /// The actor's nonce.
+    Sequence,
+    Balance,
+}
+
+/// A change to a single actor between two state trees, as returned by [`diff_state`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActorChange {
+    /// The actor was added, with the given state.
+    Added(ActorID, ActorState),
+    /// The actor was removed. It previously had the given state.
+    Removed(ActorID, ActorState),
+    /// The actor's state was changed from `old` to `new`. `fields` lists the fields that differ.
+    Modified {
+        id: ActorID,
+        old: ActorState,
+        new: ActorState,
+        fields: Vec<ActorField>,
+    },
+}
+
+impl ActorChange {
+    /// Returns the ID of the changed actor.
+    pub fn id(&self) -> ActorID {
+        match self {
+            ActorChange::Added(id, _)
+            | ActorChange::Removed(id, _)
+            | ActorChange::Modified { id, .. } => *id,
+        }
+    }
+}
+
+/// Returns the actors that changed between the state trees rooted at `old_root` and `new_root`,
+/// ordered by ID. Subtrees shared by both state trees are skipped without being loaded, so the
+/// cost is proportional to the size of the change rather than the size of the state.
+pub fn diff_state<S>(store: &S, old_root: &Cid, new_root: &Cid) -> Result<Vec<ActorChange>>
+where
+    S: Blockstore,
+{
+    if old_root == new_root {
+        return Ok(Vec::new());
+    }
+
+    let load = |root| -> Result<Hamt<&S, ActorState>> {
+        let (_, _, actors) = StateTree::load_root(store, root)?;
+        Hamt::load_with_bit_width(&actors, store, HAMT_BIT_WIDTH)
+            .context("failed to load state tree")
+            .or_fatal()
+    };
+    let changes = load(old_root)?
+        .diff(&load(new_root)?)
+        .context("failed to diff state trees")
+        .or_fatal()?;
+
+    let mut actors = changes
+        .into_iter()
+        .map(|change| {
+            let id = |key: &BytesKey| {
+                Address::from_bytes(&key.0)
+                    .ok()
+                    .and_then(|addr| addr.id().ok())
+                    .ok_or_else(|| anyhow!("invalid key in state tree: {:?}", key))
+                    .or_fatal()
+            };
+            Ok(match change {
+                Change::Add(key, state) => ActorChange::Added(id(&key)?, state),
+                Change::Remove(key, state) => ActorChange::Removed(id(&key)?, state),
+                Change::Modify(key, old, new) => {
+                    let fields = [
+                        (ActorField::Code, old.code != new.code),
+                        (ActorField::State, old.state != new.state),
+                        (ActorField::Sequence, old.sequence != new.sequence),
+                        (ActorField::Balance, old.balance != new.balance),
+                    ]
+                    .into_iter()
+                    .filter(|(_, changed)| *changed)
+                    .map(|(field, _)| field)
+                    .collect();
+                    ActorChange::Modified {
+                        id: id(&key)?,
+                        old,
+                        new,
+                        fields,
+                    }
+                }
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    actors.sort_by_key(ActorChange::id);
+    Ok(actors)
+}
+
 #[cfg(feature = "json")]
 pub mod json {
     use std::str::FromStr;
@@ -36,6 +36,30 @@ pub fn get_actor_code_cid(
     context.memory.write_cid(&typ, obuf_off, obuf_len)
 }
 
+/// Returns 0 if the actor at the address is an account actor, or -1 otherwise.
+pub fn is_account(
+    context: Context<'_, impl Kernel>,
+    addr_off: u32, // Address
+    addr_len: u32,
+) -> Result<i32> {
+    let addr = context.memory.read_address(addr_off, addr_len)?;
+    context
+        .kernel
+        .is_account(&addr)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Returns the nonce of the actor at the address, or 0 if it doesn't exist or isn't an account
+/// actor.
+pub fn nonce_of(
+    context: Context<'_, impl Kernel>,
+    addr_off: u32, // Address
+    addr_len: u32,
+) -> Result<u64> {
+    let addr = context.memory.read_address(addr_off, addr_len)?;
+    context.kernel.nonce_of(&addr)
+}
+
 /// Generates a new actor address, and writes it into the supplied output buffer.
 ///
 /// The output buffer must be at least 21 bytes long, which is the length of a
@@ -6,7 +6,7 @@ use wasmtime::{Caller, Linker, WasmTy};
 
 use super::context::Memory;
 use super::error::Abort;
-use super::{charge_for_exec, update_gas_available, Context, InvocationData};
+use super::{charge_for_exec, update_gas_available, Context, GasCheckpoint, InvocationData};
 use crate::call_manager::backtrace;
 use crate::kernel::{self, ExecutionError, Kernel, SyscallError};
 
@@ -121,7 +121,7 @@ macro_rules! impl_bind_syscalls {
                 if mem::size_of::<Ret::Value>() == 0 {
                     // If we're returning a zero-sized "value", we return no value therefore and expect no out pointer.
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>> $(, $t: $t)*| {
-                        charge_for_exec(&mut caller)?;
+                        charge_for_exec(&mut caller, GasCheckpoint::Syscall(module, name))?;
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
@@ -144,14 +144,14 @@ macro_rules! impl_bind_syscalls {
                             Err(e) => Err(e.into()),
                         };
 
-                        update_gas_available(&mut caller)?;
+                        update_gas_available(&mut caller, GasCheckpoint::Syscall(module, name))?;
 
                         result
                     })
                 } else {
                     // If we're returning an actual value, we need to write it back into the wasm module's memory.
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>>, ret: u32 $(, $t: $t)*| {
-                        charge_for_exec(&mut caller)?;
+                        charge_for_exec(&mut caller, GasCheckpoint::Syscall(module, name))?;
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
@@ -181,7 +181,7 @@ macro_rules! impl_bind_syscalls {
                             Err(e) => Err(e.into()),
                         };
 
-                        update_gas_available(&mut caller)?;
+                        update_gas_available(&mut caller, GasCheckpoint::Syscall(module, name))?;
 
                         result
                     })
@@ -199,3 +199,5 @@ impl_bind_syscalls!(A B C D);
 impl_bind_syscalls!(A B C D E);
 impl_bind_syscalls!(A B C D E F);
 impl_bind_syscalls!(A B C D E F G);
+impl_bind_syscalls!(A B C D E F G H);
+impl_bind_syscalls!(A B C D E F G H I);
@@ -16,6 +16,10 @@ pub struct Context<'a, K> {
     pub memory: &'a mut Memory,
 }
 
+/// An actor's linear memory, as seen by syscalls.
+///
+/// Reads borrow directly from the memory instead of copying out of it, so syscalls can read their
+/// arguments (slices, addresses, CIDs, etc.) without allocating.
 #[repr(transparent)]
 pub struct Memory([u8]);
 
@@ -65,6 +69,21 @@ impl Memory {
             .or_error(ErrorNumber::IllegalArgument)
     }
 
+    /// Copies `len` bytes from `src` to `dst` within this memory. The regions may overlap.
+    pub fn copy_within(&mut self, dst: u32, src: u32, len: u32) -> Result<()> {
+        self.check_bounds(src, len)?;
+        self.check_bounds(dst, len)?;
+        let (src, len) = (src as usize, len as usize);
+        self.0.copy_within(src..src + len, dst as usize);
+        Ok(())
+    }
+
+    /// Zeroes `len` bytes starting at `offset`.
+    pub fn zero(&mut self, offset: u32, len: u32) -> Result<()> {
+        self.try_slice_mut(offset, len)?.fill(0);
+        Ok(())
+    }
+
     pub fn read_cid(&self, offset: u32) -> Result<Cid> {
         // NOTE: Be very careful when changing this code.
         //
@@ -185,6 +204,52 @@ mod test {
         expect_syscall_err!(IllegalArgument, mem.try_slice(u32::MAX, 0));
     }
 
+    #[test]
+    fn test_copy_within() {
+        let mut buf: Vec<u8> = (0..16).collect();
+        let mem = Memory::new(&mut buf);
+
+        mem.copy_within(8, 0, 4).expect("copy was in bounds");
+        assert_eq!(&mem[8..12], &[0, 1, 2, 3]);
+
+        // Overlapping, forwards.
+        mem.copy_within(2, 0, 4).expect("copy was in bounds");
+        assert_eq!(&mem[..6], &[0, 1, 0, 1, 2, 3]);
+
+        // Overlapping, backwards.
+        mem.copy_within(0, 1, 4).expect("copy was in bounds");
+        assert_eq!(&mem[..6], &[1, 0, 1, 2, 2, 3]);
+
+        // Zero-length copies at the end of memory are fine.
+        mem.copy_within(16, 16, 0).expect("copy was in bounds");
+    }
+
+    #[test]
+    fn test_copy_within_out_of_bounds() {
+        let mut buf = [7u8; 16];
+        let mem = Memory::new(&mut buf);
+        expect_syscall_err!(IllegalArgument, mem.copy_within(0, 13, 4));
+        expect_syscall_err!(IllegalArgument, mem.copy_within(13, 0, 4));
+        expect_syscall_err!(IllegalArgument, mem.copy_within(0, u32::MAX, 1));
+        assert_eq!(buf, [7u8; 16], "failed copies should not modify memory");
+    }
+
+    #[test]
+    fn test_zero() {
+        let mut buf = [7u8; 16];
+        let mem = Memory::new(&mut buf);
+
+        mem.zero(4, 8).expect("zeroing was in bounds");
+        assert_eq!(&mem[..], &[7, 7, 7, 7, 0, 0, 0, 0, 0, 0, 0, 0, 7, 7, 7, 7]);
+
+        // Zero-length regions at the end of memory are fine.
+        mem.zero(16, 0).expect("zeroing was in bounds");
+
+        expect_syscall_err!(IllegalArgument, mem.zero(13, 4));
+        expect_syscall_err!(IllegalArgument, mem.zero(u32::MAX, 1));
+        assert_eq!(mem[15], 7, "failed zeroing should not modify memory");
+    }
+
     #[test]
     fn test_read_slice_empty() {
         let mem = Memory::new(&mut []);
@@ -43,6 +43,36 @@ pub fn verify_signature(
         .map(|v| if v { 0 } else { -1 })
 }
 
+/// Computes the key address (f1 for secp256k1, f3 for BLS) of a public key, writing the address
+/// into the provided buffer and returning its length.
+pub fn pubkey_to_address(
+    context: Context<'_, impl Kernel>,
+    sig_type: u32,
+    key_off: u32, // input
+    key_len: u32,
+    obuf_off: u32, // Address (out)
+    obuf_len: u32,
+) -> Result<u32> {
+    let sig_type = SignatureType::from_u32(sig_type)
+        .with_context(|| format!("unknown signature type {}", sig_type))
+        .or_illegal_argument()?;
+
+    // Check the output bounds first so we don't do any work if they're incorrect.
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let addr = {
+        let key = context.memory.try_slice(key_off, key_len)?;
+        context.kernel.pubkey_to_address(sig_type, key)?
+    };
+
+    let bytes = addr.to_bytes();
+    if bytes.len() > obuf_len as usize {
+        return Err(syscall_error!(BufferTooSmall; "address output buffer is too small").into());
+    }
+    context.memory.try_slice_mut(obuf_off, obuf_len)?[..bytes.len()].copy_from_slice(&bytes);
+    Ok(bytes.len() as u32)
+}
+
 /// Hashes input data using the specified hash function, writing the digest into the provided
 /// buffer.
 pub fn hash(
@@ -1,6 +1,6 @@
 use crate::kernel::{ClassifyResult, Result};
 use crate::syscalls::context::Context;
-use crate::Kernel;
+use crate::{syscall_error, Kernel};
 
 pub fn log(context: Context<'_, impl Kernel>, msg_off: u32, msg_len: u32) -> Result<()> {
     // No-op if disabled.
@@ -22,6 +22,8 @@ pub fn enabled(context: Context<'_, impl Kernel>) -> Result<i32> {
     })
 }
 
+/// Stores a debug artifact under `name`. The name must not be able to escape the artifact
+/// directory, and both the name and data must be within the network's size limits.
 pub fn store_artifact(
     context: Context<'_, impl Kernel>,
     name_off: u32,
@@ -34,10 +36,29 @@ pub fn store_artifact(
         return Ok(());
     }
 
+    // Check the sizes before touching the actor's memory.
+    let max_name_len = context.kernel.max_artifact_name_len();
+    if name_len > max_name_len {
+        return Err(syscall_error!(IllegalArgument;
+            "debug artifact name too long ({} > {} bytes)", name_len, max_name_len)
+        .into());
+    }
+    let max_size = context.kernel.max_artifact_size();
+    if data_len > max_size {
+        return Err(syscall_error!(IllegalArgument;
+            "debug artifact too large ({} > {} bytes)", data_len, max_size)
+        .into());
+    }
+
     let data = context.memory.try_slice(data_off, data_len)?;
     let name = context.memory.try_slice(name_off, name_len)?;
     let name =
         std::str::from_utf8(name).or_error(fvm_shared::error::ErrorNumber::IllegalArgument)?;
+    if name.contains(['/', '\\']) || name.contains("..") || name.chars().any(char::is_control) {
+        return Err(
+            syscall_error!(IllegalArgument; "invalid debug artifact name {:?}", name).into(),
+        );
+    }
 
     context.kernel.store_artifact(name, data)?;
 
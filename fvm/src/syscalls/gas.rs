@@ -16,3 +16,14 @@ pub fn charge_gas(
     // Gas charges from actors are always in full gas units. We use milligas internally, so convert here.
     context.kernel.charge_gas(name, Gas::new(compute))
 }
+
+/// Returns the gas available to the current message, in milligas. Gas used by wasm execution up to
+/// this syscall has already been charged when it's called.
+pub fn available(context: Context<'_, impl Kernel>) -> Result<u64> {
+    Ok(context.kernel.gas_available().as_milligas().max(0) as u64)
+}
+
+/// Returns the gas the current invocation was granted, in milligas.
+pub fn gas_limit(context: Context<'_, impl Kernel>) -> Result<u64> {
+    Ok(context.kernel.gas_limit().as_milligas().max(0) as u64)
+}
@@ -1,7 +1,9 @@
+use cid::Cid;
+use fvm_ipld_encoding::PathSegment;
 use fvm_shared::sys;
 
 use super::Context;
-use crate::kernel::Result;
+use crate::kernel::{ClassifyResult, Context as _, Result};
 use crate::Kernel;
 
 pub fn block_open(context: Context<'_, impl Kernel>, cid: u32) -> Result<sys::out::ipld::IpldOpen> {
@@ -24,6 +26,36 @@ pub fn block_create(
     context.kernel.block_create(codec, data)
 }
 
+/// Returns the size of the block `block_create` would create from the data, without creating it.
+pub fn block_measure(
+    context: Context<'_, impl Kernel>,
+    codec: u64,
+    data_off: u32,
+    data_len: u32,
+) -> Result<u32> {
+    let data = context.memory.try_slice(data_off, data_len)?;
+    context.kernel.block_measure(codec, data)
+}
+
+/// Returns 0 if the data hashes to the CID's digest (with the CID's hash function), or -1
+/// otherwise.
+pub fn verify_cid(
+    context: Context<'_, impl Kernel>,
+    cid_off: u32,
+    cid_len: u32,
+    data_off: u32,
+    data_len: u32,
+) -> Result<i32> {
+    let cid = Cid::read_bytes(context.memory.try_slice(cid_off, cid_len)?)
+        .or_illegal_argument()
+        .context("failed to parse cid")?;
+    let data = context.memory.try_slice(data_off, data_len)?;
+    context
+        .kernel
+        .verify_cid(&cid, data)
+        .map(|v| if v { 0 } else { -1 })
+}
+
 pub fn block_link(
     context: Context<'_, impl Kernel>,
     id: u32,
@@ -53,6 +85,16 @@ pub fn block_read(
     context.kernel.block_read(id, offset, data)
 }
 
+pub fn block_read_field(
+    context: Context<'_, impl Kernel>,
+    id: u32,
+    path_off: u32,
+    path_len: u32,
+) -> Result<u32> {
+    let path: Vec<PathSegment> = context.memory.read_cbor(path_off, path_len)?;
+    context.kernel.block_read_field(id, &path)
+}
+
 pub fn block_stat(context: Context<'_, impl Kernel>, id: u32) -> Result<sys::out::ipld::IpldStat> {
     context
         .kernel
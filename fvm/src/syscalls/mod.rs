@@ -1,7 +1,10 @@
 use std::mem;
 
-use anyhow::{anyhow, Context as _};
-use wasmtime::{AsContextMut, Global, Linker, Memory, Val};
+use anyhow::anyhow;
+use fvm_shared::error::ExitCode;
+use wasmtime::{
+    AsContextMut, Extern, Global, GlobalType, Linker, Mutability, ResourceLimiter, Val, ValType,
+};
 
 use crate::call_manager::backtrace;
 use crate::gas::Gas;
@@ -23,6 +26,7 @@ mod sself;
 mod vm;
 
 pub(self) use context::Context;
+pub use context::Memory;
 
 /// Invocation data attached to a wasm "store" and available to the syscall binding.
 pub struct InvocationData<K> {
@@ -34,63 +38,184 @@ pub struct InvocationData<K> {
     pub last_error: Option<backtrace::Cause>,
 
     /// The global containing remaining available gas.
-    pub avail_gas_global: Global,
+    pub avail_gas_global: GasGlobal,
 
-    /// The last-set milligas limit. When `charge_for_exec` is called, we charge for the
-    /// _difference_ between the current gas available (the wasm global) and the
-    /// `last_milligas_available`.
-    pub last_milligas_available: i64,
+    /// The last-set gas limit. When `charge_for_exec` is called, we charge for the _difference_
+    /// between the current gas available (the wasm global) and the `last_gas_available`.
+    pub last_gas_available: Gas,
 
     /// The invocation's imported "memory".
-    pub memory: Memory,
+    pub memory: wasmtime::Memory,
+
+    /// The maximum number of elements in the invocation's wasm tables.
+    pub max_table_elements: u32,
+
+    /// The gas charged per element added to the invocation's wasm tables.
+    pub table_element_cost: Gas,
+
+    /// The abort raised when a table failed to grow. The resource limiter can't abort execution
+    /// itself, so this is checked once instantiation or the invocation returns.
+    pub limiter_abort: Option<Abort>,
+}
+
+/// Charges for and limits the growth of the invocation's wasm tables, including the tables
+/// allocated on instantiation. Memory growth isn't limited here.
+impl<K: Kernel> ResourceLimiter for InvocationData<K> {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> bool {
+        true
+    }
+
+    fn table_growing(&mut self, current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+        if desired > self.max_table_elements {
+            self.limiter_abort = Some(Abort::Exit(
+                ExitCode::SYS_ILLEGAL_INSTRUCTION,
+                format!(
+                    "table size {} exceeds the maximum of {} elements",
+                    desired, self.max_table_elements
+                ),
+            ));
+            return false;
+        }
+        let cost = self.table_element_cost * desired.saturating_sub(current) as i64;
+        if let Err(e) = self.kernel.charge_gas("OnTableGrow", cost) {
+            self.limiter_abort = Some(Abort::from_error_as_fatal(e));
+            return false;
+        }
+        true
+    }
+}
+
+/// The wasm global holding the gas available to an actor. The global holds milligas, but is only
+/// read and written as [`Gas`].
+#[derive(Copy, Clone, Debug)]
+pub struct GasGlobal(Global);
+
+impl GasGlobal {
+    /// Creates a new gas global in the store, with no gas available.
+    pub fn new(store: impl AsContextMut) -> anyhow::Result<Self> {
+        let ty = GlobalType::new(ValType::I64, Mutability::Var);
+        Ok(GasGlobal(Global::new(store, ty, Val::I64(0))?))
+    }
+
+    /// Returns the gas available.
+    pub fn get(&self, store: impl AsContextMut) -> anyhow::Result<Gas> {
+        self.0
+            .get(store)
+            .i64()
+            .map(Gas::from_milligas)
+            .ok_or_else(|| anyhow!("gas global is not an i64"))
+    }
+
+    /// Sets the gas available.
+    pub fn set(&self, store: impl AsContextMut, gas: Gas) -> anyhow::Result<()> {
+        self.0.set(store, Val::I64(gas.as_milligas()))
+    }
+}
+
+/// Wraps an existing global. Getting or setting the gas will fail unless the global is a mutable
+/// i64.
+impl From<Global> for GasGlobal {
+    fn from(global: Global) -> Self {
+        GasGlobal(global)
+    }
+}
+
+impl From<GasGlobal> for Extern {
+    fn from(global: GasGlobal) -> Self {
+        global.0.into()
+    }
+}
+
+/// The point at which the wasm gas global is being synchronized with the FVM's gas tracker. This
+/// is used to add context to errors.
+#[derive(Copy, Clone, Debug)]
+pub enum GasCheckpoint {
+    /// Entering or leaving the actor's `invoke` method.
+    Invoke,
+    /// Entering or leaving a syscall, by module and name.
+    Syscall(&'static str, &'static str),
+}
+
+impl std::fmt::Display for GasCheckpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasCheckpoint::Invoke => f.write_str("invoke"),
+            GasCheckpoint::Syscall(module, name) => write!(f, "syscall {}::{}", module, name),
+        }
+    }
 }
 
 pub fn update_gas_available(
     ctx: &mut impl AsContextMut<Data = InvocationData<impl Kernel>>,
+    checkpoint: GasCheckpoint,
 ) -> Result<(), Abort> {
     let mut ctx = ctx.as_context_mut();
-    let avail_milligas = ctx.data_mut().kernel.gas_available().as_milligas();
+    let avail_gas = ctx.data_mut().kernel.gas_available();
 
     let gas_global = ctx.data_mut().avail_gas_global;
-    gas_global
-        .set(&mut ctx, Val::I64(avail_milligas))
-        .map_err(|e| Abort::Fatal(anyhow!("failed to set available gas global: {}", e)))?;
+    gas_global.set(&mut ctx, avail_gas).map_err(|e| {
+        Abort::Fatal(anyhow!(
+            "failed to set available gas global (actor {}, {}): {}",
+            ctx.data().kernel.msg_receiver(),
+            checkpoint,
+            e
+        ))
+    })?;
 
-    ctx.data_mut().last_milligas_available = avail_milligas;
+    ctx.data_mut().last_gas_available = avail_gas;
     Ok(())
 }
 
 /// Updates the FVM-side gas tracker with newly accrued execution gas charges.
 pub fn charge_for_exec(
     ctx: &mut impl AsContextMut<Data = InvocationData<impl Kernel>>,
+    checkpoint: GasCheckpoint,
 ) -> Result<(), Abort> {
     let mut ctx = ctx.as_context_mut();
     let global = ctx.data_mut().avail_gas_global;
 
-    let milligas_available = global
-        .get(&mut ctx)
-        .i64()
-        .context("failed to get wasm gas")
-        .map_err(Abort::Fatal)?;
+    let gas_available = global.get(&mut ctx).map_err(|e| {
+        Abort::Fatal(anyhow!(
+            "failed to get wasm gas (actor {}, {}): {}",
+            ctx.data().kernel.msg_receiver(),
+            checkpoint,
+            e
+        ))
+    })?;
 
-    // Determine milligas used, and update the gas tracker.
-    let milligas_used = {
+    // Determine gas used, and update the gas tracker.
+    let gas_used = {
         let data = ctx.data_mut();
-        let last_milligas = mem::replace(&mut data.last_milligas_available, milligas_available);
-        // This should never be negative, but we might as well check.
-        last_milligas.saturating_sub(milligas_available)
+        let last_gas = mem::replace(&mut data.last_gas_available, gas_available);
+        // Executing wasm only ever consumes gas, so more gas being available means the global was
+        // corrupted. Fail rather than silently charging nothing.
+        if gas_available > last_gas {
+            return Err(Abort::Fatal(anyhow!(
+                "available gas increased from {} to {} (actor {}, {})",
+                last_gas,
+                gas_available,
+                data.kernel.msg_receiver(),
+                checkpoint
+            )));
+        }
+        last_gas - gas_available
     };
 
     ctx.data_mut()
         .kernel
-        .charge_gas("wasm_exec", Gas::from_milligas(milligas_used))
+        .charge_gas("wasm_exec", gas_used)
         .map_err(Abort::from_error_as_fatal)?;
 
     Ok(())
 }
 
 use self::bind::BindSyscall;
-use self::error::Abort;
+pub use self::error::Abort;
 
 // Binds the syscall handlers so they can handle invocations
 // from the actor code.
@@ -99,6 +224,13 @@ pub fn bind_syscalls(
 ) -> anyhow::Result<()> {
     linker.bind("vm", "abort", vm::abort)?;
     linker.bind("vm", "context", vm::context)?;
+    linker.bind("vm", "memcopy", vm::memcopy)?;
+    linker.bind("vm", "memzero", vm::memzero)?;
+    linker.bind("vm", "read_params", vm::read_params)?;
+    linker.bind("vm", "params_codec", vm::params_codec)?;
+    linker.bind("vm", "value_received", vm::value_received)?;
+    linker.bind("vm", "origin_nonce", vm::origin_nonce)?;
+    linker.bind("vm", "expected_return_codec", vm::expected_return_codec)?;
 
     linker.bind("network", "base_fee", network::base_fee)?;
     linker.bind(
@@ -106,20 +238,32 @@ pub fn bind_syscalls(
         "total_fil_circ_supply",
         network::total_fil_circ_supply,
     )?;
+    linker.bind("network", "circ_supply_detail", network::circ_supply_detail)?;
 
     linker.bind("ipld", "block_open", ipld::block_open)?;
     linker.bind("ipld", "block_create", ipld::block_create)?;
+    linker.bind("ipld", "block_measure", ipld::block_measure)?;
     linker.bind("ipld", "block_read", ipld::block_read)?;
     linker.bind("ipld", "block_stat", ipld::block_stat)?;
+    linker.bind("ipld", "block_read_field", ipld::block_read_field)?;
+    linker.bind("ipld", "verify_cid", ipld::verify_cid)?;
     linker.bind("ipld", "block_link", ipld::block_link)?;
 
     linker.bind("self", "root", sself::root)?;
     linker.bind("self", "set_root", sself::set_root)?;
     linker.bind("self", "current_balance", sself::current_balance)?;
     linker.bind("self", "self_destruct", sself::self_destruct)?;
+    linker.bind(
+        "self",
+        "state_commitment_with_balance",
+        sself::state_commitment_with_balance,
+    )?;
+    linker.bind("self", "bump_counter", sself::bump_counter)?;
 
     linker.bind("actor", "resolve_address", actor::resolve_address)?;
     linker.bind("actor", "get_actor_code_cid", actor::get_actor_code_cid)?;
+    linker.bind("actor", "is_account", actor::is_account)?;
+    linker.bind("actor", "nonce_of", actor::nonce_of)?;
     linker.bind("actor", "new_actor_address", actor::new_actor_address)?;
     linker.bind("actor", "create_actor", actor::create_actor)?;
     linker.bind(
@@ -138,6 +282,7 @@ pub fn bind_syscalls(
     linker.bind("actor", "install_actor", actor::install_actor)?;
 
     linker.bind("crypto", "verify_signature", crypto::verify_signature)?;
+    linker.bind("crypto", "pubkey_to_address", crypto::pubkey_to_address)?;
     linker.bind("crypto", "hash", crypto::hash)?;
     linker.bind("crypto", "verify_seal", crypto::verify_seal)?;
     linker.bind("crypto", "verify_post", crypto::verify_post)?;
@@ -165,11 +310,15 @@ pub fn bind_syscalls(
 
     linker.bind("rand", "get_chain_randomness", rand::get_chain_randomness)?;
     linker.bind("rand", "get_beacon_randomness", rand::get_beacon_randomness)?;
+    linker.bind("rand", "local_prng", rand::local_prng)?;
 
     linker.bind("gas", "charge", gas::charge_gas)?;
+    linker.bind("gas", "limit", gas::gas_limit)?;
+    linker.bind("gas", "available", gas::available)?;
 
     // Ok, this singled-out syscall should probably be in another category.
     linker.bind("send", "send", send::send)?;
+    linker.bind("send", "send_ext", send::send_ext)?;
 
     linker.bind("debug", "log", debug::log)?;
     linker.bind("debug", "enabled", debug::enabled)?;
@@ -177,3 +326,29 @@ pub fn bind_syscalls(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gas_global_roundtrip() {
+        let engine = wasmtime::Engine::default();
+        let mut store = wasmtime::Store::new(&engine, ());
+        let global = GasGlobal::new(&mut store).unwrap();
+        assert_eq!(global.get(&mut store).unwrap(), Gas::new(0));
+
+        for gas in [
+            Gas::new(10),
+            Gas::from_milligas(1234),
+            Gas::from_milligas(-1),
+        ] {
+            global.set(&mut store, gas).unwrap();
+            assert_eq!(global.get(&mut store).unwrap(), gas);
+        }
+
+        // the global itself holds milligas
+        global.set(&mut store, Gas::new(3)).unwrap();
+        assert_eq!(global.0.get(&mut store).i64(), Some(3000));
+    }
+}
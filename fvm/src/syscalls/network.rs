@@ -23,3 +23,21 @@ pub fn total_fil_circ_supply(context: Context<'_, impl Kernel>) -> Result<sys::T
         .context("circulating supply exceeds u128 limit")
         .or_fatal()
 }
+
+/// Returns the components of the network circ supply.
+pub fn circ_supply_detail(
+    context: Context<'_, impl Kernel>,
+) -> Result<sys::out::network::CircSupplyDetail> {
+    let detail = context.kernel.circ_supply_detail()?;
+    let amount = |v| {
+        sys::TokenAmount::try_from(v)
+            .context("circulating supply component exceeds u128 limit")
+            .or_fatal()
+    };
+    Ok(sys::out::network::CircSupplyDetail {
+        mined: amount(&detail.mined)?,
+        vested: amount(&detail.vested)?,
+        burnt: amount(&detail.burnt)?,
+        locked: amount(&detail.locked)?,
+    })
+}
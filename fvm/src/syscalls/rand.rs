@@ -37,3 +37,13 @@ pub fn get_beacon_randomness(
         .kernel
         .get_randomness_from_beacon(pers, round, entropy)
 }
+
+/// Gets 32 bytes of deterministic pseudo-randomness local to this invocation, derived from the
+/// message, receiver, method, and the supplied counter. This is _not_ suitable for anything
+/// security sensitive.
+pub fn local_prng(
+    context: Context<'_, impl Kernel>,
+    counter: u64,
+) -> Result<[u8; RANDOMNESS_LENGTH]> {
+    context.kernel.get_local_randomness(counter)
+}
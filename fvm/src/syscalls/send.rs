@@ -4,8 +4,9 @@ use fvm_shared::error::ExitCode;
 use fvm_shared::sys;
 
 use super::Context;
-use crate::kernel::{Result, SendResult};
-use crate::Kernel;
+use crate::gas::Gas;
+use crate::kernel::{Result, SendFlags, SendOptions, SendResult};
+use crate::{syscall_error, Kernel};
 
 /// Send a message to another actor. The result is placed as a CBOR-encoded
 /// receipt in the block registry, and can be retrieved by the returned BlockId.
@@ -18,12 +19,81 @@ pub fn send(
     value_hi: u64,
     value_lo: u64,
 ) -> Result<sys::out::send::Send> {
+    send_with_options(
+        context,
+        recipient_off,
+        recipient_len,
+        method,
+        params_id,
+        value_hi,
+        value_lo,
+        SendOptions::default(),
+    )
+}
+
+/// Like [`send`], with options controlling how the recipient is invoked.
+///
+/// A `gas_limit` of `u64::MAX` gives the recipient all the remaining gas. A non-zero
+/// `return_codec` tells the recipient which codec the caller expects the return value in. The
+/// `flags` are a [`SendFlags`] bitset; unknown bits are rejected.
+#[allow(clippy::too_many_arguments)]
+pub fn send_ext(
+    context: Context<'_, impl Kernel>,
+    recipient_off: u32,
+    recipient_len: u32,
+    method: u64,
+    params_id: u32,
+    value_hi: u64,
+    value_lo: u64,
+    gas_limit: u64,
+    return_codec: u64,
+    flags: u64,
+) -> Result<sys::out::send::Send> {
+    let flags = SendFlags::from_bits(flags)
+        .ok_or_else(|| syscall_error!(IllegalArgument; "unknown send flags: {:#x}", flags))?;
+    let gas_limit =
+        (gas_limit != u64::MAX).then(|| Gas::new(gas_limit.try_into().unwrap_or(i64::MAX)));
+    let return_codec = Some(return_codec).filter(|&codec| codec != 0);
+    send_with_options(
+        context,
+        recipient_off,
+        recipient_len,
+        method,
+        params_id,
+        value_hi,
+        value_lo,
+        SendOptions {
+            gas_limit,
+            return_codec,
+            flags,
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_with_options(
+    context: Context<'_, impl Kernel>,
+    recipient_off: u32,
+    recipient_len: u32,
+    method: u64,
+    params_id: u32,
+    value_hi: u64,
+    value_lo: u64,
+    options: SendOptions,
+) -> Result<sys::out::send::Send> {
+    // Reject an empty recipient explicitly, before trying to decode and resolve it.
+    if recipient_len == 0 {
+        return Err(syscall_error!(IllegalArgument; "recipient address is empty").into());
+    }
     let recipient: Address = context.memory.read_address(recipient_off, recipient_len)?;
     let value = TokenAmount::from((value_hi as u128) << 64 | value_lo as u128);
     // An execution error here means that something went wrong in the FVM.
     // Actor errors are communicated in the receipt.
     Ok(
-        match context.kernel.send(&recipient, method, params_id, &value)? {
+        match context
+            .kernel
+            .send(&recipient, method, params_id, &value, options)?
+        {
             SendResult::Return(id, stat) => sys::out::send::Send {
                 exit_code: ExitCode::OK.value(),
                 return_id: id,
@@ -31,6 +31,28 @@ pub fn current_balance(context: Context<'_, impl Kernel>) -> Result<sys::TokenAm
         .or_fatal()
 }
 
+/// Writes a 32 byte commitment over the actor's state root, balance, and sequence number to the
+/// specified buffer.
+pub fn state_commitment_with_balance(
+    context: Context<'_, impl Kernel>,
+    obuf_off: u32,
+) -> Result<()> {
+    // Check the output bounds first so we don't do any work if they're incorrect.
+    context.memory.check_bounds(obuf_off, 32)?;
+
+    let commitment = context.kernel.state_commitment_with_balance()?;
+
+    context
+        .memory
+        .try_slice_mut(obuf_off, 32)?
+        .copy_from_slice(&commitment);
+    Ok(())
+}
+
+pub fn bump_counter(context: Context<'_, impl Kernel>, slot: u64) -> Result<u64> {
+    context.kernel.bump_counter(slot)
+}
+
 pub fn self_destruct(
     context: Context<'_, impl Kernel>,
     addr_off: u32,
@@ -1,6 +1,6 @@
 use fvm_shared::error::ExitCode;
 use fvm_shared::sys::out::vm::InvocationContext;
-use fvm_shared::sys::SyscallSafe;
+use fvm_shared::sys::{self, SyscallSafe};
 use fvm_shared::version::NetworkVersion;
 
 use super::error::Abort;
@@ -48,6 +48,67 @@ pub fn abort(
     Err(Abort::Exit(code, message))
 }
 
+/// Copies `len` bytes from `src_off` to `dst_off` within the actor's memory, charging gas
+/// proportional to `len`. The source and destination regions may overlap.
+pub fn memcopy(
+    context: Context<'_, impl Kernel>,
+    dst_off: u32,
+    src_off: u32,
+    len: u32,
+) -> crate::kernel::Result<()> {
+    let charge = context.kernel.price_list().on_memcopy(len as usize);
+    context.kernel.charge_gas(charge.name, charge.total())?;
+    context.memory.copy_within(dst_off, src_off, len)
+}
+
+/// Zeroes `len` bytes at `off` in the actor's memory, charging gas proportional to `len`. Unlike
+/// zeroing in wasm, the host always performs the write, so it can't be optimized away.
+pub fn memzero(context: Context<'_, impl Kernel>, off: u32, len: u32) -> crate::kernel::Result<()> {
+    let charge = context.kernel.price_list().on_memzero(len as usize);
+    context.kernel.charge_gas(charge.name, charge.total())?;
+    context.memory.zero(off, len)
+}
+
+/// Copies the invocation's parameters into the actor's memory, returning their total size. At most
+/// `obuf_len` bytes are copied.
+pub fn read_params(
+    context: Context<'_, impl Kernel>,
+    obuf_off: u32,
+    obuf_len: u32,
+) -> crate::kernel::Result<u32> {
+    let data = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    context.kernel.msg_read_params(data)
+}
+
+/// Returns the codec of the invocation's parameters, or zero if there are none.
+pub fn params_codec(context: Context<'_, impl Kernel>) -> crate::kernel::Result<u64> {
+    Ok(context.kernel.msg_params_codec())
+}
+
+/// Returns the nonce of the top-level message that started the current call stack.
+pub fn origin_nonce(context: Context<'_, impl Kernel>) -> crate::kernel::Result<u64> {
+    Ok(context.kernel.msg_origin_nonce())
+}
+
+/// Returns the codec the caller expects the return value in, or `DAG_CBOR` if it didn't say.
+pub fn expected_return_codec(context: Context<'_, impl Kernel>) -> crate::kernel::Result<u64> {
+    Ok(context.kernel.msg_expected_return_codec())
+}
+
+/// Returns the value transferred with the current invocation.
+pub fn value_received(
+    context: Context<'_, impl Kernel>,
+) -> crate::kernel::Result<sys::TokenAmount> {
+    use anyhow::Context as _;
+
+    context
+        .kernel
+        .msg_value_received()
+        .try_into()
+        .context("invalid token amount")
+        .or_fatal()
+}
+
 pub fn context(context: Context<'_, impl Kernel>) -> crate::kernel::Result<InvocationContext> {
     use anyhow::Context as _;
 
@@ -1,9 +1,12 @@
+use std::sync::mpsc::SyncSender;
+
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::{ActorID, MethodNum};
 
+use crate::gas::Gas;
 use crate::kernel::SyscallError;
 
 /// Execution Trace, only for informational and debugging purposes.
@@ -21,4 +24,49 @@ pub enum ExecutionEvent {
     CallReturn(RawBytes),
     CallAbort(ExitCode),
     CallError(SyscallError),
+    /// Gas charged under the given name. Only streamed to an [`EventSender`], never recorded in
+    /// the execution trace.
+    GasCharge {
+        name: String,
+        gas: Gas,
+    },
+}
+
+/// What an [`EventSender`] does when its channel is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Drop the event, so that monitoring never slows down execution.
+    Drop,
+    /// Block execution until the receiver makes room for the event.
+    Block,
+}
+
+/// Streams execution events to a channel as they occur, for live monitoring. Configured with
+/// [`MachineContext::set_event_sender`](crate::machine::MachineContext::set_event_sender).
+///
+/// Events are only informational: a disconnected receiver is ignored.
+#[derive(Clone, Debug)]
+pub struct EventSender {
+    sender: SyncSender<ExecutionEvent>,
+    backpressure: Backpressure,
+}
+
+impl EventSender {
+    pub fn new(sender: SyncSender<ExecutionEvent>, backpressure: Backpressure) -> Self {
+        Self {
+            sender,
+            backpressure,
+        }
+    }
+
+    pub(crate) fn send(&self, event: ExecutionEvent) {
+        match self.backpressure {
+            Backpressure::Drop => {
+                let _ = self.sender.try_send(event);
+            }
+            Backpressure::Block => {
+                let _ = self.sender.send(event);
+            }
+        }
+    }
 }
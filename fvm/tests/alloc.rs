@@ -0,0 +1,56 @@
+//! Allocation tests. These live in their own test binary because they replace the global
+//! allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use cid::Cid;
+use fvm::syscalls::Memory;
+use fvm_shared::address::Address;
+use multihash::{Code, MultihashDigest};
+
+/// Counts the allocations made by the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+const RAW: u64 = 0x55;
+
+#[test]
+fn syscall_memory_reads_dont_allocate() {
+    let k = Cid::new_v1(RAW, Code::Sha2_256.digest(b"foo"));
+    let addr = Address::new_id(1234);
+    let mut buf = [k.to_bytes(), addr.to_bytes()].concat();
+    let (cid_len, addr_len) = (k.to_bytes().len() as u32, addr.to_bytes().len() as u32);
+    let mem = Memory::new(&mut buf);
+
+    // Simulate a syscall-heavy actor reading its arguments over and over.
+    let before = allocations();
+    for _ in 0..1000 {
+        assert_eq!(mem.try_slice(0, cid_len).unwrap().len(), cid_len as usize);
+        assert_eq!(mem.read_cid(0).unwrap(), k);
+        assert_eq!(mem.read_address(cid_len, addr_len).unwrap(), addr);
+        assert_eq!(mem.write_cid(&k, 0, cid_len).unwrap(), cid_len);
+    }
+    assert_eq!(allocations(), before, "syscall memory reads allocated");
+}
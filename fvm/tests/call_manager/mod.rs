@@ -0,0 +1,1513 @@
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+
+use cid::Cid;
+use fvm::call_manager::{
+    ArtifactOverflow, CallManager, DefaultCallManager, FailedSubcallGasPolicy, InvocationResult,
+    StateWriteObserver, NO_DATA_BLOCK_ID,
+};
+use fvm::gas::{Gas, GasCharge, ScalingCost};
+use fvm::kernel::{
+    BlockRegistry, DebugOps, ExecutionError, GasOps, IpldBlockOps, SendFlags, SendOps, SendOptions,
+    SendResult, SyscallError,
+};
+use fvm::machine::{DefaultMachine, Engine, Machine, MachineContext, NetworkConfig};
+use fvm::state_tree::{ActorState, StateTree};
+use fvm::trace::{Backpressure, EventSender, ExecutionEvent};
+use fvm::{DefaultKernel, Kernel};
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::{CborStore, DAG_CBOR};
+use fvm_shared::address::Address;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::{ActorID, IPLD_RAW};
+use lazy_static::lazy_static;
+use multihash::{Code, MultihashDigest};
+use num_traits::Zero;
+
+use super::*;
+
+type TestingCallManager = DefaultCallManager<DefaultMachine<MemoryBlockstore, DummyExterns>>;
+type TestingKernel = DefaultKernel<TestingCallManager>;
+
+lazy_static! {
+    /// Held by tests storing debug artifacts, as the artifact directory is set process-wide.
+    static ref ARTIFACT_DIR_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Returns a minimal actor that returns immediately with no data. The module is padded with a data
+/// segment of `padding` bytes, so that actors with distinct code (and code sizes) can be created.
+fn actor_wasm(padding: usize) -> Vec<u8> {
+    wat_actor("", &wat_data(0, &vec![0; padding]), "(i32.const 0)")
+}
+
+/// Returns a minimal actor like [`actor_wasm`], with a table of `elements` elements.
+fn table_wasm(elements: u32) -> Vec<u8> {
+    wat_actor(
+        "",
+        &format!("(table {} funcref)", elements),
+        "(i32.const 0)",
+    )
+}
+
+/// Returns a call to `$send`, sending to the address of `recipient_len` bytes at offset 0 (method
+/// 1, no params or value) with the given `flags`, and writing the result to offset 16.
+fn wat_send(recipient_len: usize, flags: u64) -> String {
+    format!(
+        "(call $send
+           (i32.const 16) (i32.const 0) (i32.const {}) (i64.const 1) (i32.const 0)
+           (i64.const 0) (i64.const 0) (i64.const -1) (i64.const 0) (i64.const {}))",
+        recipient_len, flags
+    )
+}
+
+/// Returns an actor that sends to actor 1000 (method 1, no params or value) in a loop until a send
+/// fails, then returns with no data.
+fn looping_sender_wasm() -> Vec<u8> {
+    let target = Address::new_id(1000).to_bytes();
+    wat_actor(
+        SEND,
+        &wat_data(0, &target),
+        &format!(
+            "(loop $retry (br_if $retry (i32.eqz {})))
+             (i32.const 0)",
+            wat_send(target.len(), 0)
+        ),
+    )
+}
+
+/// Returns an actor that sends with an empty recipient address, then aborts with exit code 16 plus
+/// the error number returned by `send`.
+fn empty_recipient_sender_wasm() -> Vec<u8> {
+    wat_actor(
+        &[SEND, ABORT].concat(),
+        "",
+        &format!(
+            "(call $abort (i32.add {} (i32.const 16)) (i32.const 0) (i32.const 0))",
+            wat_send(0, 0)
+        ),
+    )
+}
+
+/// Returns an actor that calls the `module::name` syscall, which writes a u64 to the given offset,
+/// then aborts with the low 32 bits of that u64 as its exit code.
+fn u64_reader_wasm(module: &str, name: &str) -> Vec<u8> {
+    wat_actor(
+        &format!(
+            r#"(import "{}" "{}" (func $read (param i32) (result i32))) {}"#,
+            module, name, ABORT
+        ),
+        "",
+        "(drop (call $read (i32.const 16)))
+         (call $abort (i32.wrap_i64 (i64.load (i32.const 16))) (i32.const 0) (i32.const 0))",
+    )
+}
+
+/// Returns an actor that reads the value it received, then aborts with the low 32 bits of the value
+/// as its exit code.
+fn value_reader_wasm() -> Vec<u8> {
+    u64_reader_wasm("vm", "value_received")
+}
+
+/// Returns an actor that reads the nonce of the top-level message, then aborts with its low 32 bits
+/// as its exit code.
+fn nonce_reader_wasm() -> Vec<u8> {
+    u64_reader_wasm("vm", "origin_nonce")
+}
+
+/// Returns an actor that reads the return codec expected by its caller, then aborts with its low 32
+/// bits as its exit code.
+fn return_codec_reader_wasm() -> Vec<u8> {
+    u64_reader_wasm("vm", "expected_return_codec")
+}
+
+/// Returns an actor that sends to `target` (method 1, no params or value), then aborts with the
+/// exit code of the send.
+fn relay_wasm(target: ActorID) -> Vec<u8> {
+    let target = Address::new_id(target).to_bytes();
+    wat_actor(
+        &[SEND, ABORT].concat(),
+        &wat_data(0, &target),
+        &format!(
+            "(drop {})
+             (call $abort (i32.load (i32.const 16)) (i32.const 0) (i32.const 0))",
+            wat_send(target.len(), 0)
+        ),
+    )
+}
+
+/// Returns an actor like [`relay_wasm`], sending through the original `send::send` syscall,
+/// which takes no send options.
+fn legacy_relay_wasm(target: ActorID) -> Vec<u8> {
+    let target = Address::new_id(target).to_bytes();
+    wat_actor(
+        &[
+            r#"(import "send" "send" (func $send (param i32 i32 i32 i64 i32 i64 i64) (result i32)))"#,
+            ABORT,
+        ]
+        .concat(),
+        &wat_data(0, &target),
+        &format!(
+            "(drop (call $send
+               (i32.const 16) (i32.const 0) (i32.const {}) (i64.const 1) (i32.const 0)
+               (i64.const 0) (i64.const 0)))
+             (call $abort (i32.load (i32.const 16)) (i32.const 0) (i32.const 0))",
+            target.len()
+        ),
+    )
+}
+
+/// Returns an actor that sends to `target` (method 1, no params or value) with the given send
+/// `flags`, then aborts with the exit code of the send, or with 16 plus the error number returned
+/// by `send` if it fails.
+fn flagged_relay_wasm(target: ActorID, flags: u64) -> Vec<u8> {
+    let target = Address::new_id(target).to_bytes();
+    wat_actor(
+        &[SEND, ABORT].concat(),
+        &wat_data(0, &target),
+        &format!(
+            "(local $err i32)
+             (local.set $err {})
+             (call $abort
+               (if (result i32) (local.get $err)
+                 (then (i32.add (local.get $err) (i32.const 16)))
+                 (else (i32.load (i32.const 16))))
+               (i32.const 0) (i32.const 0))",
+            wat_send(target.len(), flags)
+        ),
+    )
+}
+
+/// Returns an actor that sets its state root to `root`, then aborts with exit code 16 plus the
+/// error number returned by `set_root`.
+fn root_setter_wasm(root: &Cid) -> Vec<u8> {
+    wat_actor(
+        &[
+            r#"(import "self" "set_root" (func $set_root (param i32) (result i32)))"#,
+            ABORT,
+        ]
+        .concat(),
+        &wat_data(0, &root.to_bytes()),
+        "(call $abort
+           (i32.add (call $set_root (i32.const 0)) (i32.const 16))
+           (i32.const 0) (i32.const 0))",
+    )
+}
+
+/// Returns an actor that stores a debug artifact named `name`, with `data_len` zero bytes of data,
+/// then aborts with exit code 16 plus the error number returned by `store_artifact`.
+fn artifact_storer_wasm(name: &[u8], data_len: u32) -> Vec<u8> {
+    wat_actor(
+        &[
+            r#"(import "debug" "store_artifact"
+                 (func $store_artifact (param i32 i32 i32 i32) (result i32)))"#,
+            ABORT,
+        ]
+        .concat(),
+        &wat_data(0, name),
+        &format!(
+            "(call $abort
+               (i32.add
+                 (call $store_artifact (i32.const 0) (i32.const {}) (i32.const 1024) (i32.const {}))
+                 (i32.const 16))
+               (i32.const 0) (i32.const 0))",
+            name.len(),
+            data_len
+        ),
+    )
+}
+
+/// Returns an actor that reads the gas available, charges 1000 gas, reads the gas available again,
+/// then aborts with the difference (in whole gas) as its exit code.
+fn gas_probe_wasm() -> Vec<u8> {
+    wat_actor(
+        &[
+            r#"(import "gas" "available" (func $available (param i32) (result i32)))"#,
+            r#"(import "gas" "charge" (func $charge (param i32 i32 i64) (result i32)))"#,
+            ABORT,
+        ]
+        .concat(),
+        "",
+        "(drop (call $available (i32.const 16)))
+         (drop (call $charge (i32.const 0) (i32.const 0) (i64.const 1000)))
+         (drop (call $available (i32.const 24)))
+         (call $abort
+           (i32.wrap_i64
+             (i64.div_u
+               (i64.sub (i64.load (i32.const 16)) (i64.load (i32.const 24)))
+               (i64.const 1000)))
+           (i32.const 0) (i32.const 0))",
+    )
+}
+
+/// Returns an actor that loops forever, until it runs out of gas.
+fn spinner_wasm() -> Vec<u8> {
+    wat_actor("", "", "(loop (br 0)) unreachable")
+}
+
+/// Returns an actor that creates two raw blocks, "intr" and then "retn", and returns the second. If
+/// `abort` is set, the actor aborts with exit code 16 after creating the blocks instead.
+fn block_returner_wasm(abort: bool) -> Vec<u8> {
+    let abort = if abort {
+        "(drop (call $abort (i32.const 16) (i32.const 0) (i32.const 0)))"
+    } else {
+        ""
+    };
+    wat_actor(
+        &[
+            r#"(import "ipld" "block_create"
+                 (func $block_create (param i32 i64 i32 i32) (result i32)))"#,
+            ABORT,
+        ]
+        .concat(),
+        &wat_data(0, b"intrretn"),
+        &format!(
+            "(drop (call $block_create (i32.const 16) (i64.const 0x55) (i32.const 0) (i32.const 4)))
+             (drop (call $block_create (i32.const 16) (i64.const 0x55) (i32.const 4) (i32.const 4)))
+             {}
+             (i32.load (i32.const 16))",
+            abort
+        ),
+    )
+}
+
+/// Builds a call manager over a state-tree containing the given actors, with their code loaded
+/// into the engine.
+fn build_call_manager(
+    config: &mut NetworkConfig,
+    actors: &[(ActorID, &[u8])],
+) -> anyhow::Result<TestingCallManager> {
+    build_call_manager_with_context(config, actors, |_| {})
+}
+
+/// Like [`build_call_manager`], but allows customizing the machine context.
+fn build_call_manager_with_context(
+    config: &mut NetworkConfig,
+    actors: &[(ActorID, &[u8])],
+    customize: impl FnOnce(&mut MachineContext),
+) -> anyhow::Result<TestingCallManager> {
+    let bs = MemoryBlockstore::new();
+    let mut state_tree = StateTree::new(bs, StateTreeVersion::V4)?;
+
+    let mut code = Vec::new();
+    for (id, wasm) in actors {
+        let code_cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(wasm));
+        state_tree.store().put_keyed(&code_cid, wasm)?;
+        let state = state_tree.store().put_cbor(&id, Code::Blake2b256)?;
+        state_tree.set_actor(
+            &Address::new_id(*id),
+            ActorState::new(code_cid, state, 0.into(), 0),
+        )?;
+        code.push(code_cid);
+    }
+    let root = state_tree.flush()?;
+    let bs = state_tree.into_store();
+
+    let manifest: Vec<(String, Cid)> = Vec::new();
+    let manifest_cid = bs.put_cbor(&manifest, Code::Blake2b256)?;
+    let actors_cid = bs.put_cbor(&(1, manifest_cid), Code::Blake2b256)?;
+
+    let mut ctx = config.override_actors(actors_cid).for_epoch(0, root);
+    customize(&mut ctx);
+    let engine = Engine::new_default((&*config).into())?;
+    let machine = DefaultMachine::new(&engine, &ctx, bs, DummyExterns)?;
+    machine.precompile(&code)?;
+
+    Ok(TestingCallManager::new(
+        machine,
+        i64::MAX,
+        Address::new_id(100),
+        0,
+    ))
+}
+
+/// Sends to each of the given actors in turn, returning the gas used.
+fn send_all(cm: &mut TestingCallManager, to: &[ActorID]) -> anyhow::Result<Gas> {
+    for id in to {
+        let res = cm.send::<TestingKernel>(
+            100,
+            Address::new_id(*id),
+            1,
+            None,
+            &0.into(),
+            SendOptions::default(),
+        )?;
+        assert!(matches!(res, InvocationResult::Return(None)));
+    }
+    Ok(cm.gas_tracker().gas_used())
+}
+
+#[test]
+fn instantiation_cost() -> anyhow::Result<()> {
+    let small = actor_wasm(0);
+    let large = actor_wasm(1000);
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &small), (1001, &large)];
+
+    let gas_used = |config: &mut NetworkConfig, to| -> anyhow::Result<Gas> {
+        send_all(&mut build_call_manager(config, &actors)?, &[to])
+    };
+
+    // by default, instantiation isn't charged
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let small_default = gas_used(&mut config, 1000)?;
+    assert_eq!(small_default, gas_used(&mut config, 1001)?);
+
+    // but can be configured, scaling with the size of the actor's code
+    let cost = ScalingCost {
+        flat: Gas::new(1000),
+        scale: Gas::new(10),
+    };
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.override_actor_instantiation_cost(cost);
+    let small_charged = gas_used(&mut config, 1000)?;
+    let large_charged = gas_used(&mut config, 1001)?;
+
+    assert_eq!(
+        small_charged - small_default,
+        cost.flat + cost.scale * small.len() as i64
+    );
+    assert_eq!(
+        large_charged - small_charged,
+        cost.scale * (large.len() - small.len()) as i64
+    );
+
+    Ok(())
+}
+
+#[test]
+fn instantiation_cost_precompiled() -> anyhow::Result<()> {
+    let wasm = actor_wasm(100);
+    let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&wasm));
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.override_actor_instantiation_cost(ScalingCost {
+        flat: Gas::new(1000),
+        scale: Gas::new(10),
+    });
+    let mut cm = build_call_manager(&mut config, &[(1000, &wasm)])?;
+    let expected = send_all(&mut cm, &[1000])?;
+
+    // load the module into a new engine precompiled, which doesn't tell it the bytecode length
+    let (_, mut machine) = cm.finish();
+    let compiled = machine.engine().get_module(&code).unwrap().serialize()?;
+    let engine = Engine::new_default((&config).into())?;
+    unsafe { engine.load_compiled(&code, &compiled)? };
+    assert_eq!(engine.get_code_size(&code), None);
+
+    // instantiation is still charged by the bytecode length
+    let mut ctx = machine.context().clone();
+    ctx.initial_state_root = machine.flush()?;
+    let bs = machine.into_store().into_inner();
+    let machine = DefaultMachine::new(&engine, &ctx, bs, DummyExterns)?;
+    let mut cm = TestingCallManager::new(machine, i64::MAX, Address::new_id(100), 0);
+    assert_eq!(send_all(&mut cm, &[1000])?, expected);
+    assert_eq!(engine.compilations(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn table_growth() -> anyhow::Result<()> {
+    let table = table_wasm(1000);
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &table)];
+
+    // tables are free by default
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let free = send_all(&mut build_call_manager(&mut config, &actors)?, &[1000])?;
+
+    // but can be charged per element, including those allocated on instantiation
+    let cost = Gas::new(10);
+    config.override_table_element_cost(cost);
+    let charged = send_all(&mut build_call_manager(&mut config, &actors)?, &[1000])?;
+    assert_eq!(charged - free, cost * 1000);
+
+    // tables larger than the limit abort the actor
+    config.override_max_table_elements(999);
+    let mut cm = build_call_manager(&mut config, &actors)?;
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1000),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    assert!(matches!(
+        res,
+        InvocationResult::Failure(ExitCode::SYS_ILLEGAL_INSTRUCTION)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn instantiation_charged_per_send() -> anyhow::Result<()> {
+    let wasm = actor_wasm(100);
+    // actor 1001 shares actor 1000's code
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &wasm), (1001, &wasm)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.override_actor_instantiation_cost(ScalingCost {
+        flat: Gas::new(1000),
+        scale: Gas::new(10),
+    });
+
+    // every send is charged for instantiation, even to code already instantiated in the message
+    let mut cm = build_call_manager(&mut config, &actors)?;
+    let first = send_all(&mut cm, &[1000])?;
+    let second = send_all(&mut cm, &[1000])? - first;
+    let shared = send_all(&mut cm, &[1001])? - first - second;
+    assert_eq!(second, first);
+    assert_eq!(shared, first);
+
+    Ok(())
+}
+
+#[test]
+fn precompile() -> anyhow::Result<()> {
+    let wasm: Vec<_> = (0..2).map(|i| actor_wasm(i * 100)).collect();
+    let actors: [(ActorID, &[u8]); 3] = [(1000, &wasm[0]), (1001, &wasm[1]), (1002, &wasm[0])];
+
+    // the call manager's machine precompiles each distinct code once
+    let mut cm = build_call_manager(&mut NetworkConfig::new(STUB_NETWORK_VER), &actors)?;
+    let engine = cm.machine().engine().clone();
+    assert_eq!(engine.compilations(), 2);
+
+    // so the first sends to the actors don't compile anything
+    send_all(&mut cm, &[1000, 1001, 1002])?;
+    assert_eq!(engine.compilations(), 2);
+
+    // and precompiling cached code is a no-op
+    let code: Vec<_> = wasm
+        .iter()
+        .map(|w| Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(w)))
+        .collect();
+    cm.machine().precompile(&code)?;
+    assert_eq!(engine.compilations(), 2);
+
+    // but missing code is an error
+    let missing = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"missing"));
+    assert!(cm.machine().precompile(&[missing]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn invalid_actor_code() -> anyhow::Result<()> {
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager(&mut config, &[])?;
+
+    // install an actor whose code isn't valid wasm, which fails to precompile
+    let wasm = b"\0asm\x01\0\0\0not wasm";
+    let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(wasm));
+    cm.blockstore().put_keyed(&code, wasm)?;
+    assert!(cm.machine().precompile(&[code]).is_err());
+    let state = cm.blockstore().put_cbor(&1000, Code::Blake2b256)?;
+    cm.state_tree_mut().set_actor(
+        &Address::new_id(1000),
+        ActorState::new(code, state, 0.into(), 0),
+    )?;
+
+    // the send fails cleanly, rather than with a fatal error
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1000),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    assert!(matches!(
+        res,
+        InvocationResult::Failure(ExitCode::SYS_INVALID_METHOD)
+    ));
+
+    // and the failure is attributed to the code
+    let (ret, _) = cm.finish();
+    let frame = &ret.backtrace.frames[0];
+    assert_eq!(frame.source, 1000);
+    assert_eq!(frame.code, ExitCode::SYS_INVALID_METHOD);
+    assert!(
+        frame.message.contains(&code.to_string()),
+        "{}",
+        frame.message
+    );
+
+    Ok(())
+}
+
+#[test]
+fn send_limit() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let sender = looping_sender_wasm();
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &noop), (1001, &sender)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.max_sends_per_message = 5;
+    let mut cm = build_call_manager_with_context(&mut config, &actors, |ctx| {
+        ctx.enable_tracing();
+    })?;
+
+    // the sender's sixth send fails, ending its loop
+    send_all(&mut cm, &[1001])?;
+    assert_eq!(cm.invocation_count(), 6);
+
+    let (ret, _) = cm.finish();
+    let errors: Vec<_> = ret
+        .exec_trace
+        .iter()
+        .filter_map(|event| match event {
+            ExecutionEvent::CallError(err) => Some(err.1),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(errors, vec![ErrorNumber::LimitExceeded]);
+
+    Ok(())
+}
+
+#[test]
+fn send_empty_recipient() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let sender = empty_recipient_sender_wasm();
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &noop), (1001, &sender)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager(&mut config, &actors)?;
+
+    // the send is rejected before reaching the recipient, with an illegal argument error
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1001),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    let expected = 16 + ErrorNumber::IllegalArgument as u32;
+    assert!(
+        matches!(res, InvocationResult::Failure(code) if code.value() == expected),
+        "{:?}",
+        res
+    );
+    assert_eq!(cm.invocation_count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn value_received() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let reader = value_reader_wasm();
+    let actors: [(ActorID, &[u8]); 2] = [(100, &noop), (1000, &reader)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager(&mut config, &actors)?;
+    cm.state_tree_mut().mutate_actor_id(100, |actor| {
+        actor.balance = 10_000.into();
+        Ok(())
+    })?;
+
+    // the callee reads exactly the value sent with the call
+    for value in [42, 999] {
+        let res = cm.send::<TestingKernel>(
+            100,
+            Address::new_id(1000),
+            1,
+            None,
+            &value.into(),
+            SendOptions::default(),
+        )?;
+        assert!(
+            matches!(res, InvocationResult::Failure(code) if code.value() == value),
+            "{:?}",
+            res
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn origin_nonce() -> anyhow::Result<()> {
+    let reader = nonce_reader_wasm();
+    let relay = relay_wasm(1000);
+    let relay_relay = relay_wasm(1001);
+    let actors: [(ActorID, &[u8]); 3] = [(1000, &reader), (1001, &relay), (1002, &relay_relay)];
+
+    // start a call stack for a message with nonce 42
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let (_, machine) = build_call_manager(&mut config, &actors)?.finish();
+    let mut cm = TestingCallManager::new(machine, i64::MAX, Address::new_id(100), 42);
+
+    // the reader sees the top-level message's nonce however deep it's called
+    for id in [1000, 1001, 1002] {
+        let res = cm.send::<TestingKernel>(
+            100,
+            Address::new_id(id),
+            1,
+            None,
+            &0.into(),
+            SendOptions::default(),
+        )?;
+        assert!(
+            matches!(res, InvocationResult::Failure(code) if code.value() == 42),
+            "{:?}",
+            res
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "chrome-trace")]
+fn chrome_trace() -> anyhow::Result<()> {
+    let reader = nonce_reader_wasm();
+    let relay = relay_wasm(1000);
+    let relay_relay = relay_wasm(1001);
+    let actors: [(ActorID, &[u8]); 3] = [(1000, &reader), (1001, &relay), (1002, &relay_relay)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager_with_context(&mut config, &actors, |ctx| {
+        ctx.enable_tracing();
+    })?;
+    cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1002),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    let (ret, _) = cm.finish();
+    let mut apply_ret = fvm::executor::ApplyRet::prevalidation_fail(ExitCode::OK, "", Zero::zero());
+    apply_ret.exec_trace = ret.exec_trace;
+
+    // each call is a span, closed by its matching end event, and nested within its caller's span
+    let events: Vec<serde_json::Value> = serde_json::from_str(&apply_ret.to_chrome_trace())?;
+    let mut open = Vec::new();
+    let mut calls = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(event["ts"], i);
+        match event["ph"].as_str() {
+            Some("B") => {
+                calls.push((event["args"]["to"].clone(), open.len()));
+                open.push(event["name"].clone());
+            }
+            Some("E") => {
+                assert!(open.pop().is_some(), "unmatched end event");
+                assert_eq!(event["name"], "abort");
+            }
+            ph => panic!("unexpected phase {:?}", ph),
+        }
+    }
+    assert!(open.is_empty(), "unclosed spans: {:?}", open);
+    assert_eq!(
+        calls,
+        [
+            ("f01002".into(), 0),
+            ("f01001".into(), 1),
+            ("f01000".into(), 2)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn store_artifact_validation() -> anyhow::Result<()> {
+    let _lock = ARTIFACT_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // the kernel creates the artifact directory when storing an artifact, so it only exists if the
+    // kernel was reached
+    let dir = std::env::temp_dir().join(format!("fvm-artifacts-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::env::set_var("FVM_STORE_ARTIFACT_DIR", &dir);
+
+    let store = |name: &[u8], data_len| -> anyhow::Result<InvocationResult> {
+        let storer = artifact_storer_wasm(name, data_len);
+        let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+        config.enable_actor_debugging();
+        config.max_artifact_size = 1024;
+        let mut cm = build_call_manager(&mut config, &[(1000, &storer)])?;
+        Ok(cm.send::<TestingKernel>(
+            100,
+            Address::new_id(1000),
+            1,
+            None,
+            &0.into(),
+            SendOptions::default(),
+        )?)
+    };
+
+    // bad names and oversized data are rejected before reaching the kernel
+    let illegal_argument = 16 + ErrorNumber::IllegalArgument as u32;
+    for (name, data_len) in [
+        (&b"../../etc/something"[..], 4),
+        (b"nul\0byte", 4),
+        (b"artifact", 1025),
+    ] {
+        let res = store(name, data_len)?;
+        assert!(
+            matches!(res, InvocationResult::Failure(code) if code.value() == illegal_argument),
+            "{:?}",
+            res
+        );
+        assert!(!dir.exists());
+    }
+
+    // an artifact within the limits is stored
+    let res = store(b"artifact", 1024)?;
+    assert!(
+        matches!(res, InvocationResult::Failure(code) if code.value() == 16),
+        "{:?}",
+        res
+    );
+    assert!(dir.exists());
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
+
+#[test]
+fn total_artifact_size_cap() -> anyhow::Result<()> {
+    /// Returns the names of the files under `dir`, sorted.
+    fn stored(dir: &std::path::Path) -> std::io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                names.extend(stored(&path)?);
+            } else {
+                names.extend(path.file_name().and_then(|n| n.to_str()).map(String::from));
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    let _lock = ARTIFACT_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = std::env::temp_dir().join(format!("fvm-artifact-cap-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::env::set_var("FVM_STORE_ARTIFACT_DIR", &dir);
+
+    for overflow in [ArtifactOverflow::Drop, ArtifactOverflow::Error] {
+        let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+        config.enable_actor_debugging();
+        config.max_artifact_bytes_per_message = 1000;
+        config.override_artifact_overflow(overflow);
+        let cm = build_call_manager(&mut config, &[])?;
+        let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+        // artifacts are stored until their total size reaches the cap
+        kern.store_artifact("first", &[0; 600])?;
+        kern.store_artifact("second", &[0; 400])?;
+
+        // past the cap, even small artifacts are dropped or rejected
+        match overflow {
+            ArtifactOverflow::Drop => kern.store_artifact("third", &[0; 1])?,
+            ArtifactOverflow::Error => {
+                expect_syscall_err!(LimitExceeded, kern.store_artifact("third", &[0; 1]))
+            }
+        }
+        assert_eq!(stored(&dir)?, ["first", "second"]);
+        std::fs::remove_dir_all(&dir)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gas_available() -> anyhow::Result<()> {
+    let probe = gas_probe_wasm();
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager(&mut config, &[(1000, &probe)])?;
+
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1000),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    let used = match res {
+        InvocationResult::Failure(code) => Gas::new(code.value().into()),
+        res => panic!("unexpected result: {:?}", res),
+    };
+
+    // the gas available dropped by the charge, the two syscalls after the first reading, and the
+    // few instructions executed between them
+    let syscalls = cm.price_list().on_syscall().total() * 2;
+    assert!(used >= Gas::new(1000) + syscalls, "{}", used);
+    assert!(used < Gas::new(1010) + syscalls, "{}", used);
+
+    Ok(())
+}
+
+#[test]
+fn send_params_size_limit() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &noop)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.max_send_params_size = 4;
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // params at the limit are sent
+    let params = kern.block_create(IPLD_RAW, b"four")?;
+    assert!(matches!(
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            params,
+            &0.into(),
+            SendOptions::default()
+        )?,
+        SendResult::Return(..)
+    ));
+
+    // larger params are rejected without invoking the recipient
+    let params = kern.block_create(IPLD_RAW, b"five!")?;
+    expect_syscall_err!(
+        IllegalArgument,
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            params,
+            &0.into(),
+            SendOptions::default()
+        )
+        .map(|_| ())
+    );
+    let (cm, _) = kern.into_inner();
+    assert_eq!(cm.invocation_count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn send_param_codecs() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &noop)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.restrict_send_param_codecs(vec![DAG_CBOR]);
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // DAG-CBOR params, and no params at all, are sent
+    let params = kern.block_create(DAG_CBOR, &[0x80])?;
+    for params in [params, NO_DATA_BLOCK_ID] {
+        assert!(matches!(
+            kern.send(
+                &Address::new_id(1000),
+                1,
+                params,
+                &0.into(),
+                SendOptions::default()
+            )?,
+            SendResult::Return(..)
+        ));
+    }
+
+    // but RAW params are rejected without invoking the recipient
+    let params = kern.block_create(IPLD_RAW, b"raw")?;
+    expect_syscall_err!(
+        IllegalCodec,
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            params,
+            &0.into(),
+            SendOptions::default()
+        )
+        .map(|_| ())
+    );
+    let (cm, _) = kern.into_inner();
+    assert_eq!(cm.invocation_count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn gas_by_actor() -> anyhow::Result<()> {
+    // the relay calls an actor that fails after charging 1000 gas
+    let failing = gas_probe_wasm();
+    let relay = relay_wasm(1000);
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &failing), (1001, &relay)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let (_, machine) = build_call_manager(&mut config, &actors)?.finish();
+    let mut cm = TestingCallManager::new(machine, i64::MAX, Address::new_id(100), 0);
+
+    // gas charged outside any actor is attributed to the sender
+    cm.charge_gas(GasCharge::new("inclusion", Gas::new(1000), Gas::zero()))?;
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1001),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    assert!(matches!(res, InvocationResult::Failure(_)), "{:?}", res);
+    let total = cm.gas_tracker().gas_used();
+    let (ret, _) = cm.finish();
+
+    let ids: Vec<_> = ret.gas_by_actor.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, [100, 1000, 1001]);
+    // the failed call's gas is attributed to the actor that failed, not the relay
+    let gas = |id| ret.gas_by_actor.iter().find(|(i, _)| *i == id).unwrap().1;
+    assert!(gas(100) >= Gas::new(1000), "{}", gas(100));
+    assert!(gas(1000) >= Gas::new(1000), "{}", gas(1000));
+    assert!(gas(1001) > Gas::zero());
+
+    let sum = ret
+        .gas_by_actor
+        .iter()
+        .fold(Gas::zero(), |sum, (_, gas)| sum + *gas);
+    assert_eq!(sum, total);
+    assert_eq!(sum.round_up(), ret.gas_used);
+
+    Ok(())
+}
+
+#[test]
+fn self_recursion_limit() -> anyhow::Result<()> {
+    let limit_exceeded = 16 + ErrorNumber::LimitExceeded as u32;
+
+    // an actor that sends to itself until a send fails
+    let relay = flagged_relay_wasm(1000, 0);
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &relay)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.max_self_recursion_depth = 3;
+    let mut cm = build_call_manager(&mut config, &actors)?;
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1000),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+
+    // the actor sends to itself three times, well within the call depth limit, and the fourth send
+    // fails with LimitExceeded, which each caller aborts with in turn
+    assert!(
+        matches!(res, InvocationResult::Failure(code) if code.value() == limit_exceeded),
+        "{:?}",
+        res
+    );
+    assert_eq!(cm.invocation_count(), 4);
+    assert!(cm.context().max_call_depth > 4);
+
+    // re-entering an actor through another actor isn't limited, only by the call depth
+    let (to_other, to_first) = (flagged_relay_wasm(1001, 0), flagged_relay_wasm(1000, 0));
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &to_other), (1001, &to_first)];
+    config.max_self_recursion_depth = 1;
+    config.max_call_depth = 6;
+    let mut cm = build_call_manager(&mut config, &actors)?;
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1000),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    assert!(
+        matches!(res, InvocationResult::Failure(code) if code.value() == limit_exceeded),
+        "{:?}",
+        res
+    );
+    assert_eq!(cm.invocation_count(), 7);
+
+    Ok(())
+}
+
+#[test]
+fn send_params_depth_limit() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &noop)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.max_cbor_depth = 2;
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // `[[1]]` is at the limit, while `[[[1]]]` is too deeply nested
+    let params = kern.block_create(DAG_CBOR, &[0x81, 0x81, 0x01])?;
+    assert!(matches!(
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            params,
+            &0.into(),
+            SendOptions::default()
+        )?,
+        SendResult::Return(..)
+    ));
+    let params = kern.block_create(DAG_CBOR, &[0x81, 0x81, 0x81, 0x01])?;
+    expect_syscall_err!(
+        IllegalArgument,
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            params,
+            &0.into(),
+            SendOptions::default()
+        )
+        .map(|_| ())
+    );
+
+    // the depth of raw params isn't checked
+    let params = kern.block_create(IPLD_RAW, &[0x81, 0x81, 0x81, 0x01])?;
+    assert!(matches!(
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            params,
+            &0.into(),
+            SendOptions::default()
+        )?,
+        SendResult::Return(..)
+    ));
+    let (cm, _) = kern.into_inner();
+    assert_eq!(cm.invocation_count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn event_sender() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let sender = looping_sender_wasm();
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &noop), (1001, &sender)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.max_sends_per_message = 2;
+    let (tx, rx) = sync_channel(100);
+    let mut cm = build_call_manager_with_context(&mut config, &actors, |ctx| {
+        ctx.set_event_sender(EventSender::new(tx, Backpressure::Block));
+    })?;
+    send_all(&mut cm, &[1001])?;
+
+    // events are streamed even though tracing is disabled
+    let gas_used = cm.gas_tracker().gas_used();
+    let (ret, _) = cm.finish();
+    assert!(ret.exec_trace.is_empty());
+
+    // including every gas charge
+    let mut charged = Gas::zero();
+    let events: Vec<String> = rx
+        .try_iter()
+        .filter_map(|event| match event {
+            ExecutionEvent::Call { to, .. } => Some(format!("call {}", to)),
+            ExecutionEvent::CallReturn(_) => Some("return".into()),
+            ExecutionEvent::CallAbort(code) => Some(format!("abort {}", code)),
+            ExecutionEvent::CallError(err) => Some(format!("error {}", err.1)),
+            ExecutionEvent::GasCharge { gas, .. } => {
+                charged += gas;
+                None
+            }
+        })
+        .collect();
+    assert_eq!(charged, gas_used);
+    assert_eq!(
+        events,
+        [
+            "call f01001",
+            "call f01000",
+            "return",
+            "call f01000",
+            "return",
+            "call f01000",
+            "error limit exceeded",
+            "return",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn event_sender_drop() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &noop)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let (tx, rx) = sync_channel(1);
+    let mut cm = build_call_manager_with_context(&mut config, &actors, |ctx| {
+        ctx.set_event_sender(EventSender::new(tx, Backpressure::Drop));
+    })?;
+
+    // events are dropped once the channel is full, rather than blocking execution
+    send_all(&mut cm, &[1000, 1000])?;
+    assert!(matches!(
+        &rx.try_iter().collect::<Vec<_>>()[..],
+        [ExecutionEvent::Call { method: 1, .. }]
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn block_visibility_across_sends() -> anyhow::Result<()> {
+    let returner = block_returner_wasm(false);
+    let aborter = block_returner_wasm(true);
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &returner), (1001, &aborter)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+    // all blocks in this test are 4 bytes long
+    let read = |kern: &mut TestingKernel, id| -> anyhow::Result<[u8; 4]> {
+        let mut buf = [0u8; 4];
+        assert_eq!(kern.block_read(id, 0, &mut buf)?, 0);
+        Ok(buf)
+    };
+
+    let mine = kern.block_create(IPLD_RAW, b"mine")?;
+    assert_eq!(mine, 1);
+
+    // the return value of a successful send is registered under a new handle in the caller
+    let ret = match kern.send(
+        &Address::new_id(1000),
+        2,
+        NO_DATA_BLOCK_ID,
+        &0.into(),
+        SendOptions::default(),
+    )? {
+        SendResult::Return(id, stat) => {
+            assert_eq!((stat.codec, stat.size), (IPLD_RAW, 4));
+            id
+        }
+        SendResult::Abort(code) => panic!("unexpected abort: {}", code),
+    };
+    assert_eq!(ret, 2);
+    assert_eq!(read(&mut kern, ret)?, *b"retn");
+
+    // the callee's handles (1 for "intr" and 2 for "retn") don't leak into the caller
+    assert_eq!(read(&mut kern, mine)?, *b"mine");
+    expect_syscall_err!(InvalidHandle, kern.block_stat(3));
+
+    // blocks created by an aborted send are dropped
+    assert!(matches!(
+        kern.send(&Address::new_id(1001), 2, NO_DATA_BLOCK_ID, &0.into(), SendOptions::default())?,
+        SendResult::Abort(code) if code.value() == 16
+    ));
+    expect_syscall_err!(InvalidHandle, kern.block_stat(3));
+    assert_eq!(read(&mut kern, ret)?, *b"retn");
+
+    Ok(())
+}
+
+#[test]
+fn unresolvable_recipient() -> anyhow::Result<()> {
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let cm = build_call_manager(&mut config, &[])?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // sends to actors that don't exist (and can't be created) fail with a syscall error, not an
+    // abort receipt
+    let res = kern.send(
+        &Address::new_id(1000),
+        1,
+        NO_DATA_BLOCK_ID,
+        &0.into(),
+        SendOptions::default(),
+    );
+    assert!(
+        matches!(
+            res,
+            Err(ExecutionError::Syscall(SyscallError(
+                _,
+                ErrorNumber::NotFound
+            )))
+        ),
+        "{:?}",
+        res.map(|_| ())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn send_gas_limit() -> anyhow::Result<()> {
+    let noop = actor_wasm(0);
+    let spinner = spinner_wasm();
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &noop), (1001, &spinner)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // the callee runs out of the gas it was given, which only aborts the send
+    let limit = Gas::new(10_000);
+    let available = kern.gas_available();
+    assert!(matches!(
+        kern.send(&Address::new_id(1001), 1, NO_DATA_BLOCK_ID, &0.into(), SendOptions { gas_limit: Some(limit), ..Default::default() })?,
+        SendResult::Abort(code) if code == ExitCode::SYS_OUT_OF_GAS
+    ));
+    assert_eq!(kern.gas_available(), available - limit);
+
+    // the caller keeps executing with the rest of its gas
+    assert!(matches!(
+        kern.send(
+            &Address::new_id(1000),
+            1,
+            NO_DATA_BLOCK_ID,
+            &0.into(),
+            SendOptions::default()
+        )?,
+        SendResult::Return(..)
+    ));
+    assert!(kern.gas_available() > Gas::new(0));
+
+    Ok(())
+}
+
+#[test]
+fn invocation_gas_limit() -> anyhow::Result<()> {
+    let reader = u64_reader_wasm("gas", "limit");
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &reader)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+    kern.charge_gas("before", Gas::new(1234))?;
+
+    // the callee sees the gas it was granted, not the gas used before the send plus its grant
+    let limit = Gas::new(1_000_000);
+    assert!(matches!(
+        kern.send(&Address::new_id(1000), 1, NO_DATA_BLOCK_ID, &0.into(), SendOptions { gas_limit: Some(limit), ..Default::default() })?,
+        SendResult::Abort(code) if code.value() as i64 == limit.as_milligas()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn expected_return_codec() -> anyhow::Result<()> {
+    let reader = return_codec_reader_wasm();
+    let actors: [(ActorID, &[u8]); 1] = [(1000, &reader)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // the callee sees the codec hint passed by the caller, or DAG_CBOR by default
+    for (hint, expected) in [(Some(IPLD_RAW), IPLD_RAW), (None, DAG_CBOR)] {
+        assert!(matches!(
+            kern.send(&Address::new_id(1000), 1, NO_DATA_BLOCK_ID, &0.into(), SendOptions { return_codec: hint, ..Default::default() })?,
+            SendResult::Abort(code) if code.value() as u64 == expected
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn legacy_send() -> anyhow::Result<()> {
+    let reader = return_codec_reader_wasm();
+    let relay = legacy_relay_wasm(1000);
+    let actors: [(ActorID, &[u8]); 2] = [(1000, &reader), (1001, &relay)];
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager(&mut config, &actors)?;
+
+    // actors built against the original send syscall still link, and send with the default options
+    let res = cm.send::<TestingKernel>(
+        100,
+        Address::new_id(1001),
+        1,
+        None,
+        &0.into(),
+        SendOptions::default(),
+    )?;
+    assert!(
+        matches!(res, InvocationResult::Failure(code) if code.value() as u64 == DAG_CBOR),
+        "{:?}",
+        res
+    );
+    assert_eq!(cm.invocation_count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn failed_subcall_gas_policy() -> anyhow::Result<()> {
+    let aborter = block_returner_wasm(true);
+
+    // Sends to the aborting actor under the given policy, returning the gas used by the caller
+    // and the gas attributed to each actor.
+    let send_failing = |policy| -> anyhow::Result<(Gas, Vec<(ActorID, Gas)>)> {
+        let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+        config.override_failed_subcall_gas_policy(policy);
+        let cm = build_call_manager(&mut config, &[(1001, &aborter)])?;
+        let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+        assert!(matches!(
+            kern.send(&Address::new_id(1001), 2, NO_DATA_BLOCK_ID, &0.into(), SendOptions::default())?,
+            SendResult::Abort(code) if code.value() == 16
+        ));
+        let gas_used = kern.gas_used();
+        let (ret, _) = kern.into_inner().0.finish();
+        Ok((gas_used, ret.gas_by_actor))
+    };
+
+    let (all, all_by_actor) = send_failing(FailedSubcallGasPolicy::ChargeAll)?;
+    assert!(all > Gas::new(0));
+
+    let (half, half_by_actor) = send_failing(FailedSubcallGasPolicy::CapAtHalf)?;
+    assert_eq!(half, all - Gas::from_milligas(all.as_milligas() / 2));
+    // the refund is deducted from the caller
+    assert_eq!(half_by_actor, [(100, all_by_actor[0].1 - (all - half))]);
+
+    // the penalty is charged on top of the consumed gas, never refunding it
+    let (flat, _) = send_failing(FailedSubcallGasPolicy::FlatPenalty(Gas::new(10)))?;
+    assert_eq!(flat, all + Gas::new(10));
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct RecordingObserver(Mutex<Vec<(ActorID, Cid)>>);
+
+impl StateWriteObserver for RecordingObserver {
+    fn on_actor_write(&self, id: ActorID, new_state: &Cid) {
+        self.0.lock().unwrap().push((id, *new_state));
+    }
+}
+
+impl RecordingObserver {
+    fn take(&self) -> Vec<(ActorID, Cid)> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+#[test]
+fn state_write_observer() -> anyhow::Result<()> {
+    let observer = Arc::new(RecordingObserver::default());
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let mut cm = build_call_manager_with_context(&mut config, &[], |ctx| {
+        ctx.set_state_write_observer(observer.clone());
+    })?;
+
+    let ok = || Ok(InvocationResult::Return(None));
+    let failed = || Ok(InvocationResult::Failure(ExitCode::USR_ASSERTION_FAILED));
+
+    cm.with_transaction(|cm| {
+        cm.record_state_write(1000, state_cid(1));
+        // reverted writes are dropped, even if they were committed by an inner transaction
+        cm.with_transaction(|cm| {
+            cm.record_state_write(1001, state_cid(2));
+            cm.with_transaction(|cm| {
+                cm.record_state_write(1002, state_cid(3));
+                ok()
+            })?;
+            failed()
+        })?;
+        cm.with_transaction(|cm| {
+            cm.record_state_write(1003, state_cid(4));
+            ok()
+        })?;
+        // nothing is reported until the outermost transaction commits
+        assert_eq!(observer.take(), vec![]);
+        ok()
+    })?;
+    assert_eq!(
+        observer.take(),
+        vec![(1000, state_cid(1)), (1003, state_cid(4))]
+    );
+
+    cm.with_transaction(|cm| {
+        cm.record_state_write(1000, state_cid(5));
+        failed()
+    })?;
+    assert_eq!(observer.take(), vec![]);
+
+    // writes outside of a transaction are committed immediately
+    cm.record_state_write(1000, state_cid(6));
+    assert_eq!(observer.take(), vec![(1000, state_cid(6))]);
+
+    Ok(())
+}
+
+#[test]
+fn send_flags() -> anyhow::Result<()> {
+    let setter = root_setter_wasm(&state_cid(1));
+    let relay = flagged_relay_wasm(1000, 0);
+    let read_only_relay = flagged_relay_wasm(1000, SendFlags::READ_ONLY.bits());
+    let unknown_relay = flagged_relay_wasm(1000, 1 << 5);
+    let actors: [(ActorID, &[u8]); 4] = [
+        (1000, &setter),
+        (1001, &relay),
+        (1002, &read_only_relay),
+        (1003, &unknown_relay),
+    ];
+
+    let send = |kern: &mut TestingKernel, to, params, value: u64, flags| {
+        kern.send(
+            &Address::new_id(to),
+            1,
+            params,
+            &value.into(),
+            SendOptions {
+                flags,
+                ..Default::default()
+            },
+        )
+    };
+    let exit_code = |res: SendResult| match res {
+        SendResult::Return(..) => ExitCode::OK.value(),
+        SendResult::Abort(code) => code.value(),
+    };
+    let set_root_ok = 16;
+    let set_root_illegal = 16 + ErrorNumber::IllegalOperation as u32;
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+
+    // read-only sends can't modify state, including in nested sends
+    let res = send(&mut kern, 1000, NO_DATA_BLOCK_ID, 0, SendFlags::READ_ONLY)?;
+    assert_eq!(exit_code(res), set_root_illegal);
+    let res = send(&mut kern, 1002, NO_DATA_BLOCK_ID, 0, SendFlags::empty())?;
+    assert_eq!(exit_code(res), set_root_illegal);
+    let res = send(&mut kern, 1001, NO_DATA_BLOCK_ID, 0, SendFlags::READ_ONLY)?;
+    assert_eq!(exit_code(res), set_root_illegal);
+    // or transfer value
+    expect_syscall_err!(
+        IllegalArgument,
+        send(&mut kern, 1000, NO_DATA_BLOCK_ID, 1, SendFlags::READ_ONLY).map(|_| ())
+    );
+    // and read-only mode doesn't leak into later sends
+    let res = send(&mut kern, 1001, NO_DATA_BLOCK_ID, 0, SendFlags::empty())?;
+    assert_eq!(exit_code(res), set_root_ok);
+
+    // unknown flags are rejected by the send syscall
+    let res = send(&mut kern, 1003, NO_DATA_BLOCK_ID, 0, SendFlags::empty())?;
+    assert_eq!(exit_code(res), 16 + ErrorNumber::IllegalArgument as u32);
+
+    // value-only sends don't invoke the recipient, and can't have parameters
+    let cm = build_call_manager(&mut config, &actors)?;
+    let mut kern = TestingKernel::new(cm, BlockRegistry::default(), 100, 100, 0, 0.into());
+    let res = send(&mut kern, 1000, NO_DATA_BLOCK_ID, 0, SendFlags::VALUE_ONLY)?;
+    assert_eq!(exit_code(res), ExitCode::OK.value());
+    let params = kern.block_create(DAG_CBOR, &[0x80])?;
+    expect_syscall_err!(
+        IllegalArgument,
+        send(&mut kern, 1000, params, 0, SendFlags::VALUE_ONLY).map(|_| ())
+    );
+    let (cm, _) = kern.into_inner();
+    assert_eq!(cm.invocation_count(), 0);
+
+    Ok(())
+}
+
+fn state_cid(n: u64) -> Cid {
+    Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&n.to_be_bytes()))
+}
@@ -1,4 +1,5 @@
 mod ops;
+mod replay;
 
 use std::cell::RefCell;
 use std::rc::Rc;
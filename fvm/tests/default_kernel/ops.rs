@@ -3,15 +3,80 @@ use super::*;
 mod ipld {
 
     use cid::Cid;
-    use fvm::kernel::IpldBlockOps;
+    use fvm::kernel::{GasOps, IpldBlockOps};
     use fvm::machine::Machine;
     use fvm_ipld_blockstore::Blockstore;
-    use fvm_ipld_encoding::DAG_CBOR;
-    use multihash::MultihashDigest;
+    use fvm_ipld_encoding::{to_vec, DAG_CBOR};
+    use fvm_shared::{IDENTITY_HASH, IPLD_RAW};
+    use multihash::{Multihash, MultihashDigest};
     use pretty_assertions::{assert_eq, assert_ne};
 
     use super::*;
 
+    #[test]
+    fn verify_cid() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let data = b"some content";
+        for code in [Code::Blake2b256, Code::Sha2_256] {
+            let cid = Cid::new_v1(IPLD_RAW, code.digest(data));
+            assert!(kern.verify_cid(&cid, data)?);
+            // the codec isn't checked
+            assert!(kern.verify_cid(&Cid::new_v1(DAG_CBOR, code.digest(data)), data)?);
+
+            // tampered content doesn't match
+            assert!(!kern.verify_cid(&cid, b"some c0ntent")?);
+            assert!(!kern.verify_cid(&cid, b"")?);
+        }
+
+        // truncated and empty digests are rejected
+        for len in [31, 20, 2, 1, 0] {
+            let hash = Code::Blake2b256.digest(data).truncate(len);
+            assert!(!kern.verify_cid(&Cid::new_v1(IPLD_RAW, hash), data)?);
+        }
+
+        // unsupported hash functions are rejected
+        let cid = Cid::new_v1(IPLD_RAW, Code::Sha2_512.digest(data));
+        expect_syscall_err!(IllegalCid, kern.verify_cid(&cid, data));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_measure() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        // the measured size matches the size of the created block
+        let blocks: [(u64, &[u8]); 3] = [
+            (DAG_CBOR, b"\x83\x01\x02\x03"),
+            (IPLD_RAW, b"raw"),
+            (IPLD_RAW, b""),
+        ];
+        let sizes = blocks
+            .iter()
+            .map(|(codec, data)| kern.block_measure(*codec, data))
+            .collect::<Result<Vec<_>, _>>()?;
+        for ((codec, data), size) in blocks.iter().zip(sizes) {
+            let id = kern.block_create(*codec, data)?;
+            assert_eq!(kern.block_stat(id)?.size, size);
+        }
+        // and measuring doesn't use up block handles
+        assert_eq!(kern.block_create(IPLD_RAW, b"")?, 4);
+
+        // the codec must be allowed
+        expect_syscall_err!(IllegalCodec, kern.block_measure(0x70, b"data"));
+
+        // and the charge doesn't depend on the size of the data
+        let before = kern.gas_used();
+        kern.block_measure(IPLD_RAW, &[0u8; 1 << 16])?;
+        assert_eq!(
+            kern.gas_used() - before,
+            kern.price_list().on_block_measure().total()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn roundtrip() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -114,6 +179,112 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn create_dedup() -> anyhow::Result<()> {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.ctx.enable_block_create_dedup();
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        let block = "foo".as_bytes();
+
+        let id = kern.block_create(DAG_CBOR, block)?;
+        let id1 = kern.block_create(DAG_CBOR, block)?;
+        assert_eq!(id, id1, "identical blocks should share a block id");
+
+        // different codec or content still gets a new handle
+        let id2 = kern.block_create(IPLD_RAW, block)?;
+        let id3 = kern.block_create(DAG_CBOR, "bar".as_bytes())?;
+        assert_eq!(id2, 2);
+        assert_eq!(id3, 3);
+
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(
+            call_manager.test_data.borrow().charge_gas_calls,
+            4,
+            "deduplicated creates should still be charged"
+        );
+
+        // without dedup, identical blocks get distinct ids
+        let (mut kern, _) = build_inspecting_test()?;
+        let id = kern.block_create(DAG_CBOR, block)?;
+        let id1 = kern.block_create(DAG_CBOR, block)?;
+        assert_ne!(id, id1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_strict_cbor() -> anyhow::Result<()> {
+        // `1`, with a non-minimal integer encoding
+        let block = [0x18, 0x01];
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&block));
+        let canonical = [0x01];
+        let canonical_cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&canonical));
+
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.ctx.enable_strict_cbor();
+        let bs = call_manager.machine.blockstore();
+        bs.put_keyed(&cid, &block)?;
+        bs.put_keyed(&canonical_cid, &canonical)?;
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        expect_syscall_err!(IllegalArgument, kern.block_open(&cid));
+        kern.block_open(&canonical_cid)?;
+
+        // without strict mode, non-canonical blocks can be opened
+        let (call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.blockstore().put_keyed(&cid, &block)?;
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+        let (_, stat) = kern.block_open(&cid)?;
+        assert_eq!(stat.size, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_max_cbor_depth() -> anyhow::Result<()> {
+        // an integer wrapped in `depth` single-element arrays
+        let nested = |depth: usize| {
+            let mut block = vec![0x81; depth];
+            block.push(0x00);
+            let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&block));
+            (cid, block)
+        };
+        let (shallow_cid, shallow) = nested(8);
+        let (deep_cid, deep) = nested(9);
+        let (pathological_cid, pathological) = nested(100_000);
+
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.ctx.max_cbor_depth = 8;
+        let bs = call_manager.machine.blockstore();
+        bs.put_keyed(&shallow_cid, &shallow)?;
+        bs.put_keyed(&deep_cid, &deep)?;
+        bs.put_keyed(&pathological_cid, &pathological)?;
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        kern.block_open(&shallow_cid)?;
+        expect_syscall_err!(IllegalArgument, kern.block_open(&deep_cid));
+        expect_syscall_err!(IllegalArgument, kern.block_open(&pathological_cid));
+
+        // the depth of raw blocks isn't checked
+        let raw_cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&deep));
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.machine.ctx.max_cbor_depth = 8;
+        call_manager
+            .machine
+            .blockstore()
+            .put_keyed(&raw_cid, &deep)?;
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+        kern.block_open(&raw_cid)?;
+
+        Ok(())
+    }
+
     #[test]
     fn create_unexpected() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -232,6 +403,77 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn link_strict() -> anyhow::Result<()> {
+        let present = to_vec(&"present")?;
+        let present_cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&present));
+        let dangling_cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b"dangling"));
+        // an inline block, linking to the dangling block
+        let inline_cid = Cid::new_v1(
+            DAG_CBOR,
+            Multihash::wrap(IDENTITY_HASH, &to_vec(&(1u8, dangling_cid))?)?,
+        );
+
+        let new_kernel = |strict: bool| -> anyhow::Result<TestingKernel> {
+            let (mut call_manager, _) = DummyCallManager::new_stub();
+            if strict {
+                call_manager.machine.ctx.enable_strict_block_links();
+            }
+            call_manager
+                .machine
+                .blockstore()
+                .put_keyed(&present_cid, &present)?;
+            Ok(TestingKernel::new(
+                call_manager,
+                BlockRegistry::default(),
+                0,
+                0,
+                0,
+                0.into(),
+            ))
+        };
+        let link = |kern: &mut TestingKernel, data: Vec<u8>| {
+            let id = kern.block_create(DAG_CBOR, &data)?;
+            kern.block_link(id, Code::Blake2b256.into(), 32)
+        };
+
+        let mut kern = new_kernel(true)?;
+        // blocks linking to present blocks (or nothing at all) can be linked
+        link(&mut kern, to_vec(&(present_cid, "foo"))?)?;
+        link(&mut kern, to_vec(&"foo")?)?;
+        // but not blocks with dangling links, even inside inline blocks
+        expect_syscall_err!(
+            NotFound,
+            link(&mut kern, to_vec(&vec![present_cid, dangling_cid])?)
+        );
+        expect_syscall_err!(NotFound, link(&mut kern, to_vec(&inline_cid)?));
+        // and a rejected block isn't stored
+        let rejected = to_vec(&dangling_cid)?;
+        expect_syscall_err!(NotFound, link(&mut kern, rejected.clone()));
+        let rejected_cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&rejected));
+        let (call_manager, _) = kern.into_inner();
+        assert!(!call_manager.machine.blockstore().has(&rejected_cid)?);
+
+        // links aren't checked by default
+        let mut kern = new_kernel(false)?;
+        link(&mut kern, to_vec(&dangling_cid)?)?;
+
+        // each link looked up in the blockstore is charged as a block open, but inline ones aren't
+        let data = to_vec(&(
+            present_cid,
+            present_cid,
+            Cid::new_v1(DAG_CBOR, Multihash::wrap(IDENTITY_HASH, &present)?),
+        ))?;
+        let mut lax = new_kernel(false)?;
+        link(&mut lax, data.clone())?;
+        let mut strict = new_kernel(true)?;
+        link(&mut strict, data)?;
+        let open = strict.price_list().on_block_open_base().total();
+        assert_eq!(strict.gas_used() - lax.gas_used(), open + open);
+
+        Ok(())
+    }
+
     #[test]
     fn read() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -427,6 +669,419 @@ mod ipld {
 
         Ok(())
     }
+
+    #[test]
+    fn read_field() -> anyhow::Result<()> {
+        use std::collections::BTreeMap;
+
+        use fvm::kernel::GasOps;
+        use fvm_ipld_encoding::{from_slice, to_vec, PathSegment};
+
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let mut entries = BTreeMap::new();
+        entries.insert("a".to_string(), vec![1u64, 2, 3]);
+        entries.insert("b".to_string(), vec![4u64, 5]);
+        let value = (entries, "padding".repeat(10));
+        let block = to_vec(&value)?;
+        let id = kern.block_create(DAG_CBOR, &block)?;
+
+        let path = [
+            PathSegment::Index(0),
+            PathSegment::Key("b".into()),
+            PathSegment::Index(1),
+        ];
+        let gas_before = kern.gas_used();
+        let field_id = kern.block_read_field(id, &path)?;
+        let gas_used = kern.gas_used() - gas_before;
+
+        // the field matches the value found by decoding the whole block
+        let decoded: (BTreeMap<String, Vec<u64>>, String) = from_slice(&block)?;
+        let expected = to_vec(&decoded.0["b"][1])?;
+        let mut buf = vec![0u8; expected.len()];
+        assert_eq!(kern.block_read(field_id, 0, &mut buf)?, 0);
+        assert_eq!(buf, expected);
+        assert_eq!(kern.block_stat(field_id)?.codec, DAG_CBOR);
+
+        // only the bytes up to the end of the field (the end of the map) are charged
+        let traversed = block.len() - to_vec(&value.1)?.len();
+        let price_list = kern.price_list();
+        assert_eq!(
+            gas_used,
+            price_list.on_block_read_field(traversed).total()
+                + price_list.on_block_create(expected.len()).total()
+        );
+
+        expect_syscall_err!(
+            NotFound,
+            kern.block_read_field(id, &[PathSegment::Key("b".into())])
+        );
+        let raw = kern.block_create(IPLD_RAW, &block)?;
+        expect_syscall_err!(IllegalCodec, kern.block_read_field(raw, &path));
+
+        Ok(())
+    }
+}
+
+mod crypto {
+    use fvm::kernel::CryptoOps;
+    use fvm_shared::address::Address;
+    use fvm_shared::crypto::signature::SignatureType;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn pubkey_to_address() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let secp_key = [
+            4, 148, 2, 250, 195, 126, 100, 50, 164, 22, 163, 160, 202, 84, 38, 181, 24, 90, 179,
+            178, 79, 97, 52, 239, 162, 92, 228, 135, 200, 45, 46, 78, 19, 191, 69, 37, 17, 224,
+            210, 36, 84, 33, 248, 97, 59, 193, 13, 114, 250, 33, 102, 102, 169, 108, 59, 193, 57,
+            32, 211, 255, 35, 63, 208, 188, 5,
+        ];
+        assert_eq!(
+            kern.pubkey_to_address(SignatureType::Secp256k1, &secp_key)?
+                .to_string(),
+            "f15ihq5ibzwki2b4ep2f46avlkrqzhpqgtga7pdrq"
+        );
+
+        let bls_key = [
+            173, 88, 223, 105, 110, 45, 78, 145, 234, 134, 200, 129, 233, 56, 186, 78, 168, 27, 57,
+            94, 18, 121, 123, 132, 185, 207, 49, 75, 149, 70, 112, 94, 131, 156, 122, 153, 214, 6,
+            178, 71, 221, 180, 249, 172, 122, 52, 20, 221,
+        ];
+        assert_eq!(
+            kern.pubkey_to_address(SignatureType::BLS, &bls_key)?,
+            Address::new_bls(&bls_key)?
+        );
+        assert_eq!(
+            kern.pubkey_to_address(SignatureType::BLS, &bls_key)?
+                .to_string(),
+            "f3vvmn62lofvhjd2ugzca6sof2j2ubwok6cj4xxbfzz4yuxfkgobpihhd2thlanmsh3w2ptld2gqkn2jvlss4a"
+        );
+
+        // keys must have the right length for their type
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.pubkey_to_address(SignatureType::BLS, &secp_key)
+        );
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.pubkey_to_address(SignatureType::Secp256k1, &bls_key)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pubkey_to_address_gas() -> anyhow::Result<()> {
+        use fvm::gas::{Gas, GasTracker};
+        use fvm::kernel::GasOps;
+
+        let gas_tracker = GasTracker::new(Gas::new(i64::MAX), Gas::new(0));
+        let (mut kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        let price_list = kern.price_list().clone();
+
+        let bls_key = [0u8; 48];
+        kern.pubkey_to_address(SignatureType::BLS, &bls_key)?;
+        let bls_gas = kern.gas_used();
+        assert_eq!(
+            bls_gas,
+            price_list.on_pubkey_to_address(SignatureType::BLS).total()
+        );
+
+        // the charge is fixed per key type, and taken even if the key is invalid
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.pubkey_to_address(SignatureType::Secp256k1, &bls_key)
+        );
+        assert_eq!(
+            kern.gas_used() - bls_gas,
+            price_list
+                .on_pubkey_to_address(SignatureType::Secp256k1)
+                .total()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_gas() -> anyhow::Result<()> {
+        use fvm::gas::{Gas, ScalingCost};
+        use fvm::kernel::GasOps;
+        use fvm_shared::crypto::hash::SupportedHashes;
+
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        let mut price_list = call_manager.machine.ctx.price_list.clone();
+        price_list
+            .set_hashing_cost(
+                SupportedHashes::Sha2_256,
+                ScalingCost {
+                    flat: Gas::new(100),
+                    scale: Gas::new(2),
+                },
+            )
+            .set_hashing_cost(
+                SupportedHashes::Blake3_256,
+                ScalingCost {
+                    flat: Gas::new(10),
+                    scale: Gas::new(1),
+                },
+            );
+        call_manager.machine.ctx.price_list = Box::leak(Box::new(price_list));
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        // each hash function is charged at its own rate
+        let digest = kern.hash(SupportedHashes::Sha2_256 as u64, b"abc")?;
+        assert_eq!(
+            digest,
+            [
+                186, 120, 22, 191, 143, 1, 207, 234, 65, 65, 64, 222, 93, 174, 34, 35, 176, 3, 97,
+                163, 150, 23, 122, 156, 180, 16, 255, 97, 242, 0, 21, 173
+            ]
+        );
+        assert_eq!(kern.gas_used(), Gas::new(100 + 2 * 3));
+
+        kern.hash(SupportedHashes::Blake3_256 as u64, b"abc")?;
+        assert_eq!(kern.gas_used(), Gas::new(106 + 10 + 3));
+
+        // unsupported hash functions aren't charged
+        expect_syscall_err!(IllegalArgument, kern.hash(0x13, b"abc"));
+        assert_eq!(kern.gas_used(), Gas::new(119));
+
+        Ok(())
+    }
+}
+
+mod sself {
+    use fvm::kernel::SelfOps;
+    use fvm::state_tree::ActorState;
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::address::Address;
+    use multihash::Code;
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use super::*;
+
+    #[test]
+    fn state_commitment_with_balance() -> anyhow::Result<()> {
+        let mut commitments = Vec::new();
+        for balance in [0, 1000] {
+            let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+            let bs = call_manager.machine.state_tree.store();
+            let code = bs.put_cbor(&"code", Code::Blake2b256)?;
+            let state = bs.put_cbor(&"state", Code::Blake2b256)?;
+            call_manager.machine.state_tree.set_actor(
+                &Address::new_id(100),
+                ActorState::new(code, state, balance.into(), 0),
+            )?;
+            let mut kern =
+                TestingKernel::new(call_manager, BlockRegistry::default(), 0, 100, 0, 0.into());
+
+            commitments.push((kern.root()?, kern.state_commitment_with_balance()?));
+        }
+
+        // same state root, but the balance is committed to
+        assert_eq!(commitments[0].0, commitments[1].0);
+        assert_ne!(commitments[0].1, commitments[1].1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_counter() -> anyhow::Result<()> {
+        use fvm::kernel::Kernel;
+
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        let bs = call_manager.machine.state_tree.store();
+        let code = bs.put_cbor(&"code", Code::Blake2b256)?;
+        let state = bs.put_cbor(&(Vec::<u64>::new(), "data"), Code::Blake2b256)?;
+        call_manager.machine.state_tree.set_actor(
+            &Address::new_id(100),
+            ActorState::new(code, state, 0.into(), 0),
+        )?;
+
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 100, 0, 0.into());
+        let values = (0..3)
+            .map(|_| kern.bump_counter(1))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(values, vec![1, 2, 3]);
+
+        // the counters persist across invocations
+        let (call_manager, _) = kern.into_inner();
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 100, 0, 0.into());
+        assert_eq!(kern.bump_counter(1)?, 4);
+        assert_eq!(kern.bump_counter(0)?, 1);
+
+        // and live in the actor's state, alongside the rest of it
+        let root = kern.root()?;
+        let (call_manager, _) = kern.into_inner();
+        let state: (Vec<u64>, String) = call_manager
+            .machine
+            .state_tree
+            .store()
+            .get_cbor(&root)?
+            .unwrap();
+        assert_eq!(state, (vec![1, 4], "data".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_counter_invalid() -> anyhow::Result<()> {
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        let bs = call_manager.machine.state_tree.store();
+        let code = bs.put_cbor(&"code", Code::Blake2b256)?;
+        let state = bs.put_cbor(&"no counters", Code::Blake2b256)?;
+        call_manager.machine.state_tree.set_actor(
+            &Address::new_id(100),
+            ActorState::new(code, state, 0.into(), 0),
+        )?;
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 100, 0, 0.into());
+
+        expect_syscall_err!(IllegalOperation, kern.bump_counter(0));
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.bump_counter(fvm_shared::MAX_COUNTER_SLOTS)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn state_commitment_after_deletion() -> anyhow::Result<()> {
+        let (call_manager, _) = dummy::DummyCallManager::new_stub();
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 100, 0, 0.into());
+
+        expect_syscall_err!(IllegalOperation, kern.state_commitment_with_balance());
+
+        Ok(())
+    }
+}
+
+mod actor {
+    use fvm::kernel::ActorOps;
+    use fvm::state_tree::ActorState;
+    use fvm_ipld_encoding::CborStore;
+    use fvm_shared::actor::builtin::Type;
+    use fvm_shared::address::Address;
+    use multihash::Code;
+
+    use super::*;
+
+    #[test]
+    fn is_account() -> anyhow::Result<()> {
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        let machine = &mut call_manager.machine;
+        let bs = machine.state_tree.store();
+        let account_code = bs.put_cbor(&"account", Code::Blake2b256)?;
+        let multisig_code = bs.put_cbor(&"multisig", Code::Blake2b256)?;
+        let state = bs.put_cbor(&(), Code::Blake2b256)?;
+        machine.builtin_actors.insert(account_code, Type::Account);
+        machine.builtin_actors.insert(multisig_code, Type::Multisig);
+        for (id, code) in [(100, account_code), (101, multisig_code)] {
+            machine.state_tree.set_actor(
+                &Address::new_id(id),
+                ActorState::new(code, state, 0.into(), 0),
+            )?;
+        }
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        assert!(kern.is_account(&Address::new_id(100))?);
+        assert!(!kern.is_account(&Address::new_id(101))?);
+        expect_syscall_err!(NotFound, kern.is_account(&Address::new_id(102)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn nonce_of() -> anyhow::Result<()> {
+        use fvm::machine::Machine;
+
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        let machine = &mut call_manager.machine;
+        let bs = machine.state_tree.store();
+        let account_code = bs.put_cbor(&"account", Code::Blake2b256)?;
+        let multisig_code = bs.put_cbor(&"multisig", Code::Blake2b256)?;
+        let state = bs.put_cbor(&(), Code::Blake2b256)?;
+        machine.builtin_actors.insert(account_code, Type::Account);
+        machine.builtin_actors.insert(multisig_code, Type::Multisig);
+        // an account that has sent three messages, and a multisig with a (bogus) non-zero nonce
+        for (id, code, sequence) in [(100, account_code, 3), (101, multisig_code, 7)] {
+            machine.state_tree.set_actor(
+                &Address::new_id(id),
+                ActorState::new(code, state, 0.into(), sequence),
+            )?;
+        }
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        assert_eq!(kern.nonce_of(&Address::new_id(100))?, 3);
+        assert_eq!(kern.nonce_of(&Address::new_id(101))?, 0);
+        assert_eq!(kern.nonce_of(&Address::new_id(102))?, 0);
+
+        // each lookup is charged
+        let (call_manager, _) = kern.into_inner();
+        let lookup = call_manager
+            .machine
+            .context()
+            .price_list
+            .on_actor_lookup()
+            .total();
+        assert_eq!(call_manager.gas_tracker.gas_used(), lookup * 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_address_gas() -> anyhow::Result<()> {
+        use fvm::gas::Gas;
+        use fvm::kernel::GasOps;
+        use fvm_ipld_hamt::{BytesKey, Hamt};
+        use fvm_shared::{ActorID, HAMT_BIT_WIDTH};
+
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        let mut price_list = call_manager.machine.ctx.price_list.clone();
+        price_list.set_address_resolution_cost(Gas::new(1000));
+        call_manager.machine.ctx.price_list = Box::leak(Box::new(price_list));
+
+        // map a key address to an ID in the init actor's address map
+        let (key, id) = (Address::new_secp256k1(&[4; 65])?, 100);
+        let machine = &mut call_manager.machine;
+        let bs = machine.state_tree.store();
+        let mut address_map = Hamt::<_, ActorID>::new_with_bit_width(bs, HAMT_BIT_WIDTH);
+        address_map.set(BytesKey(key.to_bytes()), id)?;
+        let address_map = address_map.flush()?;
+        let init_code = bs.put_cbor(&"init", Code::Blake2b256)?;
+        let init_state = bs.put_cbor(&(address_map, id + 1, "test"), Code::Blake2b256)?;
+        machine.state_tree.set_actor(
+            &Address::new_id(1),
+            ActorState::new(init_code, init_state, 0.into(), 0),
+        )?;
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        // the first resolution loads the init actor's state, and the second hits the cache, but
+        // both are charged the same
+        assert_eq!(kern.resolve_address(&key)?, Some(id));
+        assert_eq!(kern.gas_used(), Gas::new(1000));
+        assert_eq!(kern.resolve_address(&key)?, Some(id));
+        assert_eq!(kern.gas_used(), Gas::new(2000));
+
+        // ID addresses need no resolution, so they're free
+        assert_eq!(kern.resolve_address(&Address::new_id(id))?, Some(id));
+        assert_eq!(kern.gas_used(), Gas::new(2000));
+
+        Ok(())
+    }
 }
 
 mod gas {
@@ -483,6 +1138,24 @@ mod gas {
         Ok(())
     }
 
+    #[test]
+    fn limit() -> anyhow::Result<()> {
+        let limit = Gas::new(123456);
+        let gas_tracker = GasTracker::new(limit, Gas::new(100));
+
+        let (mut kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        assert_eq!(kern.gas_limit(), limit);
+        assert_eq!(kern.gas_limit() - kern.gas_available(), kern.gas_used());
+
+        // the limit is fixed, so at any checkpoint `limit - available` is the gas used so far
+        kern.charge_gas("checkpoint", Gas::from_milligas(4321))?;
+        assert_eq!(kern.gas_limit(), limit);
+        assert_eq!(kern.gas_limit() - kern.gas_available(), kern.gas_used());
+        assert_eq!(kern.gas_used(), Gas::new(100) + Gas::from_milligas(4321));
+
+        Ok(())
+    }
+
     #[test]
     fn charge() -> anyhow::Result<()> {
         let test_gas = Gas::new(123456);
@@ -546,3 +1219,258 @@ mod gas {
         Ok(())
     }
 }
+
+mod message {
+    use fvm::kernel::{Block, IpldBlockOps, MessageOps};
+    use fvm_ipld_encoding::DAG_CBOR;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn read_params() -> anyhow::Result<()> {
+        let params = b"some params";
+        let mut blocks = BlockRegistry::default();
+        let id = blocks.put(Block::new(DAG_CBOR, &params[..]))?;
+
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.params = Some(Block::new(DAG_CBOR, &params[..]));
+        let mut kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, 0.into());
+
+        let mut buf = [0u8; 32];
+        let size = kern.msg_read_params(&mut buf)?;
+        let mut block_buf = [0u8; 32];
+        let remaining = kern.block_read(id, 0, &mut block_buf)?;
+
+        assert_eq!(size, params.len() as u32);
+        assert_eq!(remaining, params.len() as i32 - block_buf.len() as i32);
+        assert_eq!(buf, block_buf);
+
+        // a short buffer is filled, and the full size is still reported
+        let mut short = [0u8; 4];
+        assert_eq!(kern.msg_read_params(&mut short)?, params.len() as u32);
+        assert_eq!(&short, &params[..4]);
+
+        // without parameters, nothing is read
+        let (mut kern, _) = build_inspecting_test()?;
+        let mut buf = [0u8; 4];
+        assert_eq!(kern.msg_read_params(&mut buf)?, 0);
+        assert_eq!(buf, [0u8; 4]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn params_codec() -> anyhow::Result<()> {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.params = Some(Block::new(DAG_CBOR, &b"some params"[..]));
+        let kern = TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+        assert_eq!(kern.msg_params_codec(), DAG_CBOR);
+
+        // without parameters, there's no codec
+        let (kern, _) = build_inspecting_test()?;
+        assert_eq!(kern.msg_params_codec(), 0);
+
+        // even if the block registry already holds a block
+        let mut blocks = BlockRegistry::default();
+        blocks.put(Block::new(DAG_CBOR, &b"not params"[..]))?;
+        let (call_manager, _) = DummyCallManager::new_stub();
+        let kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, 0.into());
+        assert_eq!(kern.msg_params_codec(), 0);
+
+        Ok(())
+    }
+}
+
+mod network {
+    use fvm::kernel::CircSupplyOps;
+    use fvm_shared::econ::CircSupplyDetail;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn circ_supply_detail() -> anyhow::Result<()> {
+        let detail = CircSupplyDetail {
+            mined: 1000.into(),
+            vested: 500.into(),
+            burnt: 200.into(),
+            locked: 100.into(),
+        };
+
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager
+            .machine
+            .ctx
+            .set_circulating_supply_detail(detail.clone());
+        let kern = TestingKernel::new(call_manager, BlockRegistry::default(), 0, 0, 0, 0.into());
+
+        let reported = kern.circ_supply_detail()?;
+        assert_eq!(reported, detail);
+        assert_eq!(
+            reported.mined + reported.vested - reported.burnt - reported.locked,
+            kern.total_fil_circ_supply()?
+        );
+
+        // the breakdown is only available when provided by the client
+        let (kern, _) = build_inspecting_test()?;
+        expect_syscall_err!(IllegalOperation, kern.circ_supply_detail());
+
+        Ok(())
+    }
+}
+
+mod rand {
+    use fvm::externs::RandomnessFallback;
+    use fvm::kernel::RandomnessOps;
+    use fvm::Kernel;
+    use fvm_shared::address::Address;
+    use fvm_shared::{ActorID, MethodNum};
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use super::*;
+
+    fn build_kernel(
+        origin: Address,
+        nonce: u64,
+        actor: ActorID,
+        method: MethodNum,
+    ) -> TestingKernel {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager.origin = origin;
+        call_manager.nonce = nonce;
+        TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            actor,
+            method,
+            0.into(),
+        )
+    }
+
+    fn draw(kern: &mut TestingKernel, n: u64) -> anyhow::Result<Vec<[u8; 32]>> {
+        (0..n).map(|i| Ok(kern.get_local_randomness(i)?)).collect()
+    }
+
+    #[test]
+    fn local_randomness() -> anyhow::Result<()> {
+        let origin = Address::new_id(100);
+
+        let mut kern = build_kernel(origin, 1, 1000, 2);
+        let seq = draw(&mut kern, 4)?;
+
+        // the same invocation reproduces the same sequence
+        let mut kern1 = build_kernel(origin, 1, 1000, 2);
+        assert_eq!(seq, draw(&mut kern1, 4)?);
+
+        // draws within a sequence differ
+        assert_ne!(seq[0], seq[1]);
+
+        // changing any of the inputs changes the sequence
+        for mut other in [
+            build_kernel(Address::new_id(101), 1, 1000, 2),
+            build_kernel(origin, 2, 1000, 2),
+            build_kernel(origin, 1, 1001, 2),
+            build_kernel(origin, 1, 1000, 3),
+        ] {
+            assert_ne!(seq, draw(&mut other, 4)?);
+        }
+
+        // drawing randomness is charged
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(call_manager.test_data.borrow().charge_gas_calls, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn beacon_randomness_sources() -> anyhow::Result<()> {
+        let (mut call_manager, _) = DummyCallManager::new_stub();
+        call_manager
+            .machine
+            .ctx
+            .override_beacon_schedule(vec![(200, 2), (100, 1)]);
+        let mut kern =
+            TestingKernel::new(call_manager, BlockRegistry::default(), 0, 1000, 2, 0.into());
+
+        // each round draws from the source of the last switch at or before it
+        let source = |kern: &mut TestingKernel, round| -> anyhow::Result<u8> {
+            Ok(kern.get_randomness_from_beacon(1, round, b"entropy")?[0] - 1)
+        };
+        assert_eq!(source(&mut kern, 99)?, 0);
+        assert_eq!(source(&mut kern, 100)?, 1);
+        assert_eq!(source(&mut kern, 199)?, 1);
+        assert_eq!(source(&mut kern, 200)?, 2);
+        assert_eq!(source(&mut kern, 1000)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn randomness_fallback() -> anyhow::Result<()> {
+        let draw = |fallback, round, entropy: &[u8]| {
+            let (mut call_manager, _) = DummyCallManager::new_stub();
+            call_manager
+                .machine
+                .ctx
+                .override_randomness_fallback(fallback);
+            let mut kern =
+                TestingKernel::new(call_manager, BlockRegistry::default(), 0, 1000, 2, 0.into());
+            kern.get_randomness_from_beacon(1, round, entropy)
+        };
+
+        // by default, a missing beacon entry fails the syscall
+        expect_syscall_err!(
+            IllegalArgument,
+            draw(RandomnessFallback::Propagate, -1, b"entropy")
+        );
+
+        // but with the fallback, it yields a stable draw derived from the inputs
+        let fallback =
+            |round, entropy: &[u8]| draw(RandomnessFallback::DeterministicFallback, round, entropy);
+        let stable = fallback(-1, b"entropy")?;
+        assert_eq!(stable, fallback(-1, b"entropy")?);
+        assert_ne!(stable, fallback(-1, b"other")?);
+        assert_ne!(stable, fallback(-2, b"entropy")?);
+
+        // available beacon entries are unaffected
+        assert_eq!(
+            fallback(1, b"entropy")?,
+            draw(RandomnessFallback::Propagate, 1, b"entropy")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn chain_and_beacon_randomness_cached() -> anyhow::Result<()> {
+        let mut kern = build_kernel(Address::new_id(100), 1, 1000, 2);
+
+        // a repeated request is served from the cache, while new entropy goes to the externs
+        let chain = kern.get_randomness_from_tickets(1, 10, b"entropy")?;
+        assert_eq!(kern.get_randomness_from_tickets(1, 10, b"entropy")?, chain);
+        assert_ne!(kern.get_randomness_from_tickets(1, 10, b"other")?, chain);
+
+        // beacon randomness is cached separately from chain randomness
+        let beacon = kern.get_randomness_from_beacon(1, 10, b"entropy")?;
+        assert_ne!(beacon, chain);
+        assert_eq!(kern.get_randomness_from_beacon(1, 10, b"entropy")?, beacon);
+
+        let (call_manager, _) = kern.into_inner();
+        let externs = &call_manager.machine.externs;
+        assert_eq!(externs.chain_randomness_calls.get(), 2);
+        assert_eq!(externs.beacon_randomness_calls.get(), 1);
+
+        // every request is still charged
+        assert_eq!(call_manager.test_data.borrow().charge_gas_calls, 5);
+
+        // a new invocation starts with an empty cache
+        let mut kern = build_kernel(Address::new_id(100), 1, 1000, 2);
+        assert_eq!(kern.get_randomness_from_tickets(1, 10, b"entropy")?, chain);
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(call_manager.machine.externs.chain_randomness_calls.get(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use cid::Cid;
+use fvm::machine::{DefaultMachine, Engine, Machine, NetworkConfig};
+use fvm::state_tree::{ActorState, StateTree};
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::{to_vec, CborStore};
+use fvm_shared::address::Address;
+use fvm_shared::state::StateTreeVersion;
+use multihash::Code;
+
+use super::*;
+
+type TestingMachine = DefaultMachine<MemoryBlockstore, DummyExterns>;
+
+/// Build a default machine over an empty state-tree with no builtin actors.
+fn build_machine() -> anyhow::Result<TestingMachine> {
+    let bs = MemoryBlockstore::new();
+
+    let mut state_tree = StateTree::new(bs, StateTreeVersion::V4)?;
+    let root = state_tree.flush()?;
+    let bs = state_tree.into_store();
+
+    let manifest: Vec<(String, Cid)> = Vec::new();
+    let manifest_cid = bs.put_cbor(&manifest, Code::Blake2b256)?;
+    let actors_cid = bs.put_cbor(&(1, manifest_cid), Code::Blake2b256)?;
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    let ctx = config.override_actors(actors_cid).for_epoch(0, root);
+    let engine = Engine::new_default((&config).into())?;
+
+    TestingMachine::new(&engine, &ctx, bs, DummyExterns)
+}
+
+fn set_actor(machine: &mut TestingMachine, id: u64) -> anyhow::Result<()> {
+    let code = machine.blockstore().put_cbor(&"code", Code::Blake2b256)?;
+    let state = machine.blockstore().put_cbor(&id, Code::Blake2b256)?;
+    machine.state_tree_mut().set_actor(
+        &Address::new_id(id),
+        ActorState::new(code, state, 0.into(), 0),
+    )?;
+    Ok(())
+}
+
+#[test]
+fn discard_buffered_writes() -> anyhow::Result<()> {
+    let mut machine = build_machine()?;
+    let initial_root = machine.context().initial_state_root;
+
+    // speculative mutation, discarded before ever flushing.
+    set_actor(&mut machine, 1000)?;
+    let block = machine
+        .blockstore()
+        .put_cbor(&"speculative", Code::Blake2b256)?;
+    machine.discard_buffered_writes()?;
+
+    assert!(!machine.blockstore().has(&block)?);
+    assert!(machine
+        .state_tree()
+        .get_actor(&Address::new_id(1000))?
+        .is_none());
+    assert_eq!(machine.flush()?, initial_root);
+
+    // a flushed mutation survives a later discard.
+    set_actor(&mut machine, 1001)?;
+    let flushed_root = machine.flush()?;
+    assert_ne!(flushed_root, initial_root);
+
+    set_actor(&mut machine, 1002)?;
+    machine
+        .state_tree_mut()
+        .delete_actor(&Address::new_id(1001))?;
+    machine.discard_buffered_writes()?;
+
+    assert!(machine
+        .state_tree()
+        .get_actor(&Address::new_id(1001))?
+        .is_some());
+    assert!(machine
+        .state_tree()
+        .get_actor(&Address::new_id(1002))?
+        .is_none());
+    assert_eq!(machine.flush()?, flushed_root);
+
+    // already-persisted blocks are unaffected.
+    assert!(machine.into_store().into_inner().has(&flushed_root)?);
+
+    Ok(())
+}
+
+#[test]
+fn pending_writes() -> anyhow::Result<()> {
+    let mut machine = build_machine()?;
+    assert!(machine.pending_writes().is_empty());
+
+    // the actor's code and state blocks are buffered until the next flush.
+    set_actor(&mut machine, 1000)?;
+    let code = machine.blockstore().put_cbor(&"code", Code::Blake2b256)?;
+    let state = machine.blockstore().put_cbor(&1000u64, Code::Blake2b256)?;
+    let mut expected = vec![
+        (code, to_vec(&"code")?.len()),
+        (state, to_vec(&1000u64)?.len()),
+    ];
+    expected.sort();
+    assert_eq!(machine.pending_writes(), expected);
+    // listing them doesn't flush them.
+    assert_eq!(machine.pending_writes(), expected);
+
+    machine.flush()?;
+    assert!(machine.pending_writes().is_empty());
+
+    Ok(())
+}
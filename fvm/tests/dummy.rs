@@ -1,5 +1,5 @@
 use std::borrow::Borrow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 use anyhow::Context;
@@ -13,6 +13,7 @@ use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::CborStore;
 use fvm_shared::actor::builtin::Manifest;
 use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::state::StateTreeVersion;
 use fvm_shared::version::NetworkVersion;
 use multihash::Code;
@@ -56,12 +57,85 @@ impl Consensus for DummyExterns {
     }
 }
 
+/// `Externs` whose randomness is derived from the inputs, counting how many times each source of
+/// randomness is drawn from.
+#[derive(Default)]
+pub struct CountingExterns {
+    pub chain_randomness_calls: Cell<usize>,
+    pub beacon_randomness_calls: Cell<usize>,
+}
+
+impl CountingExterns {
+    fn randomness(source: u8, pers: i64, round: ChainEpoch, entropy: &[u8]) -> [u8; 32] {
+        let mut out = [source; 32];
+        out[1..9].copy_from_slice(&pers.to_be_bytes());
+        out[9..17].copy_from_slice(&round.to_be_bytes());
+        for (o, e) in out[17..].iter_mut().zip(entropy) {
+            *o = *e;
+        }
+        out
+    }
+}
+
+impl Externs for CountingExterns {}
+
+impl Rand for CountingExterns {
+    fn get_chain_randomness(
+        &self,
+        pers: i64,
+        round: ChainEpoch,
+        entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        self.chain_randomness_calls
+            .set(self.chain_randomness_calls.get() + 1);
+        Ok(Self::randomness(0, pers, round, entropy))
+    }
+
+    fn get_beacon_randomness(
+        &self,
+        pers: i64,
+        round: ChainEpoch,
+        entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        self.get_beacon_randomness_from_source(0, pers, round, entropy)
+    }
+
+    /// The first byte of beacon randomness is one more than the source it was drawn from. Beacon
+    /// entries for negative rounds are missing.
+    fn get_beacon_randomness_from_source(
+        &self,
+        source: u64,
+        pers: i64,
+        round: ChainEpoch,
+        entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        if round < 0 {
+            return Err(anyhow::anyhow!("missing beacon entry for round {}", round));
+        }
+        self.beacon_randomness_calls
+            .set(self.beacon_randomness_calls.get() + 1);
+        Ok(Self::randomness(1 + source as u8, pers, round, entropy))
+    }
+}
+
+impl Consensus for CountingExterns {
+    fn verify_consensus_fault(
+        &self,
+        h1: &[u8],
+        h2: &[u8],
+        extra: &[u8],
+    ) -> anyhow::Result<(Option<fvm_shared::consensus::ConsensusFault>, i64)> {
+        DummyExterns.verify_consensus_fault(h1, h2, extra)
+    }
+}
+
 /// Minimal *pseudo-functional* implementation of `Machine` for tests
 pub struct DummyMachine {
     pub engine: Engine,
     pub state_tree: StateTree<MemoryBlockstore>,
     pub ctx: MachineContext,
     pub builtin_actors: Manifest,
+    pub externs: CountingExterns,
 }
 
 impl DummyMachine {
@@ -99,6 +173,7 @@ impl DummyMachine {
             engine: Engine::new_default((&config).into())?,
             state_tree,
             builtin_actors: manifest,
+            externs: CountingExterns::default(),
         })
     }
 }
@@ -106,7 +181,7 @@ impl DummyMachine {
 impl Machine for DummyMachine {
     type Blockstore = MemoryBlockstore;
 
-    type Externs = DummyExterns;
+    type Externs = CountingExterns;
 
     fn engine(&self) -> &Engine {
         &self.engine
@@ -121,7 +196,7 @@ impl Machine for DummyMachine {
     }
 
     fn externs(&self) -> &Self::Externs {
-        &DummyExterns
+        &self.externs
     }
 
     fn builtin_actors(&self) -> &Manifest {
@@ -153,6 +228,26 @@ impl Machine for DummyMachine {
         todo!()
     }
 
+    fn discard_buffered_writes(&mut self) -> kernel::Result<()> {
+        todo!()
+    }
+
+    fn pending_writes(&self) -> Vec<(cid::Cid, usize)> {
+        todo!()
+    }
+
+    fn record_reads(&mut self) -> kernel::Result<cid::Cid> {
+        todo!()
+    }
+
+    fn recording_reads(&self) -> bool {
+        todo!()
+    }
+
+    fn take_reads(&mut self) -> std::collections::BTreeMap<cid::Cid, Vec<u8>> {
+        todo!()
+    }
+
     fn into_store(self) -> Self::Blockstore {
         self.state_tree.into_store()
     }
@@ -168,6 +263,8 @@ pub struct DummyCallManager {
     pub gas_tracker: GasTracker,
     pub origin: Address,
     pub nonce: u64,
+    /// The parameters of the current invocation.
+    pub params: Option<kernel::Block>,
     pub test_data: Rc<RefCell<TestData>>,
 }
 
@@ -188,6 +285,7 @@ impl DummyCallManager {
                 gas_tracker: GasTracker::new(Gas::new(i64::MAX), Gas::new(0)),
                 origin: Address::new_actor(&[]),
                 nonce: 0,
+                params: None,
                 test_data: rc,
             },
             cell_ref,
@@ -205,6 +303,7 @@ impl DummyCallManager {
                 gas_tracker,
                 origin: Address::new_actor(&[]),
                 nonce: 0,
+                params: None,
                 test_data: rc,
             },
             cell_ref,
@@ -224,6 +323,7 @@ impl CallManager for DummyCallManager {
             gas_tracker: GasTracker::new(Gas::new(i64::MAX), Gas::new(0)),
             origin,
             nonce,
+            params: None,
             test_data: rc,
         }
     }
@@ -235,6 +335,7 @@ impl CallManager for DummyCallManager {
         _method: fvm_shared::MethodNum,
         _params: Option<kernel::Block>,
         _value: &fvm_shared::econ::TokenAmount,
+        _options: kernel::SendOptions,
     ) -> kernel::Result<InvocationResult> {
         // Ok(InvocationResult::Return(None))
         todo!()
@@ -252,6 +353,8 @@ impl CallManager for DummyCallManager {
         (
             FinishRet {
                 gas_used: 0,
+                gas_by_actor: Vec::new(),
+                gas_profile: Vec::new(),
                 backtrace: Backtrace {
                     frames: Vec::new(),
                     cause: None,
@@ -298,4 +401,30 @@ impl CallManager for DummyCallManager {
     fn invocation_count(&self) -> u64 {
         todo!()
     }
+
+    fn expected_return_codec(&self) -> u64 {
+        todo!()
+    }
+
+    fn invocation_params(&self) -> Option<&kernel::Block> {
+        self.params.as_ref()
+    }
+
+    fn invocation_gas_limit(&self) -> Gas {
+        self.gas_tracker.gas_limit()
+    }
+
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    fn set_price_list(&mut self, _price_list: &'static fvm::gas::PriceList) {
+        todo!()
+    }
+
+    fn record_artifact(&mut self, _size: u64) -> bool {
+        todo!()
+    }
+
+    fn record_state_write(&mut self, _id: fvm_shared::ActorID, _state: cid::Cid) {}
 }
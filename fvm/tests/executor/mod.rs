@@ -0,0 +1,688 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use arbitrary::{Arbitrary, Unstructured};
+use cid::Cid;
+use fvm::call_manager::DefaultCallManager;
+use fvm::executor::{
+    compare_gas, ApplyKind, ApplyRet, ApplyRetFieldDiff, CorpusMessage, DefaultExecutor,
+    DeterminismFailure, Executor, ParallelExecutor,
+};
+use fvm::gas::{price_list_by_network_version, Gas};
+use fvm::kernel::{ExecutionError, SyscallError};
+use fvm::machine::{DefaultMachine, Engine, Machine, MachineContext, MachineExport, NetworkConfig};
+use fvm::state_tree::{ActorState, StateTree};
+use fvm::DefaultKernel;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::{CborStore, RawBytes};
+use fvm_shared::actor::builtin::Manifest;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::{ActorID, IPLD_RAW, METHOD_SEND};
+use multihash::{Code, MultihashDigest};
+use num_traits::Zero;
+use pretty_assertions::assert_eq;
+use proptest::prelude::*;
+
+use super::*;
+
+/// A blockstore that can be shared between machines on different threads.
+#[derive(Clone, Default)]
+struct SharedBlockstore(Arc<Mutex<HashMap<Cid, Vec<u8>>>>);
+
+impl Blockstore for SharedBlockstore {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.0.lock().unwrap().get(k).cloned())
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.0.lock().unwrap().insert(*k, block.into());
+        Ok(())
+    }
+}
+
+type TestingMachine = DefaultMachine<SharedBlockstore, DummyExterns>;
+type TestingKernel = DefaultKernel<DefaultCallManager<TestingMachine>>;
+
+const ACCOUNTS: [ActorID; 2] = [100, 101];
+const RECIPIENTS: [ActorID; 2] = [200, 201];
+
+/// Builds a state-tree containing the reward and burnt funds actors, two funded accounts, and two
+/// recipients, returning a function that creates machines on top of it and the initial state root.
+fn build_genesis() -> anyhow::Result<(impl Fn(Cid) -> anyhow::Result<TestingMachine>, Cid)> {
+    build_genesis_with_config(|_| {})
+}
+
+/// Like [`build_genesis`], but allows customizing the network config.
+fn build_genesis_with_config(
+    customize: impl FnOnce(&mut NetworkConfig),
+) -> anyhow::Result<(impl Fn(Cid) -> anyhow::Result<TestingMachine>, Cid)> {
+    let bs = SharedBlockstore::default();
+    let mut state_tree = StateTree::new(bs.clone(), StateTreeVersion::V4)?;
+
+    // The account actor is never invoked, so an empty module will do.
+    let wasm = b"\0asm\x01\0\0\0";
+    let account_code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(wasm));
+    bs.put_keyed(&account_code, wasm)?;
+    let other_code = bs.put_cbor(&"code", Code::Blake2b256)?;
+
+    let actors = [(2, other_code), (99, other_code)]
+        .into_iter()
+        .chain(ACCOUNTS.map(|id| (id, account_code)))
+        .chain(RECIPIENTS.map(|id| (id, other_code)));
+    for (id, code) in actors {
+        let state = bs.put_cbor(&id, Code::Blake2b256)?;
+        state_tree.set_actor(
+            &Address::new_id(id),
+            ActorState::new(code, state, 1_000_000_000.into(), 0),
+        )?;
+    }
+    let root = state_tree.flush()?;
+
+    let manifest = vec![("account".to_string(), account_code)];
+    let manifest_cid = bs.put_cbor(&manifest, Code::Blake2b256)?;
+    let actors_cid = bs.put_cbor(&(1, manifest_cid), Code::Blake2b256)?;
+
+    let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+    config.override_actors(actors_cid);
+    customize(&mut config);
+    let engine = Engine::new_default((&config).into())?;
+
+    let new_machine = move |root| {
+        let mut ctx = config.for_epoch(0, root);
+        ctx.set_base_fee(1.into()).enable_gas_profiling();
+        TestingMachine::new(&engine, &ctx, bs.clone(), DummyExterns)
+    };
+    Ok((new_machine, root))
+}
+
+fn transfer(from: ActorID, to: ActorID, sequence: u64) -> (Message, ApplyKind, usize) {
+    let msg = Message {
+        version: 0,
+        from: Address::new_id(from),
+        to: Address::new_id(to),
+        sequence,
+        value: 1000.into(),
+        method_num: METHOD_SEND,
+        params: RawBytes::default(),
+        gas_limit: 10_000_000,
+        gas_fee_cap: 2.into(),
+        gas_premium: 1.into(),
+    };
+    (msg, ApplyKind::Explicit, 100)
+}
+
+#[test]
+fn parallel_transfers() -> anyhow::Result<()> {
+    let (new_machine, root) = build_genesis()?;
+    let msgs = vec![
+        transfer(ACCOUNTS[0], RECIPIENTS[0], 0),
+        transfer(ACCOUNTS[1], RECIPIENTS[1], 0),
+        // conflicts with the first transfer, so it's applied after the others
+        transfer(ACCOUNTS[0], RECIPIENTS[1], 1),
+    ];
+
+    let mut serial = DefaultExecutor::<TestingKernel>::new(new_machine(root)?);
+    let serial_rets = msgs
+        .iter()
+        .map(|(msg, kind, len)| serial.execute_message(msg.clone(), *kind, *len))
+        .collect::<anyhow::Result<Vec<ApplyRet>>>()?;
+    let serial_root = serial.flush()?;
+
+    let views = AtomicUsize::new(0);
+    let mut parallel = ParallelExecutor::<TestingKernel, _>::new(new_machine(root)?, |root| {
+        views.fetch_add(1, Ordering::SeqCst);
+        new_machine(root)
+    });
+    let parallel_rets = parallel.execute_messages(msgs)?;
+    let parallel_root = parallel.flush()?;
+
+    // the disjoint transfers were each applied on their own machine
+    assert_eq!(views.load(Ordering::SeqCst), 2);
+
+    assert_eq!(serial_root, parallel_root);
+    assert_eq!(serial_rets.len(), parallel_rets.len());
+    for (serial_ret, parallel_ret) in serial_rets.iter().zip(&parallel_rets) {
+        assert!(serial_ret.msg_receipt.exit_code.is_success());
+        assert_eq!(serial_ret.diff(parallel_ret), vec![]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn execute_at_root() -> anyhow::Result<()> {
+    let (new_machine, genesis) = build_genesis()?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+
+    let (msg, kind, len) = transfer(ACCOUNTS[0], RECIPIENTS[0], 0);
+    assert!(executor
+        .execute_message(msg.clone(), kind, len)?
+        .msg_receipt
+        .exit_code
+        .is_success());
+    let root = executor.flush()?;
+
+    // the sender's nonce has since moved on, so replaying the message fails now...
+    let ret = executor.execute_message(msg.clone(), kind, len)?;
+    assert_eq!(
+        ret.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+
+    // ...but succeeds against the genesis state, as it did originally
+    let ret = executor.execute_at_root(genesis, msg, kind, len)?;
+    assert!(ret.msg_receipt.exit_code.is_success());
+
+    // and the current state is restored afterwards
+    assert_eq!(executor.flush()?, root);
+    let recipient = executor
+        .state_tree()
+        .get_actor_id(RECIPIENTS[0])?
+        .expect("recipient exists");
+    assert_eq!(recipient.balance, TokenAmount::from(1_000_001_000));
+
+    Ok(())
+}
+
+#[test]
+fn save_points() -> anyhow::Result<()> {
+    let (new_machine, genesis) = build_genesis()?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+    let apply = |executor: &mut DefaultExecutor<TestingKernel>, sequence| {
+        let (msg, kind, len) = transfer(ACCOUNTS[0], RECIPIENTS[0], sequence);
+        let ret = executor.execute_message(msg, kind, len)?;
+        assert!(ret.msg_receipt.exit_code.is_success());
+        anyhow::Ok(())
+    };
+
+    apply(&mut executor, 0)?;
+    let before = executor.save_point("before")?;
+    apply(&mut executor, 1)?;
+    apply(&mut executor, 2)?;
+    let after = executor.save_point("after")?;
+    assert_ne!(before, after);
+
+    // restoring discards the later messages, and unflushed changes
+    apply(&mut executor, 3)?;
+    assert_eq!(executor.restore_point("before")?, before);
+    assert_eq!(executor.flush()?, before);
+    let sender = executor
+        .state_tree()
+        .get_actor_id(ACCOUNTS[0])?
+        .expect("sender exists");
+    assert_eq!(sender.sequence, 1);
+
+    // the later point is gone, but "before" can be restored again
+    assert!(executor.restore_point("after").is_err());
+    apply(&mut executor, 1)?;
+    assert_eq!(executor.restore_point("before")?, before);
+    assert_eq!(executor.flush()?, before);
+
+    // an unknown point leaves the state alone
+    apply(&mut executor, 1)?;
+    let current = executor.flush()?;
+    assert!(executor.restore_point("unknown").is_err());
+    assert_eq!(executor.flush()?, current);
+
+    Ok(())
+}
+
+#[test]
+fn gas_by_actor() -> anyhow::Result<()> {
+    let (new_machine, genesis) = build_genesis()?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+
+    // a plain transfer doesn't invoke any actor, so all the gas is attributed to the sender
+    let (msg, kind, len) = transfer(ACCOUNTS[0], RECIPIENTS[0], 0);
+    let ret = executor.execute_message(msg, kind, len)?;
+    assert!(ret.msg_receipt.exit_code.is_success());
+    assert_eq!(ret.gas_by_actor.len(), 1);
+    let (id, gas) = ret.gas_by_actor[0];
+    assert_eq!(id, ACCOUNTS[0]);
+    assert_eq!(gas.round_up(), ret.msg_receipt.gas_used);
+
+    Ok(())
+}
+
+#[test]
+fn implicit_unlimited_gas_not_enforced() -> anyhow::Result<()> {
+    let (new_machine, genesis) = build_genesis()?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+
+    let (mut msg, _, len) = transfer(ACCOUNTS[0], RECIPIENTS[0], 0);
+    msg.gas_limit = 1;
+
+    // an unlimited implicit message completes despite the tiny gas limit, reporting the gas it
+    // used
+    let ret = executor.execute_message(msg.clone(), ApplyKind::ImplicitUnlimited, len)?;
+    assert_eq!(ret.msg_receipt.exit_code, ExitCode::OK);
+    assert!(ret.msg_receipt.gas_used > msg.gas_limit);
+    assert_eq!(ret.gas_burned, 0);
+
+    // while a plain implicit one runs out of gas, as does an explicit one
+    for kind in [ApplyKind::Implicit, ApplyKind::Explicit] {
+        let ret = executor.execute_message(msg.clone(), kind, len)?;
+        assert_eq!(ret.msg_receipt.exit_code, ExitCode::SYS_OUT_OF_GAS);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn export_import_state() -> anyhow::Result<()> {
+    let (new_machine, genesis) = build_genesis()?;
+    let mut original = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+    let mut exported = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+    let (msg, kind, len) = transfer(ACCOUNTS[0], RECIPIENTS[0], 0);
+    original.execute_message(msg.clone(), kind, len)?;
+    exported.execute_message(msg, kind, len)?;
+
+    // persist the export, and import it into a new machine
+    let mut machine = exported.into_machine().expect("machine isn't poisoned");
+    let export = machine.export_state()?;
+    let bytes = fvm_ipld_encoding::to_vec(&export)?;
+    let engine = machine.engine().clone();
+    let bs = machine.into_store().into_inner();
+
+    let decoded: MachineExport = fvm_ipld_encoding::from_slice(&bytes)?;
+    assert_eq!(decoded, export);
+    let imported = TestingMachine::import_state(&engine, &decoded, bs, DummyExterns)?;
+    assert_eq!(imported.context().base_fee, TokenAmount::from(1));
+
+    // the imported machine executes messages exactly like the original one
+    let mut imported = DefaultExecutor::<TestingKernel>::new(imported);
+    for (msg, kind, len) in [
+        transfer(ACCOUNTS[0], RECIPIENTS[1], 1),
+        transfer(ACCOUNTS[1], RECIPIENTS[0], 0),
+    ] {
+        let expected = original.execute_message(msg.clone(), kind, len)?;
+        let ret = imported.execute_message(msg, kind, len)?;
+        assert!(ret.msg_receipt.exit_code.is_success());
+        assert_eq!(ret.diff(&expected), vec![]);
+    }
+    assert_eq!(imported.flush()?, original.flush()?);
+
+    Ok(())
+}
+
+#[test]
+fn exit_code_remap() -> anyhow::Result<()> {
+    const CUSTOM: ExitCode = ExitCode::new(1005);
+
+    let (new_machine, genesis) = build_genesis()?;
+    let (new_remapped, remapped_genesis) = build_genesis_with_config(|config| {
+        config.remap_exit_codes(vec![(ExitCode::SYS_INVALID_RECEIVER, CUSTOM)]);
+    })?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+    let mut remapped = DefaultExecutor::<TestingKernel>::new(new_remapped(remapped_genesis)?);
+
+    // sending to an actor that doesn't exist fails with the custom exit code...
+    let (msg, kind, len) = transfer(ACCOUNTS[0], 1000, 0);
+    let ret = executor.execute_message(msg.clone(), kind, len)?;
+    let remapped_ret = remapped.execute_message(msg, kind, len)?;
+    assert_eq!(ret.msg_receipt.exit_code, ExitCode::SYS_INVALID_RECEIVER);
+    assert_eq!(remapped_ret.msg_receipt.exit_code, CUSTOM);
+
+    // ...but is otherwise executed identically
+    assert_eq!(
+        ret.diff(&remapped_ret),
+        vec![ApplyRetFieldDiff::ExitCode(
+            ExitCode::SYS_INVALID_RECEIVER,
+            CUSTOM
+        )]
+    );
+    assert_eq!(executor.flush()?, remapped.flush()?);
+
+    // other exit codes are unchanged
+    let (msg, kind, len) = transfer(ACCOUNTS[0], RECIPIENTS[0], 1);
+    let ret = remapped.execute_message(msg, kind, len)?;
+    assert_eq!(ret.msg_receipt.exit_code, ExitCode::OK);
+
+    Ok(())
+}
+
+/// An actor that makes a single syscall, so that its execution is charged syscall gas.
+const PROBE: ActorID = 300;
+
+/// Installs the [`PROBE`] actor.
+fn install_probe(executor: &mut DefaultExecutor<TestingKernel>) -> anyhow::Result<()> {
+    let wasm = wat_actor(
+        r#"(import "gas" "available" (func $available (param i32) (result i32)))"#,
+        "",
+        "(drop (call $available (i32.const 0))) (i32.const 0)",
+    );
+    let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&wasm));
+    executor.blockstore().put_keyed(&code, &wasm)?;
+    executor.precompile(&[code])?;
+    let state = executor.blockstore().put_cbor(&PROBE, Code::Blake2b256)?;
+    executor.state_tree_mut().set_actor(
+        &Address::new_id(PROBE),
+        ActorState::new(code, state, 0.into(), 0),
+    )?;
+
+    Ok(())
+}
+
+/// Returns a message invoking the [`PROBE`] actor.
+fn invoke(sequence: u64) -> (Message, ApplyKind, usize) {
+    let (mut msg, kind, len) = transfer(ACCOUNTS[0], PROBE, sequence);
+    msg.method_num = 2;
+    msg.value = 0.into();
+    (msg, kind, len)
+}
+
+#[test]
+fn custom_price_list() -> anyhow::Result<()> {
+    let (new_machine, genesis) = build_genesis()?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+
+    install_probe(&mut executor)?;
+
+    // the machine's price list doesn't charge for syscalls...
+    let machine_prices = executor.context().price_list;
+    assert_eq!(
+        machine_prices,
+        price_list_by_network_version(NetworkVersion::V15)
+    );
+    let (msg, kind, len) = invoke(0);
+    let ret = executor.execute_message(msg, kind, len)?;
+    assert!(ret.msg_receipt.exit_code.is_success());
+
+    // ...so passing it explicitly changes nothing
+    let (msg, kind, len) = invoke(1);
+    let same = executor.execute_message_with_price_list(msg, kind, len, machine_prices)?;
+    assert!(same.msg_receipt.exit_code.is_success());
+    assert_eq!(same.msg_receipt.gas_used, ret.msg_receipt.gas_used);
+
+    // while a newer price list does
+    let (msg, kind, len) = invoke(2);
+    let newer = price_list_by_network_version(NetworkVersion::V16);
+    let custom = executor.execute_message_with_price_list(msg, kind, len, newer)?;
+    assert!(custom.msg_receipt.exit_code.is_success());
+    assert!(custom.msg_receipt.gas_used > ret.msg_receipt.gas_used);
+
+    // and the override only applies to that message
+    let (msg, kind, len) = invoke(3);
+    let after = executor.execute_message(msg, kind, len)?;
+    assert_eq!(after.msg_receipt.gas_used, ret.msg_receipt.gas_used);
+
+    Ok(())
+}
+
+#[test]
+fn compare_gas_profiles() -> anyhow::Result<()> {
+    let newer = price_list_by_network_version(NetworkVersion::V16);
+    let (new_machine, genesis) = build_genesis()?;
+    let (new_newer, newer_genesis) = build_genesis_with_config(|config| {
+        config.price_list = newer;
+    })?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+    let mut newer_executor = DefaultExecutor::<TestingKernel>::new(new_newer(newer_genesis)?);
+    install_probe(&mut executor)?;
+    install_probe(&mut newer_executor)?;
+    let older = executor.context().price_list;
+
+    let (msg, kind, len) = invoke(0);
+    let cmp = compare_gas(&mut executor, &mut newer_executor, msg, kind, len)?;
+    assert!(cmp.ret_a.msg_receipt.exit_code.is_success());
+    assert!(cmp.ret_b.msg_receipt.exit_code.is_success());
+
+    // each profile adds up to the gas used
+    for ret in [&cmp.ret_a, &cmp.ret_b] {
+        let total = ret.gas_profile.iter().fold(Gas::zero(), |t, (_, g)| t + *g);
+        assert_eq!(total.round_up(), ret.msg_receipt.gas_used);
+    }
+
+    // the newer price list charges for the actor's syscall, and for executing wasm...
+    assert_eq!(
+        cmp.delta("OnSyscall"),
+        newer.on_syscall().total() - older.on_syscall().total()
+    );
+    let wasm_exec = cmp.charges.iter().find(|c| c.name == "wasm_exec").unwrap();
+    assert!(wasm_exec.a.is_zero() && wasm_exec.b > Gas::zero());
+    // ...but charges the same for everything else
+    for charge in &cmp.charges {
+        if !["OnSyscall", "wasm_exec"].contains(&charge.name.as_str()) {
+            assert_eq!(charge.delta(), Gas::zero(), "{}", charge.name);
+        }
+    }
+    assert!(cmp.charges.iter().any(|c| c.name == "OnChainMessage"));
+    assert_eq!(
+        cmp.total_delta(),
+        cmp.delta("OnSyscall") + cmp.delta("wasm_exec")
+    );
+
+    Ok(())
+}
+
+fn corpus() -> Vec<CorpusMessage> {
+    [
+        transfer(ACCOUNTS[0], RECIPIENTS[0], 0),
+        transfer(ACCOUNTS[1], RECIPIENTS[1], 0),
+        transfer(ACCOUNTS[0], RECIPIENTS[1], 1),
+    ]
+    .into_iter()
+    .map(|(msg, apply_kind, raw_length)| CorpusMessage {
+        msg,
+        apply_kind,
+        raw_length,
+        expected_receipt: None,
+    })
+    .collect()
+}
+
+#[test]
+fn determinism_selftest() -> anyhow::Result<()> {
+    let (new_machine, root) = build_genesis()?;
+    let mut corpus = corpus();
+
+    let mut golden = DefaultExecutor::<TestingKernel>::new(new_machine(root)?);
+    for m in &mut corpus {
+        let ret = golden.execute_message(m.msg.clone(), m.apply_kind, m.raw_length)?;
+        m.expected_receipt = Some(ret.msg_receipt);
+    }
+
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(root)?);
+    executor.determinism_selftest(&corpus)?;
+    // the self-test doesn't change the state
+    assert_eq!(executor.flush()?, root);
+
+    // a receipt that doesn't match the golden receipt is reported
+    let mut expected = corpus[1].expected_receipt.clone().unwrap();
+    expected.gas_used += 1;
+    corpus[1].expected_receipt = Some(expected.clone());
+    let report = executor.determinism_selftest(&corpus).unwrap_err();
+    assert!(matches!(
+        &report.failures[..],
+        [DeterminismFailure::Golden { index: 1, expected: e, .. }] if *e == expected
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn execute_with_witness() -> anyhow::Result<()> {
+    type WitnessKernel =
+        DefaultKernel<DefaultCallManager<DefaultMachine<MemoryBlockstore, DummyExterns>>>;
+
+    let (new_machine, genesis) = build_genesis()?;
+    let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(genesis)?);
+    install_probe(&mut executor)?;
+    // warm up the state and the engine's code cache
+    let (msg, kind, len) = invoke(0);
+    executor.execute_message(msg, kind, len)?;
+
+    let export = executor.export_state()?;
+    let (msg, kind, len) = invoke(1);
+    let (ret, witness) = executor.execute_message_with_witness(msg.clone(), kind, len)?;
+    assert!(ret.msg_receipt.exit_code.is_success());
+    assert_eq!(witness.root, export.state_root);
+    assert!(!executor.recording_reads());
+    let root = executor.flush()?;
+
+    // a machine over only the witness, with a fresh engine, re-executes the message identically
+    let engine = Engine::new_default((&export.context()?.network).into())?;
+    let machine =
+        DefaultMachine::import_state(&engine, &export, witness.to_blockstore()?, DummyExterns)?;
+    let probe = machine
+        .state_tree()
+        .get_actor(&Address::new_id(PROBE))?
+        .unwrap();
+    machine.precompile(&[probe.code])?;
+    let mut replay = DefaultExecutor::<WitnessKernel>::new(machine);
+    let replayed = replay.execute_message(msg, kind, len)?;
+    assert_eq!(ret.diff(&replayed), vec![]);
+    assert_eq!(ret.gas_profile, replayed.gas_profile);
+    assert_eq!(replay.flush()?, root);
+
+    Ok(())
+}
+
+/// A machine that fails the first transfer it's asked to make, and makes all others.
+struct FlakyMachine {
+    machine: TestingMachine,
+    transfers: usize,
+}
+
+impl Machine for FlakyMachine {
+    type Blockstore = <TestingMachine as Machine>::Blockstore;
+    type Externs = DummyExterns;
+
+    fn engine(&self) -> &Engine {
+        self.machine.engine()
+    }
+
+    fn blockstore(&self) -> &Self::Blockstore {
+        self.machine.blockstore()
+    }
+
+    fn context(&self) -> &MachineContext {
+        self.machine.context()
+    }
+
+    fn externs(&self) -> &Self::Externs {
+        self.machine.externs()
+    }
+
+    fn builtin_actors(&self) -> &Manifest {
+        self.machine.builtin_actors()
+    }
+
+    fn state_tree(&self) -> &StateTree<Self::Blockstore> {
+        self.machine.state_tree()
+    }
+
+    fn state_tree_mut(&mut self) -> &mut StateTree<Self::Blockstore> {
+        self.machine.state_tree_mut()
+    }
+
+    fn create_actor(&mut self, addr: &Address, act: ActorState) -> fvm::kernel::Result<ActorID> {
+        self.machine.create_actor(addr, act)
+    }
+
+    fn transfer(
+        &mut self,
+        from: ActorID,
+        to: ActorID,
+        value: &TokenAmount,
+    ) -> fvm::kernel::Result<()> {
+        self.transfers += 1;
+        if self.transfers == 1 {
+            return Err(ExecutionError::Syscall(SyscallError::new(
+                ErrorNumber::InsufficientFunds,
+                "flaky transfer",
+            )));
+        }
+        self.machine.transfer(from, to, value)
+    }
+
+    fn flush(&mut self) -> fvm::kernel::Result<Cid> {
+        self.machine.flush()
+    }
+
+    fn discard_buffered_writes(&mut self) -> fvm::kernel::Result<()> {
+        self.machine.discard_buffered_writes()
+    }
+
+    fn pending_writes(&self) -> Vec<(Cid, usize)> {
+        self.machine.pending_writes()
+    }
+
+    fn record_reads(&mut self) -> fvm::kernel::Result<Cid> {
+        self.machine.record_reads()
+    }
+
+    fn recording_reads(&self) -> bool {
+        self.machine.recording_reads()
+    }
+
+    fn take_reads(&mut self) -> BTreeMap<Cid, Vec<u8>> {
+        self.machine.take_reads()
+    }
+
+    fn into_store(self) -> Self::Blockstore {
+        self.machine.into_store()
+    }
+
+    fn machine_id(&self) -> &str {
+        self.machine.machine_id()
+    }
+}
+
+#[test]
+fn determinism_selftest_nondeterministic() -> anyhow::Result<()> {
+    type FlakyKernel = DefaultKernel<DefaultCallManager<FlakyMachine>>;
+
+    let (new_machine, root) = build_genesis()?;
+    let mut executor = DefaultExecutor::<FlakyKernel>::new(FlakyMachine {
+        machine: new_machine(root)?,
+        transfers: 0,
+    });
+
+    // the first message only fails the first time it's executed
+    let report = executor.determinism_selftest(&corpus()).unwrap_err();
+    assert!(matches!(
+        &report.failures[..],
+        [
+            DeterminismFailure::Execution { index: 0, diffs },
+            DeterminismFailure::StateRoot(..),
+        ] if diffs[0] == ApplyRetFieldDiff::ExitCode(ExitCode::SYS_INSUFFICIENT_FUNDS, ExitCode::OK)
+    ));
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    /// Arbitrary messages fail with receipts or typed errors, but never panic.
+    #[test]
+    fn arbitrary_messages_dont_panic(
+        data in proptest::collection::vec(any::<u8>(), 0..512),
+        from_account in any::<bool>(),
+        to_known in any::<bool>(),
+        explicit in any::<bool>(),
+    ) {
+        let mut u = Unstructured::new(&data);
+        let mut msg = Message::arbitrary(&mut u).unwrap();
+        // most arbitrary addresses don't exist, so also send between existing actors to reach
+        // execution
+        if from_account {
+            msg.from = Address::new_id(ACCOUNTS[0]);
+        }
+        if to_known {
+            msg.to = Address::new_id(RECIPIENTS[0]);
+        }
+        let kind = if explicit { ApplyKind::Explicit } else { ApplyKind::Implicit };
+
+        let (new_machine, root) = build_genesis().unwrap();
+        let mut executor = DefaultExecutor::<TestingKernel>::new(new_machine(root).unwrap());
+        let _ = executor.execute_message(msg, kind, data.len());
+    }
+}
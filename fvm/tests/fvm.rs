@@ -1,4 +1,10 @@
+mod call_manager;
 mod default_kernel;
+mod default_machine;
 mod dummy;
+mod executor;
+mod syscalls;
+mod wasm;
 
 use dummy::*;
+use wasm::*;
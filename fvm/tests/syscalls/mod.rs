@@ -0,0 +1,111 @@
+use fvm::gas::Gas;
+use fvm::kernel::default::DefaultKernel;
+use fvm::kernel::{BlockRegistry, GasOps, MessageOps};
+use fvm::syscalls::{charge_for_exec, update_gas_available, Abort, GasCheckpoint, InvocationData};
+use fvm::Kernel;
+use wasmtime::{Global, GlobalType, Mutability, Store, Val, ValType};
+
+use super::*;
+
+const ACTOR_ID: fvm_shared::ActorID = 1234;
+
+type TestingKernel = DefaultKernel<DummyCallManager>;
+
+/// Build a store whose gas global has been replaced with `global_type`, so that synchronizing it
+/// with the gas tracker fails.
+fn build_broken_gas_store(
+    global_type: GlobalType,
+    init: Val,
+) -> anyhow::Result<Store<InvocationData<TestingKernel>>> {
+    let (call_manager, _) = DummyCallManager::new_stub();
+    let engine = call_manager.machine.engine.clone();
+    let kern = TestingKernel::new(
+        call_manager,
+        BlockRegistry::default(),
+        0,
+        ACTOR_ID,
+        0,
+        0.into(),
+    );
+    let mut store = engine.new_store(kern);
+    let global = Global::new(&mut store, global_type, init)?;
+    store.data_mut().avail_gas_global = global.into();
+    Ok(store)
+}
+
+fn expect_fatal(res: Result<(), Abort>) -> String {
+    match res.expect_err("expected gas global update to fail") {
+        Abort::Fatal(e) => e.to_string(),
+        other => panic!("expected a fatal error, got {:?}", other),
+    }
+}
+
+#[test]
+fn update_gas_available_error_context() -> anyhow::Result<()> {
+    let mut store = build_broken_gas_store(
+        GlobalType::new(ValType::I64, Mutability::Const),
+        Val::I64(0),
+    )?;
+    assert_eq!(store.data().kernel.msg_receiver(), ACTOR_ID);
+
+    let msg = expect_fatal(update_gas_available(
+        &mut store,
+        GasCheckpoint::Syscall("ipld", "block_read"),
+    ));
+    assert!(msg.contains("actor 1234"), "missing actor id: {}", msg);
+    assert!(
+        msg.contains("syscall ipld::block_read"),
+        "missing syscall: {}",
+        msg
+    );
+
+    let msg = expect_fatal(update_gas_available(&mut store, GasCheckpoint::Invoke));
+    assert!(msg.contains("actor 1234"), "missing actor id: {}", msg);
+    assert!(msg.contains("invoke"), "missing checkpoint: {}", msg);
+
+    Ok(())
+}
+
+#[test]
+fn charge_for_exec_error_context() -> anyhow::Result<()> {
+    let mut store =
+        build_broken_gas_store(GlobalType::new(ValType::I32, Mutability::Var), Val::I32(0))?;
+
+    let msg = expect_fatal(charge_for_exec(
+        &mut store,
+        GasCheckpoint::Syscall("send", "send"),
+    ));
+    assert!(msg.contains("actor 1234"), "missing actor id: {}", msg);
+    assert!(
+        msg.contains("syscall send::send"),
+        "missing syscall: {}",
+        msg
+    );
+
+    Ok(())
+}
+
+#[test]
+fn charge_for_exec_rejects_gas_increase() -> anyhow::Result<()> {
+    let mut store = build_broken_gas_store(
+        GlobalType::new(ValType::I64, Mutability::Var),
+        Val::I64(Gas::new(10).as_milligas()),
+    )?;
+
+    // consuming gas is fine
+    store.data_mut().last_gas_available = Gas::new(15);
+    charge_for_exec(&mut store, GasCheckpoint::Invoke).expect("failed to charge for exec");
+    assert_eq!(store.data().kernel.gas_used(), Gas::new(5));
+
+    // but gas appearing out of nowhere means the global was corrupted
+    store.data_mut().last_gas_available = Gas::new(5);
+    let msg = expect_fatal(charge_for_exec(
+        &mut store,
+        GasCheckpoint::Syscall("gas", "charge"),
+    ));
+    assert!(msg.contains("available gas increased"), "{}", msg);
+    assert!(msg.contains("actor 1234"), "missing actor id: {}", msg);
+    assert_eq!(store.data().kernel.gas_used(), Gas::new(5));
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Test actors, assembled from WAT.
+
+use wabt::wat2wasm;
+
+/// Imports `send::send_ext` as `$send`.
+pub const SEND: &str = r#"(import "send" "send_ext" (func $send (param i32 i32 i32 i64 i32 i64 i64 i64 i64 i64) (result i32)))"#;
+
+/// Imports `vm::abort` as `$abort`.
+pub const ABORT: &str = r#"(import "vm" "abort" (func $abort (param i32 i32 i32) (result i32)))"#;
+
+/// Assembles an actor with one page of memory, from its syscall `imports`, any other module
+/// `fields` (data segments, tables, etc.), and the `body` of its `invoke` function. The body
+/// receives the params block ID in `$params`, and returns the block ID of the return value.
+pub fn wat_actor(imports: &str, fields: &str, body: &str) -> Vec<u8> {
+    let wat = format!(
+        r#"(module
+             {}
+             (memory (export "memory") 1)
+             {}
+             (func (export "invoke") (param $params i32) (result i32)
+               {}))"#,
+        imports, fields, body
+    );
+    wat2wasm(&wat).unwrap_or_else(|e| panic!("invalid test actor: {}\n{}", e, wat))
+}
+
+/// Returns a data segment placing `bytes` at the given memory offset.
+pub fn wat_data(offset: u32, bytes: &[u8]) -> String {
+    let escaped: String = bytes.iter().map(|b| format!("\\{:02x}", b)).collect();
+    format!(r#"(data (i32.const {}) "{}")"#, offset, escaped)
+}
@@ -0,0 +1,116 @@
+use std::future::Future;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use cid::Cid;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use super::Blockstore;
+
+/// An asynchronous blockstore. Use it where a [`Blockstore`] is expected (e.g., in the FVM) by
+/// wrapping it in a [`BlockstoreBridge`].
+#[async_trait]
+pub trait AsyncBlockstore: Send + Sync {
+    /// Gets the block from the blockstore.
+    async fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>>;
+
+    /// Put a block with a pre-computed cid.
+    async fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()>;
+
+    /// Checks if the blockstore has the specified block.
+    async fn has(&self, k: &Cid) -> Result<bool> {
+        Ok(self.get(k).await?.is_some())
+    }
+}
+
+/// Adapts an [`AsyncBlockstore`] to the synchronous [`Blockstore`] trait, by blocking the current
+/// thread on a tokio runtime until each operation completes.
+///
+/// The bridge may be used from any thread outside of a runtime, or from within a multi-threaded
+/// runtime, where the current worker hands its other tasks off to the rest of the pool while it
+/// blocks. Blocking within a current-thread runtime would deadlock it, so operations fail there
+/// instead.
+///
+/// ```
+/// use fvm_ipld_blockstore::{AsyncBlockstore, Blockstore, BlockstoreBridge};
+/// # use std::collections::HashMap;
+/// # use std::sync::Mutex;
+/// # use async_trait::async_trait;
+/// # use cid::Cid;
+/// #
+/// # #[derive(Default)]
+/// # struct AsyncStore(Mutex<HashMap<Cid, Vec<u8>>>);
+/// #
+/// # #[async_trait]
+/// # impl AsyncBlockstore for AsyncStore {
+/// #     async fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+/// #         Ok(self.0.lock().unwrap().get(k).cloned())
+/// #     }
+/// #
+/// #     async fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+/// #         self.0.lock().unwrap().insert(*k, block.to_vec());
+/// #         Ok(())
+/// #     }
+/// # }
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let bs = BlockstoreBridge::new(AsyncStore::default(), runtime.handle().clone());
+///
+/// let k = Cid::default();
+/// bs.put_keyed(&k, b"block").unwrap();
+/// assert_eq!(bs.get(&k).unwrap(), Some(b"block".to_vec()));
+/// ```
+#[derive(Debug)]
+pub struct BlockstoreBridge<S> {
+    store: S,
+    handle: Handle,
+}
+
+impl<S> BlockstoreBridge<S> {
+    /// Wraps `store`, running its operations on the runtime behind `handle`.
+    pub fn new(store: S, handle: Handle) -> Self {
+        Self { store, handle }
+    }
+
+    /// Returns a reference to the wrapped store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Consumes the bridge and returns the wrapped store.
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    /// Blocks the current thread until `f` completes.
+    fn block_on<F: Future>(&self, f: F) -> Result<F::Output> {
+        match Handle::try_current().map(|current| current.runtime_flavor()) {
+            // Not in a runtime, so we're free to block.
+            Err(_) => Ok(self.handle.block_on(f)),
+            // Blocking a runtime worker is only safe once its tasks have been moved elsewhere.
+            Ok(RuntimeFlavor::MultiThread) => {
+                Ok(tokio::task::block_in_place(|| self.handle.block_on(f)))
+            }
+            Ok(_) => Err(anyhow!(
+                "cannot block on an async blockstore from a current-thread runtime"
+            )),
+        }
+    }
+}
+
+impl<S> Blockstore for BlockstoreBridge<S>
+where
+    S: AsyncBlockstore,
+{
+    fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
+        self.block_on(self.store.get(k))?
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.block_on(self.store.put_keyed(k, block))?
+    }
+
+    fn has(&self, k: &Cid) -> Result<bool> {
+        self.block_on(self.store.has(k))?
+    }
+}
@@ -11,6 +11,11 @@ pub use memory::MemoryBlockstore;
 mod block;
 pub use block::*;
 
+#[cfg(feature = "async")]
+mod bridge;
+#[cfg(feature = "async")]
+pub use bridge::{AsyncBlockstore, BlockstoreBridge};
+
 /// An IPLD blockstore suitable for injection into the FVM.
 ///
 /// The cgo blockstore adapter implements this trait.
@@ -0,0 +1,259 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+
+use crate::{CodecProtocol, Error};
+
+/// The CBOR tag for CIDs.
+const CID_TAG: u64 = 42;
+
+/// Checks that `data` is a single, canonically encoded DAG-CBOR object. That is:
+///
+/// - Integers, lengths, and tags use the shortest possible encoding.
+/// - Strings, arrays, and maps have definite lengths.
+/// - Map keys are strings, sorted by length and then bytewise, with no duplicates.
+/// - The only tag is 42 (CID), applied to a byte string.
+/// - The only simple values are `false`, `true`, and `null`, and floats are 64-bit.
+/// - There are no trailing bytes.
+pub fn check_canonical_cbor(data: &[u8]) -> Result<(), Error> {
+    let mut reader = Reader { data, pos: 0 };
+
+    // The number of items left to read at each nesting level. Maps count keys and values as
+    // separate items, and track the last key read to check the key order.
+    let mut levels = vec![Level {
+        remaining: 1,
+        last_key: None,
+        is_map: false,
+    }];
+    while let Some(level) = levels.last_mut() {
+        if level.remaining == 0 {
+            levels.pop();
+            continue;
+        }
+        level.remaining -= 1;
+        let is_key = level.is_map && level.remaining % 2 == 1;
+
+        let start = reader.pos;
+        let (major, value) = reader.header()?;
+        if is_key && major != 3 {
+            return Err(error("map keys must be strings"));
+        }
+
+        match major {
+            // Integers.
+            0 | 1 => {}
+            // Byte strings.
+            2 => {
+                reader.take(value)?;
+            }
+            // Text strings.
+            3 => {
+                let s = reader.take(value)?;
+                std::str::from_utf8(s).map_err(|e| error(format!("invalid string: {}", e)))?;
+                if is_key {
+                    let key = &data[start..reader.pos];
+                    // Keys are sorted by length first, and then bytewise. As string headers are
+                    // minimal, comparing the encoded keys by length compares the key lengths.
+                    if let Some(last) = level.last_key {
+                        let ord = last.len().cmp(&key.len()).then_with(|| last.cmp(key));
+                        if ord != Ordering::Less {
+                            return Err(error("map keys are not sorted or not unique"));
+                        }
+                    }
+                    level.last_key = Some(key);
+                }
+            }
+            // Arrays.
+            4 => levels.push(Level {
+                remaining: value,
+                last_key: None,
+                is_map: false,
+            }),
+            // Maps.
+            5 => levels.push(Level {
+                remaining: value
+                    .checked_mul(2)
+                    .ok_or_else(|| error("map length overflow"))?,
+                last_key: None,
+                is_map: true,
+            }),
+            // Tags.
+            6 => {
+                if value != CID_TAG {
+                    return Err(error(format!("unexpected tag {}", value)));
+                }
+                match reader.header()? {
+                    (2, len) => {
+                        reader.take(len)?;
+                    }
+                    _ => return Err(error("CIDs must be byte strings")),
+                }
+            }
+            // Simple values and floats, already checked when reading the header.
+            _ => {}
+        }
+    }
+
+    if reader.pos != data.len() {
+        return Err(error("trailing data"));
+    }
+    Ok(())
+}
+
+struct Level<'a> {
+    remaining: u64,
+    last_key: Option<&'a [u8]>,
+    is_map: bool,
+}
+
+/// A reader for the items of a CBOR object.
+pub(crate) struct Reader<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, len: u64) -> Result<&'a [u8], Error> {
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| self.pos.checked_add(len))
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| error("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N as u64)?.try_into().expect("took N bytes"))
+    }
+
+    /// Reads an item header, returning the major type and the argument.
+    pub(crate) fn header(&mut self) -> Result<(u8, u64), Error> {
+        let [initial] = self.take_array()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+
+        if major == 7 {
+            return match info {
+                // false, true, null
+                20..=22 => Ok((major, info as u64)),
+                27 => Ok((major, u64::from_be_bytes(self.take_array()?))),
+                _ => Err(error(format!("unsupported simple value or float {}", info))),
+            };
+        }
+
+        let (value, min) = match info {
+            0..=23 => return Ok((major, info as u64)),
+            24 => (u8::from_be_bytes(self.take_array()?) as u64, 24),
+            25 => (u16::from_be_bytes(self.take_array()?) as u64, 0x100),
+            26 => (u32::from_be_bytes(self.take_array()?) as u64, 0x1_0000),
+            27 => (u64::from_be_bytes(self.take_array()?), 0x1_0000_0000),
+            31 => return Err(error("indefinite lengths are not allowed")),
+            _ => return Err(error(format!("reserved additional info {}", info))),
+        };
+        if value < min {
+            return Err(error("integer is not minimally encoded"));
+        }
+        Ok((major, value))
+    }
+
+    /// Skips over the next `items` items, including any items nested within them.
+    pub(crate) fn skip(&mut self, items: u64) -> Result<(), Error> {
+        let mut remaining = items;
+        while remaining > 0 {
+            remaining -= 1;
+            let (major, value) = self.header()?;
+            let nested = match major {
+                2 | 3 => {
+                    self.take(value)?;
+                    0
+                }
+                4 => value,
+                5 => value
+                    .checked_mul(2)
+                    .ok_or_else(|| error("map length overflow"))?,
+                6 => 1,
+                _ => 0,
+            };
+            remaining = remaining
+                .checked_add(nested)
+                .ok_or_else(|| error("length overflow"))?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn error(description: impl ToString) -> Error {
+    Error {
+        description: description.to_string(),
+        protocol: CodecProtocol::Cbor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use cid::Cid;
+    use multihash::{Code, MultihashDigest};
+
+    use super::*;
+    use crate::{to_vec, DAG_CBOR};
+
+    #[test]
+    fn accepts_canonical() {
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b"foo"));
+        let mut map = BTreeMap::new();
+        map.insert("bb".to_string(), vec![1u64, 24, 256, 1 << 40]);
+        map.insert("a".to_string(), vec![]);
+        map.insert("c".to_string(), vec![u64::MAX]);
+
+        for data in [
+            to_vec(&1u8).unwrap(),
+            to_vec(&-1000i64).unwrap(),
+            to_vec(&"foo").unwrap(),
+            to_vec(&(true, false, (), 1.5f64)).unwrap(),
+            to_vec(&cid).unwrap(),
+            to_vec(&map).unwrap(),
+            to_vec(&(cid, map)).unwrap(),
+        ] {
+            check_canonical_cbor(&data).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_non_canonical() {
+        for data in [
+            // non-minimal integers and lengths
+            &[0x18, 0x01][..],
+            &[0x19, 0x00, 0xff],
+            &[0x5a, 0x00, 0x00, 0x00, 0x01, 0x00],
+            // indefinite length array
+            &[0x9f, 0x01, 0xff],
+            // map keys out of order, by length and bytewise
+            &[0xa2, 0x62, b'b', b'b', 0x00, 0x61, b'a', 0x00],
+            &[0xa2, 0x61, b'b', 0x00, 0x61, b'a', 0x00],
+            // duplicate map keys
+            &[0xa2, 0x61, b'a', 0x00, 0x61, b'a', 0x00],
+            // non-string map key
+            &[0xa1, 0x01, 0x00],
+            // unknown tag
+            &[0xc1, 0x01],
+            // 32-bit float and undefined
+            &[0xfa, 0x3f, 0xc0, 0x00, 0x00],
+            &[0xf7],
+            // truncated and trailing data
+            &[0x82, 0x01],
+            &[0x01, 0x01],
+            &[],
+        ] {
+            assert!(
+                check_canonical_cbor(data).is_err(),
+                "expected {:02x?} to be rejected",
+                data
+            );
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::convert::TryInto;
+
+use crate::canonical::{error, Reader};
+use crate::Error;
+
+/// Returns the nesting depth of the first CBOR object in `data`: 0 for a scalar, 1 for an array
+/// or map of scalars, and so on. Tags count as a level of nesting, as decoders recurse into the
+/// tagged item.
+///
+/// Unlike [`check_canonical_cbor`](crate::check_canonical_cbor), this accepts any well-formed
+/// CBOR, including non-minimal encodings and indefinite lengths.
+pub fn cbor_depth(data: &[u8]) -> Result<u32, Error> {
+    match walk(data, u32::MAX) {
+        Ok(depth) => Ok(depth),
+        Err(Stop::TooDeep) => Err(error("object is too deeply nested")),
+        Err(Stop::Malformed(e)) => Err(e),
+    }
+}
+
+/// Checks that the first CBOR object in `data` is nested at most `max_depth` levels deep, as
+/// measured by [`cbor_depth`]. The walk stops at the first level past the limit, so it never
+/// tracks more than `max_depth` levels.
+///
+/// Malformed data isn't rejected, as it can't be decoded anyway: only the items before the first
+/// malformed item are checked.
+pub fn check_cbor_depth(data: &[u8], max_depth: u32) -> Result<(), Error> {
+    match walk(data, max_depth) {
+        Err(Stop::TooDeep) => Err(error(format!(
+            "object is nested more than {} levels deep",
+            max_depth
+        ))),
+        Ok(_) | Err(Stop::Malformed(_)) => Ok(()),
+    }
+}
+
+enum Stop {
+    TooDeep,
+    Malformed(Error),
+}
+
+impl From<Error> for Stop {
+    fn from(e: Error) -> Self {
+        Stop::Malformed(e)
+    }
+}
+
+struct Level {
+    /// The number of items left to read, or `None` for an indefinite length item, which ends with
+    /// a "break" byte.
+    remaining: Option<u64>,
+    /// Whether the level counts towards the depth. Indefinite length strings are split into
+    /// chunks, but aren't nested.
+    nests: bool,
+}
+
+/// The "break" byte ending indefinite length items.
+const BREAK: u8 = 0xff;
+
+/// Walks the first CBOR object in `data`, returning its depth, or stopping once it's deeper than
+/// `max_depth`.
+fn walk(data: &[u8], max_depth: u32) -> Result<u32, Stop> {
+    let mut reader = Reader { data, pos: 0 };
+
+    let mut levels = vec![Level {
+        remaining: Some(1),
+        nests: false,
+    }];
+    let (mut depth, mut max_seen) = (0u32, 0u32);
+    while let Some(level) = levels.last_mut() {
+        match &mut level.remaining {
+            Some(0) => {
+                depth -= level.nests as u32;
+                levels.pop();
+                continue;
+            }
+            Some(n) => *n -= 1,
+            None => {
+                if data.get(reader.pos) == Some(&BREAK) {
+                    reader.pos += 1;
+                    depth -= level.nests as u32;
+                    levels.pop();
+                    continue;
+                }
+            }
+        }
+
+        let (major, len) = header(&mut reader)?;
+        let nested = match (major, len) {
+            // Byte and text strings.
+            (2 | 3, Some(len)) => {
+                reader.take(len)?;
+                continue;
+            }
+            (2 | 3, None) => Level {
+                remaining: None,
+                nests: false,
+            },
+            // Arrays.
+            (4, len) => Level {
+                remaining: len,
+                nests: true,
+            },
+            // Maps.
+            (5, len) => Level {
+                remaining: match len {
+                    Some(len) => Some(
+                        len.checked_mul(2)
+                            .ok_or_else(|| error("map length overflow"))?,
+                    ),
+                    None => None,
+                },
+                nests: true,
+            },
+            // Tags.
+            (6, _) => Level {
+                remaining: Some(1),
+                nests: true,
+            },
+            // Integers, simple values and floats.
+            _ => continue,
+        };
+
+        if nested.nests {
+            depth += 1;
+            if depth > max_depth {
+                return Err(Stop::TooDeep);
+            }
+            max_seen = max_seen.max(depth);
+        }
+        levels.push(nested);
+    }
+    Ok(max_seen)
+}
+
+/// Reads an item header, returning the major type and the argument, or `None` for an indefinite
+/// length.
+fn header(reader: &mut Reader) -> Result<(u8, Option<u64>), Error> {
+    let initial = reader.take(1)?[0];
+    let (major, info) = (initial >> 5, initial & 0x1f);
+    let value = match info {
+        0..=23 => info as u64,
+        24 => reader.take(1)?[0] as u64,
+        25 => u16::from_be_bytes(reader.take(2)?.try_into().expect("took 2 bytes")) as u64,
+        26 => u32::from_be_bytes(reader.take(4)?.try_into().expect("took 4 bytes")) as u64,
+        27 => u64::from_be_bytes(reader.take(8)?.try_into().expect("took 8 bytes")),
+        31 if (2..=5).contains(&major) => return Ok((major, None)),
+        _ => return Err(error(format!("unexpected additional info {}", info))),
+    };
+    Ok((major, Some(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_vec;
+
+    /// An array nested `depth` levels deep, around a single integer.
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut data = vec![0x81; depth];
+        data.push(0x00);
+        data
+    }
+
+    #[test]
+    fn measures_depth() {
+        for (data, depth) in [
+            (to_vec(&1u8).unwrap(), 0),
+            (to_vec(&"foo").unwrap(), 0),
+            (to_vec(&Vec::<u8>::new()).unwrap(), 1),
+            (to_vec(&(1u8, vec![vec![2u8]], "foo")).unwrap(), 3),
+            // a tagged integer
+            (vec![0xc1, 0x01], 1),
+            // an indefinite length map, holding an indefinite length string and an array
+            (vec![0xbf, 0x7f, 0x61, b'a', 0xff, 0x81, 0x00, 0xff], 2),
+            // a non-minimal array length
+            (vec![0x98, 0x01, 0x80], 2),
+            (nested(1000), 1000),
+        ] {
+            assert_eq!(cbor_depth(&data).unwrap(), depth, "{:02x?}", data);
+        }
+    }
+
+    #[test]
+    fn limits_depth() {
+        check_cbor_depth(&nested(10), 10).unwrap();
+        check_cbor_depth(&nested(11), 10).unwrap_err();
+        check_cbor_depth(&[0xc1, 0xc1, 0x01], 1).unwrap_err();
+
+        // truncated data is only checked up to the truncation
+        check_cbor_depth(&[0x82, 0x81], 2).unwrap();
+        check_cbor_depth(&[0x82, 0x81, 0x81], 2).unwrap_err();
+        cbor_depth(&[0x82, 0x81]).unwrap_err();
+    }
+}
@@ -2,9 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod bytes;
+mod canonical;
 mod cbor;
 mod cbor_store;
+mod depth;
 mod errors;
+mod links;
+mod path;
 mod vec;
 use std::io;
 
@@ -12,9 +16,13 @@ pub use serde::{self, de, ser};
 pub use serde_bytes;
 
 pub use self::bytes::*;
+pub use self::canonical::check_canonical_cbor;
 pub use self::cbor::*;
 pub use self::cbor_store::CborStore;
+pub use self::depth::{cbor_depth, check_cbor_depth};
 pub use self::errors::*;
+pub use self::links::cbor_links;
+pub use self::path::{find_cbor_path, PathLookup, PathSegment};
 pub use self::vec::*;
 
 // TODO: these really don't work all that well in a shared context like this as anyone importing
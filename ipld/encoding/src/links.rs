@@ -0,0 +1,99 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::convert::TryFrom;
+
+use cid::Cid;
+
+use crate::canonical::{error, Reader};
+use crate::Error;
+
+/// The CBOR tag for CIDs.
+const CID_TAG: u64 = 42;
+
+/// Returns the CIDs linked from the DAG-CBOR object `data`, in the order they appear.
+///
+/// The walk fails if the object isn't well-formed DAG-CBOR (integers and lengths must be minimally
+/// encoded, and lengths must be definite), or if a CID can't be parsed. Unlike
+/// [`check_canonical_cbor`](crate::check_canonical_cbor), map key order isn't checked.
+pub fn cbor_links(data: &[u8]) -> Result<Vec<Cid>, Error> {
+    let mut reader = Reader { data, pos: 0 };
+    let mut links = Vec::new();
+
+    let mut remaining = 1u64;
+    while remaining > 0 {
+        remaining -= 1;
+        let (major, value) = reader.header()?;
+        let nested = match major {
+            2 | 3 => {
+                reader.take(value)?;
+                0
+            }
+            4 => value,
+            5 => value
+                .checked_mul(2)
+                .ok_or_else(|| error("map length overflow"))?,
+            6 if value == CID_TAG => {
+                let bytes = match reader.header()? {
+                    (2, len) => reader.take(len)?,
+                    _ => return Err(error("CIDs must be byte strings")),
+                };
+                // CIDs are prefixed with the "identity" multibase.
+                let cid = match bytes.split_first() {
+                    Some((0, cid)) => {
+                        Cid::try_from(cid).map_err(|e| error(format!("invalid CID: {}", e)))?
+                    }
+                    _ => return Err(error("CIDs must have the identity multibase prefix")),
+                };
+                links.push(cid);
+                0
+            }
+            6 => 1,
+            _ => 0,
+        };
+        remaining = remaining
+            .checked_add(nested)
+            .ok_or_else(|| error("length overflow"))?;
+    }
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use multihash::{Code, MultihashDigest};
+
+    use super::*;
+    use crate::{to_vec, DAG_CBOR};
+
+    fn cid(data: &[u8]) -> Cid {
+        Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(data))
+    }
+
+    #[test]
+    fn finds_nested_links() {
+        let (a, b, c) = (cid(b"a"), cid(b"b"), cid(b"c"));
+        let mut map = BTreeMap::new();
+        map.insert("x".to_string(), vec![b]);
+        map.insert("y".to_string(), vec![]);
+        let data = to_vec(&(a, 1u8, map, "text", (c, a))).unwrap();
+        assert_eq!(cbor_links(&data).unwrap(), vec![a, b, c, a]);
+
+        assert_eq!(
+            cbor_links(&to_vec(&(1u8, "no links")).unwrap()).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        // truncated data
+        let data = to_vec(&(cid(b"a"), 1u8)).unwrap();
+        cbor_links(&data[..data.len() - 1]).unwrap_err();
+        // a CID tag on an integer
+        cbor_links(&[0xd8, 0x2a, 0x01]).unwrap_err();
+        // a CID without the multibase prefix
+        cbor_links(&[0xd8, 0x2a, 0x42, 0x01, 0x55]).unwrap_err();
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::canonical::{error, Reader};
+use crate::{Cbor, Error};
+
+/// A step in a path through a CBOR object: an index into an array, or a key in a map.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Index(u64),
+    Key(String),
+}
+
+impl Cbor for PathSegment {}
+
+/// The result of looking up a path with [`find_cbor_path`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathLookup {
+    /// The location of the value at the path, or `None` if there's no such value.
+    pub value: Option<Range<usize>>,
+    /// The number of bytes of the object traversed by the lookup.
+    pub traversed: usize,
+}
+
+/// Finds the value at `path` within the DAG-CBOR object `data`, without decoding the object.
+///
+/// Each segment of the path selects an element of an array by index, or an entry of a map by key.
+/// The lookup fails if the part of the object traversed isn't well-formed DAG-CBOR (integers and
+/// lengths must be minimally encoded), but the rest of the object isn't checked.
+pub fn find_cbor_path(data: &[u8], path: &[PathSegment]) -> Result<PathLookup, Error> {
+    let mut reader = Reader { data, pos: 0 };
+    for segment in path {
+        let (major, len) = reader.header()?;
+        let found = match (major, segment) {
+            // Arrays.
+            (4, PathSegment::Index(idx)) if *idx < len => {
+                reader.skip(*idx)?;
+                true
+            }
+            // Maps.
+            (5, PathSegment::Key(key)) => {
+                let mut found = false;
+                for _ in 0..len {
+                    let (major, len) = reader.header()?;
+                    if major != 3 {
+                        return Err(error("map keys must be strings"));
+                    }
+                    if reader.take(len)? == key.as_bytes() {
+                        found = true;
+                        break;
+                    }
+                    reader.skip(1)?;
+                }
+                found
+            }
+            _ => false,
+        };
+        if !found {
+            return Ok(PathLookup {
+                value: None,
+                traversed: reader.pos,
+            });
+        }
+    }
+
+    let start = reader.pos;
+    reader.skip(1)?;
+    Ok(PathLookup {
+        value: Some(start..reader.pos),
+        traversed: reader.pos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{from_slice, to_vec};
+
+    #[test]
+    fn finds_nested_values() {
+        let mut inner = BTreeMap::new();
+        inner.insert("a".to_string(), (1u64, "x".to_string()));
+        inner.insert("bb".to_string(), (2u64, "y".to_string()));
+        let data = to_vec(&(vec![7u64, 8], inner, "tail")).unwrap();
+
+        let lookup = |path: &[PathSegment]| {
+            find_cbor_path(&data, path)
+                .unwrap()
+                .value
+                .map(|range| data[range].to_vec())
+        };
+        use PathSegment::*;
+
+        assert_eq!(lookup(&[]), Some(data.clone()));
+        assert_eq!(lookup(&[Index(0), Index(1)]), Some(to_vec(&8u64).unwrap()));
+        assert_eq!(
+            lookup(&[Index(1), Key("bb".into())]),
+            Some(to_vec(&(2u64, "y")).unwrap())
+        );
+        assert_eq!(
+            lookup(&[Index(1), Key("bb".into()), Index(1)]),
+            Some(to_vec(&"y").unwrap())
+        );
+        assert_eq!(lookup(&[Index(2)]), Some(to_vec(&"tail").unwrap()));
+
+        // missing indices and keys, and mismatched segments
+        assert_eq!(lookup(&[Index(3)]), None);
+        assert_eq!(lookup(&[Index(1), Key("c".into())]), None);
+        assert_eq!(lookup(&[Key("a".into())]), None);
+        assert_eq!(lookup(&[Index(2), Index(0)]), None);
+
+        // malformed data is rejected
+        assert!(find_cbor_path(&data[..data.len() - 1], &[Index(2)]).is_err());
+    }
+
+    #[test]
+    fn path_roundtrip() {
+        let path = vec![PathSegment::Index(3), PathSegment::Key("foo".into())];
+        let data = to_vec(&path).unwrap();
+        assert_eq!(data, to_vec(&(3u64, "foo")).unwrap());
+        assert_eq!(from_slice::<Vec<PathSegment>>(&data).unwrap(), path);
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::cell::RefCell;
+
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+
+use crate::Error;
+
+/// A structure that can be flushed together with others into a single store write, with
+/// [`flush_all`].
+pub trait Flushable {
+    /// Flushes all modified nodes into `batch` instead of the structure's own store, returning the
+    /// new root. The nodes aren't persisted until the batch is written.
+    fn flush_batched(&mut self, batch: &Batch) -> Result<Cid, Error>;
+}
+
+/// The blocks written by the structures flushed in a [`flush_all`], buffered in memory until they
+/// can all be written at once.
+#[derive(Debug, Default)]
+pub struct Batch {
+    blocks: RefCell<Vec<(Cid, Vec<u8>)>>,
+}
+
+impl Blockstore for Batch {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self
+            .blocks
+            .borrow()
+            .iter()
+            .find(|(cid, _)| cid == k)
+            .map(|(_, block)| block.clone()))
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.blocks.borrow_mut().push((*k, block.to_vec()));
+        Ok(())
+    }
+}
+
+/// Flushes several HAMTs (or other [`Flushable`] structures) sharing `store`, writing all their
+/// modified nodes with a single [`Blockstore::put_many_keyed`]. Returns the new roots, in the same
+/// order as `hamts`.
+///
+/// The roots are the same as if each HAMT were flushed on its own. However, if the final write
+/// fails, the HAMTs may refer to nodes that were never written.
+///
+/// # Examples
+///
+/// ```
+/// use fvm_ipld_hamt::{flush_all, Flushable, Hamt};
+///
+/// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+///
+/// let mut a: Hamt<_, _, usize> = Hamt::new(&store);
+/// let mut b: Hamt<_, _, usize> = Hamt::new(&store);
+/// a.set(1, "a".to_string()).unwrap();
+/// b.set(2, "b".to_string()).unwrap();
+///
+/// let roots = flush_all(&store, &mut [&mut a as &mut dyn Flushable, &mut b]).unwrap();
+/// assert_eq!(roots, [a.flush().unwrap(), b.flush().unwrap()]);
+/// ```
+pub fn flush_all<BS: Blockstore>(
+    store: &BS,
+    hamts: &mut [&mut dyn Flushable],
+) -> Result<Vec<Cid>, Error> {
+    let batch = Batch::default();
+    let roots = hamts
+        .iter_mut()
+        .map(|hamt| hamt.flush_batched(&batch))
+        .collect::<Result<Vec<_>, _>>()?;
+    store.put_many_keyed(batch.blocks.into_inner())?;
+    Ok(roots)
+}
@@ -0,0 +1,142 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fvm_ipld_blockstore::Blockstore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::node::Node;
+use crate::pointer::{Pointer, Resolved};
+use crate::{Error, Hash, HashAlgorithm};
+
+/// A change to a single key between two HAMTs, as returned by [`Hamt::diff`](crate::Hamt::diff).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<K, V> {
+    /// The key was added, with the given value.
+    Add(K, V),
+    /// The key was removed. It previously had the given value.
+    Remove(K, V),
+    /// The key's value was changed from the first value to the second.
+    Modify(K, V, V),
+}
+
+/// The stores of the two HAMTs being compared.
+pub(crate) struct Stores<'a, S> {
+    pub(crate) left: &'a S,
+    pub(crate) right: &'a S,
+}
+
+/// Appends the changes from `left` to `right`, two nodes at the same depth and hash prefix, to
+/// `changes`.
+pub(crate) fn diff_nodes<S, K, V, H>(
+    left: &Node<K, V, H>,
+    right: &Node<K, V, H>,
+    stores: &Stores<S>,
+    bit_width: u32,
+    changes: &mut Vec<Change<K, V>>,
+) -> Result<(), Error>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone + PartialEq,
+    S: Blockstore,
+    H: HashAlgorithm,
+{
+    let (mut i, mut j) = (0, 0);
+    for idx in 0..1 << bit_width {
+        match (left.bitfield.test_bit(idx), right.bitfield.test_bit(idx)) {
+            (true, true) => {
+                diff_pointers(
+                    &left.pointers[i],
+                    &right.pointers[j],
+                    stores,
+                    bit_width,
+                    changes,
+                )?;
+                i += 1;
+                j += 1;
+            }
+            (true, false) => {
+                let removed = entries(&left.pointers[i], stores.left)?;
+                changes.extend(removed.into_iter().map(|(k, v)| Change::Remove(k, v)));
+                i += 1;
+            }
+            (false, true) => {
+                let added = entries(&right.pointers[j], stores.right)?;
+                changes.extend(added.into_iter().map(|(k, v)| Change::Add(k, v)));
+                j += 1;
+            }
+            (false, false) => {}
+        }
+    }
+    Ok(())
+}
+
+fn diff_pointers<S, K, V, H>(
+    left: &Pointer<K, V, H>,
+    right: &Pointer<K, V, H>,
+    stores: &Stores<S>,
+    bit_width: u32,
+    changes: &mut Vec<Change<K, V>>,
+) -> Result<(), Error>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone + PartialEq,
+    S: Blockstore,
+    H: HashAlgorithm,
+{
+    // Links to the same node can't contain any changes, so they needn't be loaded.
+    if let (Pointer::Link { cid: l, .. }, Pointer::Link { cid: r, .. }) = (left, right) {
+        if l == r {
+            return Ok(());
+        }
+    }
+
+    if let (Some(Resolved::Node(l)), Some(Resolved::Node(r))) =
+        (left.resolve(stores.left)?, right.resolve(stores.right)?)
+    {
+        return diff_nodes(l, r, stores, bit_width, changes);
+    }
+
+    // Otherwise at least one side is a bucket, so there are few enough entries to compare
+    // directly.
+    let left = entries(left, stores.left)?;
+    let mut right = entries(right, stores.right)?;
+    for (k, v) in left {
+        match right.iter().position(|(k2, _)| *k2 == k) {
+            Some(pos) => {
+                let (_, v2) = right.remove(pos);
+                if v != v2 {
+                    changes.push(Change::Modify(k, v, v2));
+                }
+            }
+            None => changes.push(Change::Remove(k, v)),
+        }
+    }
+    changes.extend(right.into_iter().map(|(k, v)| Change::Add(k, v)));
+    Ok(())
+}
+
+/// Returns copies of all the entries under a pointer.
+fn entries<S, K, V, H>(pointer: &Pointer<K, V, H>, store: &S) -> Result<Vec<(K, V)>, Error>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+    S: Blockstore,
+    H: HashAlgorithm,
+{
+    Ok(match pointer.resolve(store)? {
+        Some(Resolved::Values(kvs)) => kvs
+            .iter()
+            .map(|kv| (kv.key().clone(), kv.value().clone()))
+            .collect(),
+        Some(Resolved::Node(node)) => {
+            let mut entries = Vec::new();
+            node.for_each(store, &mut |k: &K, v: &V| {
+                entries.push((k.clone(), v.clone()));
+                Ok(())
+            })?;
+            entries
+        }
+        None => Vec::new(),
+    })
+}
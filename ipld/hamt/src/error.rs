@@ -18,6 +18,18 @@ pub enum Error {
     /// This should be treated as a fatal error, must have at least one pointer in node
     #[error("Invalid HAMT format, node cannot have 0 pointers")]
     ZeroPointers,
+    /// Input to a sorted builder wasn't sorted by key hash
+    #[error("Input is not sorted by key hash")]
+    Unsorted,
+    /// A HAMT can't be split into zero shards, or more shards than the root has indices
+    #[error("Cannot split HAMT into {0} shards")]
+    InvalidShardCount(usize),
+    /// HAMTs with different bit widths can't be joined or compared
+    #[error("Mismatched HAMT bit widths {0} and {1}")]
+    BitWidthMismatch(u32, u32),
+    /// A HAMT node was decoded, but isn't well-formed
+    #[error("Malformed HAMT node: {0}")]
+    MalformedNode(String),
     /// Cid not found in store error
     #[error("Cid ({0}) did not match any in database")]
     CidNotFound(String),
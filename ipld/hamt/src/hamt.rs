@@ -12,8 +12,16 @@ use multihash::Code;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Serializer};
 
+use crate::batch::{Batch, Flushable};
+use crate::diff::{diff_nodes, Stores};
+use crate::iter::HamtIter;
+use crate::join::Join;
 use crate::node::Node;
-use crate::{Error, Hash, HashAlgorithm, Sha256, DEFAULT_BIT_WIDTH};
+use crate::pointer::Pointer;
+use crate::{
+    Change, Checkpoint, Error, Hash, HashAlgorithm, HashedKey, Sha256, DEFAULT_BIT_WIDTH,
+    MAX_ARRAY_WIDTH,
+};
 
 /// Implementation of the HAMT data structure for IPLD.
 ///
@@ -81,6 +89,78 @@ where
         }
     }
 
+    /// Builds a hamt from entries sorted by the hash of their keys, constructing nodes directly
+    /// instead of inserting each entry from the root. The result is identical to inserting the same
+    /// entries with [`set`](Self::set), including the handling of duplicate keys (the last value
+    /// wins).
+    ///
+    /// Returns [`Error::Unsorted`] if the entries aren't sorted by key hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::{Hamt, HashAlgorithm, Sha256};
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut entries: Vec<_> = (0..100usize).map(|i| (i, i.to_string())).collect();
+    /// entries.sort_by_key(|(k, _)| Sha256::hash(k));
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::from_sorted(&store, entries).unwrap();
+    /// assert_eq!(map.get(&37).unwrap(), Some(&"37".to_string()));
+    ///
+    /// let mut expected: Hamt<_, _, usize> = Hamt::new(&store);
+    /// for i in 0..100 {
+    ///     expected.set(i, i.to_string()).unwrap();
+    /// }
+    /// assert_eq!(map.flush().unwrap(), expected.flush().unwrap());
+    /// ```
+    pub fn from_sorted<I>(store: BS, entries: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_sorted_with_bit_width(store, entries, DEFAULT_BIT_WIDTH)
+    }
+
+    /// Builds a hamt with a bit width from entries sorted by the hash of their keys. See
+    /// [`from_sorted`](Self::from_sorted).
+    pub fn from_sorted_with_bit_width<I>(
+        store: BS,
+        entries: I,
+        bit_width: u32,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut sorted: Vec<(HashedKey, K, V)> = Vec::new();
+        for (key, value) in entries {
+            let hash = H::hash(&key);
+            if matches!(sorted.last(), Some((last, ..)) if *last > hash) {
+                return Err(Error::Unsorted);
+            }
+
+            // Entries with equal hashes are adjacent, so a duplicate key can only be in the
+            // trailing run of equal hashes.
+            if let Some(dup) = sorted
+                .iter_mut()
+                .rev()
+                .take_while(|(h, ..)| *h == hash)
+                .find(|(_, k, _)| *k == key)
+            {
+                dup.2 = value;
+                continue;
+            }
+            sorted.push((hash, key, value));
+        }
+
+        Ok(Self {
+            root: Node::from_sorted(sorted, bit_width, 0)?,
+            store,
+            bit_width,
+            hash: Default::default(),
+        })
+    }
+
     /// Lazily instantiate a hamt from this root Cid.
     pub fn load(cid: &Cid, store: BS) -> Result<Self, Error> {
         Self::load_with_bit_width(cid, store, DEFAULT_BIT_WIDTH)
@@ -99,6 +179,43 @@ where
         }
     }
 
+    /// Checks that the root node at `cid` is present and well-formed, and that the blocks of its
+    /// immediate children are present in the store, without loading the rest of the HAMT. This is
+    /// a cheap sanity check before trusting a root from an untrusted source; it doesn't check
+    /// any nodes below the root's children.
+    ///
+    /// Returns [`Error::CidNotFound`] if the root or a child is missing, and
+    /// [`Error::MalformedNode`] if the root's bitfield doesn't match its pointers or it has an
+    /// empty or over-full bucket.
+    pub fn verify_root_shallow(cid: &Cid, store: &BS) -> Result<(), Error> {
+        let root: Node<K, V, H> = store
+            .get_cbor(cid)?
+            .ok_or_else(|| Error::CidNotFound(cid.to_string()))?;
+
+        if root.bitfield.count_ones() != root.pointers.len() {
+            return Err(Error::MalformedNode(format!(
+                "bitfield has {} bits set, but node has {} pointers",
+                root.bitfield.count_ones(),
+                root.pointers.len()
+            )));
+        }
+        for pointer in &root.pointers {
+            match pointer {
+                Pointer::Values(kvs) if kvs.is_empty() || kvs.len() > MAX_ARRAY_WIDTH => {
+                    return Err(Error::MalformedNode(format!(
+                        "bucket has {} entries",
+                        kvs.len()
+                    )));
+                }
+                Pointer::Link { cid, .. } if !store.has(cid)? => {
+                    return Err(Error::CidNotFound(cid.to_string()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     /// Sets the root based on the Cid of the root node using the Hamt store
     pub fn set_root(&mut self, cid: &Cid) -> Result<(), Error> {
         match self.store.get_cbor(cid)? {
@@ -145,6 +262,39 @@ where
             .map(|(r, _)| r)
     }
 
+    /// Inserts a batch of key-value pairs, with the same result as calling [`set`](Self::set) on
+    /// each in turn: if a key appears more than once, the last value wins.
+    ///
+    /// The entries are applied in order of their key hashes, so entries under the same node are
+    /// inserted together, while the nodes they touch are loaded and cached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(store);
+    /// map.set_many([(1, "a".to_string()), (2, "b".to_string()), (1, "c".to_string())])
+    ///     .unwrap();
+    /// assert_eq!(map.get(&1).unwrap(), Some(&"c".to_string()));
+    /// assert_eq!(map.get(&2).unwrap(), Some(&"b".to_string()));
+    /// ```
+    pub fn set_many<I>(&mut self, entries: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        V: PartialEq,
+    {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        // The sort is stable, so duplicate keys are still set in their original order.
+        entries.sort_by_cached_key(|(k, _)| H::hash(k));
+        for (key, value) in entries {
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+
     /// Inserts a key-value pair into the HAMT only if that key does not already exist.
     ///
     /// If the HAMT did not have this key present, `true` is returned and the key/value is added.
@@ -272,12 +422,66 @@ where
             .remove_entry(k, self.store.borrow(), self.bit_width)
     }
 
+    /// Removes a batch of keys, with the same result as calling [`delete`](Self::delete) on each
+    /// in turn. Keys that aren't in the HAMT are ignored.
+    ///
+    /// As with [`set_many`](Self::set_many), the keys are removed in order of their hashes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(store);
+    /// map.set_many((0..10).map(|i| (i, i.to_string()))).unwrap();
+    /// map.delete_many(&[1, 2, 3, 20]).unwrap();
+    /// assert_eq!(map.get(&1).unwrap(), None);
+    /// assert_eq!(map.get(&4).unwrap(), Some(&"4".to_string()));
+    /// ```
+    pub fn delete_many<'a, Q, I>(&mut self, keys: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = &'a Q>,
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized + 'a,
+    {
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        keys.sort_by_cached_key(|k| H::hash(*k));
+        for key in keys {
+            self.delete(key)?;
+        }
+        Ok(())
+    }
+
     /// Flush root and return Cid for hamt
     pub fn flush(&mut self) -> Result<Cid, Error> {
         self.root.flush(self.store.borrow())?;
         Ok(self.store.put_cbor(&self.root, Code::Blake2b256)?)
     }
 
+    /// Returns an estimate of the number of bytes the HAMT will occupy in the store once flushed,
+    /// without flushing it.
+    ///
+    /// Modified nodes are measured by their encoded size, and unmodified nodes by their stored
+    /// size. Nothing is hashed or written, so this is much cheaper than [`Hamt::flush`]. However,
+    /// unmodified nodes that haven't been loaded yet will be loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(store);
+    /// map.set(1, "a".to_string()).unwrap();
+    /// assert!(map.approximate_size_bytes().unwrap() > 0);
+    /// ```
+    pub fn approximate_size_bytes(&self) -> Result<usize, Error> {
+        self.root.approximate_size(self.store.borrow())
+    }
+
     /// Returns true if the HAMT has no entries
     pub fn is_empty(&self) -> bool {
         self.root.is_empty()
@@ -314,8 +518,280 @@ where
         self.root.for_each(self.store.borrow(), &mut f)
     }
 
+    /// Counts the entries of the HAMT for which `f` returns true, walking the HAMT once without
+    /// collecting or cloning the entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(store);
+    /// map.set(1, 1).unwrap();
+    /// map.set(2, 2).unwrap();
+    /// map.set(3, 4).unwrap();
+    ///
+    /// let even = map.count_matching(|_, v: &u64| v % 2 == 0).unwrap();
+    /// assert_eq!(even, 2);
+    /// ```
+    pub fn count_matching<F>(&self, mut f: F) -> Result<usize, Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut count = 0;
+        self.root.for_each(self.store.borrow(), &mut |k, v| {
+            if f(k, v) {
+                count += 1;
+            }
+            Ok(())
+        })?;
+        Ok(count)
+    }
+
+    /// Returns an iterator over the entries of the HAMT, in the same order as
+    /// [`for_each`](Self::for_each). Nodes are loaded lazily as the iterator advances, so iteration
+    /// may be stopped early without loading the rest of the HAMT. Loading errors are yielded as
+    /// items, after which iteration stops.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(store);
+    /// for i in 0..10 {
+    ///     map.set(i, i * 10).unwrap();
+    /// }
+    ///
+    /// let found = map
+    ///     .iter()
+    ///     .find_map(|res| res.map(|(k, v)| (*v == 40).then(|| *k)).transpose());
+    /// assert_eq!(found.unwrap().unwrap(), 4);
+    /// ```
+    pub fn iter(&self) -> HamtIter<'_, BS, V, K, H> {
+        HamtIter::new(&self.store, &self.root)
+    }
+
+    /// Iterates over each KV in the Hamt like [`for_each`](Self::for_each), also passing the
+    /// [`Checkpoint`] of each entry to `f`. A checkpoint may be persisted and later passed to
+    /// [`resume_checkpointed`](Self::resume_checkpointed) to resume the traversal after its entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(store);
+    /// for i in 0..10 {
+    ///     map.set(i, i).unwrap();
+    /// }
+    ///
+    /// let mut checkpoints = Vec::new();
+    /// map.for_each_checkpointed(|_, _: &usize, checkpoint| {
+    ///     checkpoints.push(checkpoint.clone());
+    ///     Ok(())
+    /// }).unwrap();
+    ///
+    /// let mut remaining = 0;
+    /// map.resume_checkpointed(&checkpoints[4], |_, _, _| {
+    ///     remaining += 1;
+    ///     Ok(())
+    /// }).unwrap();
+    /// assert_eq!(remaining, 5);
+    /// ```
+    #[inline]
+    pub fn for_each_checkpointed<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        V: DeserializeOwned,
+        F: FnMut(&K, &V, &Checkpoint) -> anyhow::Result<()>,
+    {
+        self.root.for_each_checkpointed(
+            self.store.borrow(),
+            None,
+            &mut Checkpoint::default(),
+            &mut f,
+        )
+    }
+
+    /// Resumes a traversal started with [`for_each_checkpointed`](Self::for_each_checkpointed),
+    /// visiting only the entries after the given checkpoint. The HAMT must not have been modified
+    /// since the checkpoint was taken.
+    #[inline]
+    pub fn resume_checkpointed<F>(&self, checkpoint: &Checkpoint, mut f: F) -> Result<(), Error>
+    where
+        V: DeserializeOwned,
+        F: FnMut(&K, &V, &Checkpoint) -> anyhow::Result<()>,
+    {
+        self.root.for_each_checkpointed(
+            self.store.borrow(),
+            Some(&checkpoint.0),
+            &mut Checkpoint::default(),
+            &mut f,
+        )
+    }
+
+    /// Returns an iterator over the entries whose keys are in both this HAMT and `other`, yielding
+    /// each key with its values in both. Keys in only one of the HAMTs are skipped.
+    ///
+    /// The HAMTs are walked in lockstep, so only the hash prefixes present in both are visited, and
+    /// nodes are loaded as the iterator advances. Both HAMTs must use the same bit width, otherwise
+    /// the iterator yields [`Error::BitWidthMismatch`]. Iteration stops after the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut balances: Hamt<_, _, usize> = Hamt::new(&store);
+    /// balances.set(1, 10).unwrap();
+    /// balances.set(2, 20).unwrap();
+    ///
+    /// let mut names: Hamt<_, _, usize> = Hamt::new(&store);
+    /// names.set(2, "b".to_string()).unwrap();
+    /// names.set(3, "c".to_string()).unwrap();
+    ///
+    /// let joined: Vec<_> = balances.join(&names).collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(joined, vec![(&2, &20, &"b".to_string())]);
+    /// ```
+    pub fn join<'a, BS2, V2>(
+        &'a self,
+        other: &'a Hamt<BS2, V2, K, H>,
+    ) -> impl Iterator<Item = Result<(&'a K, &'a V, &'a V2), Error>> + 'a
+    where
+        BS2: Blockstore,
+        V2: Serialize + DeserializeOwned,
+    {
+        Join::new(
+            &self.root,
+            &self.store,
+            self.bit_width,
+            &other.root,
+            &other.store,
+            other.bit_width,
+        )
+    }
+
+    /// Returns the changes from this HAMT to `other`: the keys added, removed and modified, in a
+    /// deterministic order. Subtrees linked by the same CID in both HAMTs can't contain changes, so
+    /// they're skipped without being loaded.
+    ///
+    /// Returns [`Error::BitWidthMismatch`] if the HAMTs have different bit widths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::{Change, Hamt};
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut before: Hamt<_, _, usize> = Hamt::new(&store);
+    /// before.set(1, "a".to_string()).unwrap();
+    /// before.set(2, "b".to_string()).unwrap();
+    ///
+    /// let mut after: Hamt<_, _, usize> = Hamt::new(&store);
+    /// after.set(1, "a".to_string()).unwrap();
+    /// after.set(2, "c".to_string()).unwrap();
+    ///
+    /// let changes = before.diff(&after).unwrap();
+    /// assert_eq!(changes, vec![Change::Modify(2, "b".to_string(), "c".to_string())]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> Result<Vec<Change<K, V>>, Error>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+    {
+        if self.bit_width != other.bit_width {
+            return Err(Error::BitWidthMismatch(self.bit_width, other.bit_width));
+        }
+        let stores = Stores {
+            left: &self.store,
+            right: &other.store,
+        };
+        let mut changes = Vec::new();
+        diff_nodes(
+            &self.root,
+            &other.root,
+            &stores,
+            self.bit_width,
+            &mut changes,
+        )?;
+        Ok(changes)
+    }
+
+    /// Splits the HAMT into `shards` HAMTs sharing its store, e.g. for migrating a large HAMT in
+    /// parallel. Each shard holds the keys whose hashes fall in a contiguous range of prefixes (of
+    /// `bit_width` bits), in order, so the shards are disjoint and together hold every key. Each
+    /// shard is identical to a HAMT built by inserting its keys.
+    ///
+    /// Returns [`Error::InvalidShardCount`] unless `1 <= shards <= 2^bit_width`. Shards may be
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(&store);
+    /// for i in 0..100 {
+    ///     map.set(i, i).unwrap();
+    /// }
+    ///
+    /// let mut total = 0;
+    /// for shard in map.split_at_prefix(4).unwrap() {
+    ///     shard.for_each(|_, v: &usize| {
+    ///         total += v;
+    ///         Ok(())
+    ///     }).unwrap();
+    /// }
+    /// assert_eq!(total, (0..100).sum());
+    /// ```
+    pub fn split_at_prefix(&self, shards: usize) -> Result<Vec<Self>, Error>
+    where
+        BS: Clone,
+        K: Clone,
+        V: Clone,
+    {
+        if shards == 0 || shards > 1 << self.bit_width {
+            return Err(Error::InvalidShardCount(shards));
+        }
+        Ok(self
+            .root
+            .split(shards, self.bit_width)
+            .into_iter()
+            .map(|root| Self {
+                root,
+                store: self.store.clone(),
+                bit_width: self.bit_width,
+                hash: Default::default(),
+            })
+            .collect())
+    }
+
     /// Consumes this HAMT and returns the Blockstore it owns.
     pub fn into_store(self) -> BS {
         self.store
     }
 }
+
+impl<BS, V, K, H> Flushable for Hamt<BS, V, K, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+    H: HashAlgorithm,
+{
+    fn flush_batched(&mut self, batch: &Batch) -> Result<Cid, Error> {
+        self.root.flush(batch)?;
+        Ok(batch.put_cbor(&self.root, Code::Blake2b256)?)
+    }
+}
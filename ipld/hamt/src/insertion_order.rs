@@ -0,0 +1,187 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::borrow::Borrow;
+
+use cid::Cid;
+use forest_hash_utils::BytesKey;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use multihash::Code;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Batch, Error, Flushable, Hamt, Hash, HashAlgorithm, Sha256, DEFAULT_BIT_WIDTH};
+
+/// A [`Hamt`] that can be iterated in insertion order, with
+/// [`for_each_insertion_order`](Self::for_each_insertion_order).
+///
+/// Each value is stored along with a sequence number, recording when its key was inserted, so
+/// entries take a few more bytes than in a plain [`Hamt`]. Setting an existing key keeps its
+/// position, while a key that's deleted and set again moves to the end.
+///
+/// The root CID refers to a `(hamt_root, next_seq)` tuple rather than to the HAMT's root node, so
+/// the next sequence number is known on load without scanning the entries.
+///
+/// # Examples
+///
+/// ```
+/// use fvm_ipld_hamt::InsertionOrderHamt;
+///
+/// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+///
+/// let mut map: InsertionOrderHamt<_, _, usize> = InsertionOrderHamt::new(store);
+/// for k in [3, 1, 2] {
+///     map.set(k, k * 10).unwrap();
+/// }
+///
+/// let mut keys = Vec::new();
+/// map.for_each_insertion_order(|k, _| {
+///     keys.push(*k);
+///     Ok(())
+/// }).unwrap();
+/// assert_eq!(keys, [3, 1, 2]);
+/// ```
+#[derive(Debug)]
+pub struct InsertionOrderHamt<BS, V, K = BytesKey, H = Sha256> {
+    hamt: Hamt<BS, (u64, V), K, H>,
+    /// The sequence number of the next inserted key, persisted alongside the HAMT's root.
+    next_seq: u64,
+}
+
+impl<BS, V, K, H> InsertionOrderHamt<BS, V, K, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+    H: HashAlgorithm,
+{
+    pub fn new(store: BS) -> Self {
+        Self::new_with_bit_width(store, DEFAULT_BIT_WIDTH)
+    }
+
+    /// Construct hamt with a bit width
+    pub fn new_with_bit_width(store: BS, bit_width: u32) -> Self {
+        Self {
+            hamt: Hamt::new_with_bit_width(store, bit_width),
+            next_seq: 0,
+        }
+    }
+
+    /// Lazily instantiate a hamt from this root Cid.
+    pub fn load(cid: &Cid, store: BS) -> Result<Self, Error> {
+        Self::load_with_bit_width(cid, store, DEFAULT_BIT_WIDTH)
+    }
+
+    /// Lazily instantiate a hamt from this root Cid with a specified bit width.
+    pub fn load_with_bit_width(cid: &Cid, store: BS, bit_width: u32) -> Result<Self, Error> {
+        let (root, next_seq): (Cid, u64) = store
+            .get_cbor(cid)?
+            .ok_or_else(|| Error::CidNotFound(cid.to_string()))?;
+        Ok(Self {
+            hamt: Hamt::load_with_bit_width(&root, store, bit_width)?,
+            next_seq,
+        })
+    }
+
+    /// Returns a reference to the underlying store of the Hamt.
+    pub fn store(&self) -> &BS {
+        self.hamt.store()
+    }
+
+    /// Inserts a key-value pair, returning the previous value, if any. A new key is ordered after
+    /// all the keys already in the HAMT, while an existing key keeps its position.
+    pub fn set(&mut self, key: K, value: V) -> Result<Option<V>, Error>
+    where
+        V: PartialEq,
+    {
+        let seq = match self.hamt.get(&key)? {
+            Some((seq, _)) => *seq,
+            None => {
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                seq
+            }
+        };
+        Ok(self.hamt.set(key, (seq, value))?.map(|(_, v)| v))
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q>(&self, k: &Q) -> Result<Option<&V>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.hamt.get(k)?.map(|(_, v)| v))
+    }
+
+    /// Returns `true` if a value exists for the given key.
+    pub fn contains_key<Q>(&self, k: &Q) -> Result<bool, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.hamt.contains_key(k)
+    }
+
+    /// Removes a key from the HAMT, returning the key-value pair if it was present.
+    pub fn delete<Q>(&mut self, k: &Q) -> Result<Option<(K, V)>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.hamt.delete(k)?.map(|(k, (_, v))| (k, v)))
+    }
+
+    /// Flush root and the next sequence number, and return the Cid they are stored under.
+    pub fn flush(&mut self) -> Result<Cid, Error> {
+        let root = self.hamt.flush()?;
+        Ok(self
+            .store()
+            .put_cbor(&(root, self.next_seq), Code::Blake2b256)?)
+    }
+
+    /// Iterates over each KV in the Hamt, in hash order like [`Hamt::for_each`].
+    pub fn for_each<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V) -> anyhow::Result<()>,
+    {
+        self.hamt.for_each(|k, (_, v)| f(k, v))
+    }
+
+    /// Iterates over each KV in the Hamt, in the order the keys were inserted. All entries are
+    /// loaded before the first is visited.
+    pub fn for_each_insertion_order<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V) -> anyhow::Result<()>,
+    {
+        let mut entries = self
+            .hamt
+            .iter()
+            .map(|res| res.map(|(k, (seq, v))| (*seq, k, v)))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_unstable_by_key(|(seq, _, _)| *seq);
+        for (_, k, v) in entries {
+            f(k, v)?;
+        }
+        Ok(())
+    }
+
+    /// Consumes this HAMT and returns the Blockstore it owns.
+    pub fn into_store(self) -> BS {
+        self.hamt.into_store()
+    }
+}
+
+impl<BS, V, K, H> Flushable for InsertionOrderHamt<BS, V, K, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+    H: HashAlgorithm,
+{
+    fn flush_batched(&mut self, batch: &Batch) -> Result<Cid, Error> {
+        let root = self.hamt.flush_batched(batch)?;
+        Ok(batch.put_cbor(&(root, self.next_seq), Code::Blake2b256)?)
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::slice;
+
+use fvm_ipld_blockstore::Blockstore;
+use serde::de::DeserializeOwned;
+
+use crate::node::Node;
+use crate::pointer::{Pointer, Resolved};
+use crate::{Error, KeyValuePair};
+
+/// An iterator over the entries of a [`Hamt`](crate::Hamt), as returned by
+/// [`Hamt::iter`](crate::Hamt::iter).
+///
+/// Entries are yielded in the same order as [`Hamt::for_each`](crate::Hamt::for_each) visits them.
+/// Nodes are loaded from the store as the iterator reaches them, and cached in the HAMT. Iteration
+/// stops after the first error.
+pub struct HamtIter<'a, BS, V, K, H> {
+    store: &'a BS,
+    /// The remaining pointers of each node on the path to the current entry.
+    stack: Vec<slice::Iter<'a, Pointer<K, V, H>>>,
+    /// The remaining entries of the current bucket.
+    current: slice::Iter<'a, KeyValuePair<K, V>>,
+}
+
+impl<'a, BS, V, K, H> HamtIter<'a, BS, V, K, H> {
+    pub(crate) fn new(store: &'a BS, root: &'a Node<K, V, H>) -> Self {
+        Self {
+            store,
+            stack: vec![root.pointers.iter()],
+            current: [].iter(),
+        }
+    }
+}
+
+impl<'a, BS, V, K, H> Iterator for HamtIter<'a, BS, V, K, H>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    BS: Blockstore,
+{
+    type Item = Result<(&'a K, &'a V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(kv) = self.current.next() {
+                return Some(Ok((kv.key(), kv.value())));
+            }
+
+            let pointer = match self.stack.last_mut()?.next() {
+                Some(pointer) => pointer,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match pointer.resolve(self.store) {
+                Ok(Some(Resolved::Values(kvs))) => self.current = kvs.iter(),
+                Ok(Some(Resolved::Node(node))) => self.stack.push(node.pointers.iter()),
+                Ok(None) => {}
+                Err(e) => {
+                    self.stack.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fvm_ipld_blockstore::Blockstore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::hash_bits::HashBits;
+use crate::node::Node;
+use crate::pointer::{Pointer, Resolved};
+use crate::{Error, Hash, HashAlgorithm, KeyValuePair};
+
+/// A pending step of a [`Join`].
+enum Step<'a, K, V, V2, H> {
+    /// Join the pointers of two nodes at the same depth and hash prefix.
+    Nodes(&'a Node<K, V, H>, &'a Node<K, V2, H>, u32),
+    /// Join two pointers at the same index of nodes at the given depth.
+    Pointers(&'a Pointer<K, V, H>, &'a Pointer<K, V2, H>, u32),
+    /// Look up the key of a left entry in a right node at the given depth.
+    FindRight(&'a KeyValuePair<K, V>, &'a Node<K, V2, H>, u32),
+    /// Look up the key of a right entry in a left node at the given depth.
+    FindLeft(&'a Node<K, V, H>, &'a KeyValuePair<K, V2>, u32),
+    /// An entry present in both HAMTs.
+    Found(&'a K, &'a V, &'a V2),
+}
+
+/// An iterator over the entries whose keys are in both of two HAMTs, as returned by
+/// [`Hamt::join`](crate::Hamt::join).
+///
+/// The HAMTs are walked in lockstep, only descending into the hash prefixes present in both. Where
+/// one HAMT has a bucket of values and the other a node, the bucket's keys are looked up in the
+/// node. Nodes are loaded lazily, as the iterator advances.
+pub(crate) struct Join<'a, S1, S2, K, V, V2, H> {
+    left_store: &'a S1,
+    right_store: &'a S2,
+    bit_width: u32,
+    stack: Vec<Step<'a, K, V, V2, H>>,
+    error: Option<Error>,
+}
+
+impl<'a, S1, S2, K, V, V2, H> Join<'a, S1, S2, K, V, V2, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    V2: Serialize + DeserializeOwned,
+    S1: Blockstore,
+    S2: Blockstore,
+    H: HashAlgorithm,
+{
+    /// Joins two HAMTs, given their roots, stores and bit widths. HAMTs with different bit widths
+    /// can't be joined, so the iterator yields a single error.
+    pub(crate) fn new(
+        left: &'a Node<K, V, H>,
+        left_store: &'a S1,
+        left_bit_width: u32,
+        right: &'a Node<K, V2, H>,
+        right_store: &'a S2,
+        right_bit_width: u32,
+    ) -> Self {
+        let mut join = Join {
+            left_store,
+            right_store,
+            bit_width: left_bit_width,
+            stack: Vec::new(),
+            error: None,
+        };
+        if left_bit_width == right_bit_width {
+            join.stack.push(Step::Nodes(left, right, 0));
+        } else {
+            join.error = Some(Error::BitWidthMismatch(left_bit_width, right_bit_width));
+        }
+        join
+    }
+
+    /// Runs a single step, pushing any further steps it produces.
+    fn step(
+        &mut self,
+        step: Step<'a, K, V, V2, H>,
+    ) -> Result<Option<(&'a K, &'a V, &'a V2)>, Error> {
+        match step {
+            Step::Found(k, v, v2) => return Ok(Some((k, v, v2))),
+            Step::Nodes(left, right, depth) => {
+                // Pair up the pointers at the indices set in both nodes, pushing them in reverse
+                // so they're visited in order.
+                let mut pairs = Vec::new();
+                let (mut i, mut j) = (0, 0);
+                for idx in 0..1 << self.bit_width {
+                    match (left.bitfield.test_bit(idx), right.bitfield.test_bit(idx)) {
+                        (true, true) => {
+                            pairs.push(Step::Pointers(
+                                &left.pointers[i],
+                                &right.pointers[j],
+                                depth,
+                            ));
+                            i += 1;
+                            j += 1;
+                        }
+                        (true, false) => i += 1,
+                        (false, true) => j += 1,
+                        (false, false) => {}
+                    }
+                }
+                self.stack.extend(pairs.into_iter().rev());
+            }
+            Step::Pointers(left, right, depth) => {
+                let (left, right) = match (
+                    left.resolve(self.left_store)?,
+                    right.resolve(self.right_store)?,
+                ) {
+                    (Some(left), Some(right)) => (left, right),
+                    _ => return Ok(None),
+                };
+                let depth = depth + 1;
+                match (left, right) {
+                    (Resolved::Node(left), Resolved::Node(right)) => {
+                        self.stack.push(Step::Nodes(left, right, depth))
+                    }
+                    (Resolved::Values(left), Resolved::Node(right)) => self.stack.extend(
+                        left.iter()
+                            .rev()
+                            .map(|kv| Step::FindRight(kv, right, depth)),
+                    ),
+                    (Resolved::Node(left), Resolved::Values(right)) => self
+                        .stack
+                        .extend(right.iter().rev().map(|kv| Step::FindLeft(left, kv, depth))),
+                    (Resolved::Values(left), Resolved::Values(right)) => {
+                        self.stack.extend(left.iter().rev().filter_map(|kv| {
+                            right
+                                .iter()
+                                .find(|kv2| kv2.key() == kv.key())
+                                .map(|kv2| Step::Found(kv.key(), kv.value(), kv2.value()))
+                        }))
+                    }
+                }
+            }
+            Step::FindRight(kv, right, depth) => {
+                let hash = H::hash(kv.key());
+                let mut hashed_key = HashBits::new_at_index(&hash, depth * self.bit_width);
+                let found = right.get_value(
+                    &mut hashed_key,
+                    self.bit_width,
+                    depth as u64,
+                    kv.key(),
+                    self.right_store,
+                )?;
+                return Ok(found.map(|kv2| (kv.key(), kv.value(), kv2.value())));
+            }
+            Step::FindLeft(left, kv2, depth) => {
+                let hash = H::hash(kv2.key());
+                let mut hashed_key = HashBits::new_at_index(&hash, depth * self.bit_width);
+                let found = left.get_value(
+                    &mut hashed_key,
+                    self.bit_width,
+                    depth as u64,
+                    kv2.key(),
+                    self.left_store,
+                )?;
+                return Ok(found.map(|kv| (kv.key(), kv.value(), kv2.value())));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, S1, S2, K, V, V2, H> Iterator for Join<'a, S1, S2, K, V, V2, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    V2: Serialize + DeserializeOwned,
+    S1: Blockstore,
+    S2: Blockstore,
+    H: HashAlgorithm,
+{
+    type Item = Result<(&'a K, &'a V, &'a V2), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        while let Some(step) = self.stack.pop() {
+            match self.step(step) {
+                Ok(Some(entry)) => return Some(Ok(entry)),
+                Ok(None) => {}
+                Err(e) => {
+                    // Stop after the first error.
+                    self.stack.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
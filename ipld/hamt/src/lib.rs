@@ -9,22 +9,33 @@
 //!
 //! The Hamt is a data structure that mimmics a HashMap which has the features of being sharded, persisted, and indexable by a Cid. The Hamt supports a variable bit width to adjust the amount of possible pointers that can exist at each height of the tree. Hamt can be modified at any point, but the underlying values are only persisted to the store when the [flush](struct.Hamt.html#method.flush) is called.
 
+mod batch;
 mod bitfield;
+mod diff;
 mod error;
 mod hamt;
 mod hash;
 mod hash_algorithm;
 mod hash_bits;
+mod insertion_order;
+mod iter;
+mod join;
 mod node;
 mod pointer;
+mod soft_delete;
 
 pub use forest_hash_utils::{BytesKey, Hash};
 use serde::{Deserialize, Serialize};
 
+pub use self::batch::{flush_all, Batch, Flushable};
+pub use self::diff::Change;
 pub use self::error::Error;
 pub use self::hamt::Hamt;
 pub use self::hash::*;
 pub use self::hash_algorithm::*;
+pub use self::insertion_order::InsertionOrderHamt;
+pub use self::iter::HamtIter;
+pub use self::soft_delete::{SoftDeleteEntry, SoftDeleteHamt};
 
 const MAX_ARRAY_WIDTH: usize = 3;
 
@@ -33,7 +44,16 @@ const DEFAULT_BIT_WIDTH: u32 = 8;
 
 type HashedKey = [u8; 32];
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// A position in a [`Hamt`] traversal, as passed to the callback of
+/// [`Hamt::for_each_checkpointed`]. Traversal may be resumed after the entry at this position with
+/// [`Hamt::resume_checkpointed`], provided the HAMT hasn't changed since.
+///
+/// Checkpoints are serializable, so they may be persisted between steps of a long traversal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Checkpoint(Vec<usize>);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct KeyValuePair<K, V>(K, V);
 
 impl<K, V> KeyValuePair<K, V> {
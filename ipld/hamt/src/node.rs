@@ -2,12 +2,15 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
+use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::CborStore;
-use multihash::Code;
+use fvm_ipld_encoding::{from_slice, to_vec, CborStore, DAG_CBOR};
+use multihash::{Code, MultihashDigest};
+use once_cell::sync::Lazy;
 use once_cell::unsync::OnceCell;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -15,7 +18,24 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use super::bitfield::Bitfield;
 use super::hash_bits::HashBits;
 use super::pointer::Pointer;
-use super::{Error, Hash, HashAlgorithm, KeyValuePair, MAX_ARRAY_WIDTH};
+use super::{Checkpoint, Error, Hash, HashAlgorithm, HashedKey, KeyValuePair, MAX_ARRAY_WIDTH};
+
+/// The encoded size of a link to a flushed node.
+static LINK_SIZE: Lazy<usize> = Lazy::new(|| {
+    let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[]));
+    to_vec(&cid).expect("failed to encode cid").len()
+});
+
+/// Returns the size of a CBOR major type header for the given length.
+fn cbor_header_size(len: usize) -> usize {
+    match len {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
 
 /// Node in Hamt tree which contains bitfield of set indexes and pointers to nodes
 #[derive(Debug)]
@@ -31,6 +51,16 @@ impl<K: PartialEq, V: PartialEq, H> PartialEq for Node<K, V, H> {
     }
 }
 
+impl<K: Clone, V: Clone, H> Clone for Node<K, V, H> {
+    fn clone(&self) -> Self {
+        Node {
+            bitfield: self.bitfield,
+            pointers: self.pointers.clone(),
+            hash: Default::default(),
+        }
+    }
+}
+
 impl<K, V, H> Serialize for Node<K, V, H>
 where
     K: Serialize,
@@ -135,6 +165,65 @@ where
         self.pointers.is_empty()
     }
 
+    /// Builds a node directly from entries sorted by key hash, indexing each hash from the
+    /// `consumed` bit onwards. Buckets with more than `MAX_ARRAY_WIDTH` entries become child
+    /// nodes, matching the structure produced by inserting the same entries one at a time.
+    pub(crate) fn from_sorted(
+        entries: Vec<(HashedKey, K, V)>,
+        bit_width: u32,
+        consumed: u32,
+    ) -> Result<Self, Error> {
+        let index = |hash: &HashedKey| HashBits::new_at_index(hash, consumed).next(bit_width);
+
+        let mut node = Self::default();
+        let mut entries = entries.into_iter().peekable();
+        while let Some(first) = entries.next() {
+            let idx = index(&first.0)?;
+            let mut bucket = vec![first];
+            while let Some(next) = entries.next_if(|(hash, ..)| index(hash).ok() == Some(idx)) {
+                bucket.push(next);
+            }
+
+            let pointer = if bucket.len() <= MAX_ARRAY_WIDTH {
+                // Values within a bucket are kept ordered by key.
+                let mut kvs: Vec<_> = bucket
+                    .into_iter()
+                    .map(|(_, k, v)| KeyValuePair::new(k, v))
+                    .collect();
+                kvs.sort_by(|a, b| a.key().partial_cmp(b.key()).unwrap_or(Ordering::Equal));
+                Pointer::Values(kvs)
+            } else {
+                Pointer::Dirty(Box::new(Self::from_sorted(
+                    bucket,
+                    bit_width,
+                    consumed + bit_width,
+                )?))
+            };
+            node.bitfield.set_bit(idx);
+            node.pointers.push(pointer);
+        }
+        Ok(node)
+    }
+
+    /// Splits the node into `shards` nodes, each holding the pointers for a contiguous range of
+    /// indices. The caller must check that there are at most `2^bit_width` shards.
+    pub(crate) fn split(&self, shards: usize, bit_width: u32) -> Vec<Self>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let width = 1usize << bit_width;
+        let mut nodes: Vec<Self> = (0..shards).map(|_| Self::default()).collect();
+        // Pointers are ordered by index, so they can be matched up with the set bits in order.
+        let set_bits = (0..width as u32).filter(|&idx| self.bitfield.test_bit(idx));
+        for (idx, pointer) in set_bits.zip(&self.pointers) {
+            let node = &mut nodes[idx as usize * shards / width];
+            node.bitfield.set_bit(idx);
+            node.pointers.push(pointer.clone());
+        }
+        nodes
+    }
+
     pub(crate) fn for_each<S, F>(&self, store: &S, f: &mut F) -> Result<(), Error>
     where
         F: FnMut(&K, &V) -> anyhow::Result<()>,
@@ -172,6 +261,124 @@ where
         Ok(())
     }
 
+    /// Like [`for_each`](Self::for_each), but passes the position of each entry to `f`. The
+    /// position of this node is at the top of `checkpoint`, and only the entries after `skip` (the
+    /// remainder of a checkpoint below this node, if any) are visited.
+    pub(crate) fn for_each_checkpointed<S, F>(
+        &self,
+        store: &S,
+        skip: Option<&[usize]>,
+        checkpoint: &mut Checkpoint,
+        f: &mut F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V, &Checkpoint) -> anyhow::Result<()>,
+        S: Blockstore,
+    {
+        let (start, rest) = match skip {
+            Some([first, rest @ ..]) => (*first, Some(rest)),
+            _ => (0, None),
+        };
+        for (i, p) in self.pointers.iter().enumerate().skip(start) {
+            // Only the pointer holding the checkpointed entry is partially visited.
+            let skip = if i == start { rest } else { None };
+            checkpoint.0.push(i);
+            match p {
+                Pointer::Link { cid, cache } => {
+                    if let Some(cached_node) = cache.get() {
+                        cached_node.for_each_checkpointed(store, skip, checkpoint, f)?
+                    } else {
+                        let node = if let Some(node) = store.get_cbor(cid)? {
+                            node
+                        } else {
+                            #[cfg(not(feature = "ignore-dead-links"))]
+                            return Err(Error::CidNotFound(cid.to_string()));
+
+                            #[cfg(feature = "ignore-dead-links")]
+                            {
+                                checkpoint.0.pop();
+                                continue;
+                            }
+                        };
+
+                        // Ignore error intentionally, the cache value will always be the same
+                        let cache_node = cache.get_or_init(|| node);
+                        cache_node.for_each_checkpointed(store, skip, checkpoint, f)?
+                    }
+                }
+                Pointer::Dirty(n) => n.for_each_checkpointed(store, skip, checkpoint, f)?,
+                Pointer::Values(kvs) => {
+                    let start = match skip {
+                        Some([j, ..]) => j + 1,
+                        _ => 0,
+                    };
+                    for (j, kv) in kvs.iter().enumerate().skip(start) {
+                        checkpoint.0.push(j);
+                        f(kv.key(), kv.value(), checkpoint)?;
+                        checkpoint.0.pop();
+                    }
+                }
+            }
+            checkpoint.0.pop();
+        }
+        Ok(())
+    }
+
+    /// Returns an estimate of the number of bytes this node and all of its children will occupy in
+    /// the store once flushed.
+    pub(crate) fn approximate_size<S: Blockstore>(&self, store: &S) -> Result<usize, Error> {
+        Ok(self.encoded_size()? + self.children_size(store)?)
+    }
+
+    /// Returns the size of this node, once encoded. Dirty children are counted as links.
+    fn encoded_size(&self) -> Result<usize, Error> {
+        let mut size = cbor_header_size(2)
+            + to_vec(&self.bitfield)?.len()
+            + cbor_header_size(self.pointers.len());
+        for p in &self.pointers {
+            size += match p {
+                Pointer::Values(kvs) => to_vec(kvs)?.len(),
+                Pointer::Link { cid, .. } => to_vec(cid)?.len(),
+                // Dirty nodes will be replaced with a link when flushed.
+                Pointer::Dirty(_) => *LINK_SIZE,
+            };
+        }
+        Ok(size)
+    }
+
+    /// Returns the size of all children of this node. Children that haven't been loaded yet are
+    /// measured by their stored size, without re-encoding.
+    fn children_size<S: Blockstore>(&self, store: &S) -> Result<usize, Error> {
+        let mut size = 0;
+        for p in &self.pointers {
+            match p {
+                Pointer::Link { cid, cache } => {
+                    if let Some(cached_node) = cache.get() {
+                        size += cached_node.approximate_size(store)?;
+                    } else {
+                        let bytes = if let Some(bytes) = store.get(cid)? {
+                            bytes
+                        } else {
+                            #[cfg(not(feature = "ignore-dead-links"))]
+                            return Err(Error::CidNotFound(cid.to_string()));
+
+                            #[cfg(feature = "ignore-dead-links")]
+                            continue;
+                        };
+                        let node: Box<Node<K, V, H>> = from_slice(&bytes)?;
+
+                        // Ignore error intentionally, the cache value will always be the same
+                        let cache_node = cache.get_or_init(|| node);
+                        size += bytes.len() + cache_node.children_size(store)?;
+                    }
+                }
+                Pointer::Dirty(n) => size += n.approximate_size(store)?,
+                Pointer::Values(_) => {}
+            }
+        }
+        Ok(size)
+    }
+
     /// Search for a key.
     fn search<Q: ?Sized, S: Blockstore>(
         &self,
@@ -187,7 +394,7 @@ where
         self.get_value(&mut HashBits::new(&hash), bit_width, 0, q, store)
     }
 
-    fn get_value<Q: ?Sized, S: Blockstore>(
+    pub(crate) fn get_value<Q: ?Sized, S: Blockstore>(
         &self,
         hashed_key: &mut HashBits,
         bit_width: u32,
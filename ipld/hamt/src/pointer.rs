@@ -5,6 +5,8 @@ use std::cmp::Ordering;
 use std::convert::{TryFrom, TryInto};
 
 use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
 use libipld_core::ipld::Ipld;
 use once_cell::unsync::OnceCell;
 use serde::de::{self, DeserializeOwned};
@@ -93,12 +95,69 @@ where
     }
 }
 
+/// Clones the pointer. Links are cloned without their cached node, which is reloaded from the store
+/// on demand.
+impl<K: Clone, V: Clone, H> Clone for Pointer<K, V, H> {
+    fn clone(&self) -> Self {
+        match self {
+            Pointer::Values(kvs) => Pointer::Values(kvs.clone()),
+            Pointer::Link { cid, .. } => Pointer::Link {
+                cid: *cid,
+                cache: Default::default(),
+            },
+            Pointer::Dirty(n) => Pointer::Dirty(n.clone()),
+        }
+    }
+}
+
 impl<K, V, H> Default for Pointer<K, V, H> {
     fn default() -> Self {
         Pointer::Values(Vec::new())
     }
 }
 
+/// What a pointer resolves to: either a bucket of values, or a (possibly loaded) node.
+pub(crate) enum Resolved<'a, K, V, H> {
+    Values(&'a [KeyValuePair<K, V>]),
+    Node(&'a Node<K, V, H>),
+}
+
+impl<K, V, H> Pointer<K, V, H>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    /// Resolves the pointer, loading and caching its node if it's a link. Returns `None` for dead
+    /// links when they're ignored.
+    pub(crate) fn resolve<S: Blockstore>(
+        &self,
+        store: &S,
+    ) -> Result<Option<Resolved<'_, K, V, H>>, Error> {
+        Ok(Some(match self {
+            Pointer::Values(kvs) => Resolved::Values(kvs),
+            Pointer::Dirty(n) => Resolved::Node(n),
+            Pointer::Link { cid, cache } => {
+                if let Some(cached_node) = cache.get() {
+                    Resolved::Node(cached_node)
+                } else {
+                    let node = if let Some(node) = store.get_cbor(cid)? {
+                        node
+                    } else {
+                        #[cfg(not(feature = "ignore-dead-links"))]
+                        return Err(Error::CidNotFound(cid.to_string()));
+
+                        #[cfg(feature = "ignore-dead-links")]
+                        return Ok(None);
+                    };
+
+                    // Ignore error intentionally, the cache value will always be the same
+                    Resolved::Node(cache.get_or_init(|| node))
+                }
+            }
+        }))
+    }
+}
+
 impl<K, V, H> Pointer<K, V, H>
 where
     K: Serialize + DeserializeOwned + Hash + PartialOrd,
@@ -0,0 +1,252 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::borrow::Borrow;
+
+use cid::Cid;
+use forest_hash_utils::BytesKey;
+use fvm_ipld_blockstore::Blockstore;
+use serde::de::{self, DeserializeOwned};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Batch, Error, Flushable, Hamt, Hash, HashAlgorithm, Sha256, DEFAULT_BIT_WIDTH};
+
+/// An entry in a [`SoftDeleteHamt`]: either a live value, or a tombstone recording that the key
+/// was deleted.
+///
+/// Live values are encoded as a single element array, and tombstones as an empty array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SoftDeleteEntry<V> {
+    Live(V),
+    Tombstone,
+}
+
+impl<V> SoftDeleteEntry<V> {
+    /// Returns the live value, or `None` for a tombstone.
+    pub fn live(&self) -> Option<&V> {
+        match self {
+            SoftDeleteEntry::Live(v) => Some(v),
+            SoftDeleteEntry::Tombstone => None,
+        }
+    }
+
+    fn into_live(self) -> Option<V> {
+        match self {
+            SoftDeleteEntry::Live(v) => Some(v),
+            SoftDeleteEntry::Tombstone => None,
+        }
+    }
+}
+
+impl<V: Serialize> Serialize for SoftDeleteEntry<V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            SoftDeleteEntry::Live(v) => {
+                let mut tuple = serializer.serialize_tuple(1)?;
+                tuple.serialize_element(v)?;
+                tuple.end()
+            }
+            SoftDeleteEntry::Tombstone => serializer.serialize_tuple(0)?.end(),
+        }
+    }
+}
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for SoftDeleteEntry<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut values: Vec<V> = Deserialize::deserialize(deserializer)?;
+        match values.len() {
+            0 => Ok(SoftDeleteEntry::Tombstone),
+            1 => Ok(SoftDeleteEntry::Live(values.remove(0))),
+            n => Err(de::Error::invalid_length(n, &"zero or one values")),
+        }
+    }
+}
+
+/// A [`Hamt`] where deletes leave a tombstone behind, recording that the key was removed.
+///
+/// Tombstoned keys read as absent and may be set again. Tombstones are kept until they're removed
+/// with [`purge_tombstones`](Self::purge_tombstones).
+///
+/// # Examples
+///
+/// ```
+/// use fvm_ipld_hamt::SoftDeleteHamt;
+///
+/// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+///
+/// let mut map: SoftDeleteHamt<_, _, usize> = SoftDeleteHamt::new(store);
+/// map.set(1, "a".to_string()).unwrap();
+/// assert_eq!(map.delete(&1).unwrap(), Some("a".to_string()));
+/// assert_eq!(map.get(&1).unwrap(), None);
+/// assert!(map.is_tombstoned(&1).unwrap());
+///
+/// assert_eq!(map.purge_tombstones().unwrap(), 1);
+/// assert!(!map.is_tombstoned(&1).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct SoftDeleteHamt<BS, V, K = BytesKey, H = Sha256> {
+    hamt: Hamt<BS, SoftDeleteEntry<V>, K, H>,
+}
+
+impl<BS, V, K, H> SoftDeleteHamt<BS, V, K, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+    H: HashAlgorithm,
+{
+    pub fn new(store: BS) -> Self {
+        Self::new_with_bit_width(store, DEFAULT_BIT_WIDTH)
+    }
+
+    /// Construct hamt with a bit width
+    pub fn new_with_bit_width(store: BS, bit_width: u32) -> Self {
+        Self {
+            hamt: Hamt::new_with_bit_width(store, bit_width),
+        }
+    }
+
+    /// Lazily instantiate a hamt from this root Cid.
+    pub fn load(cid: &Cid, store: BS) -> Result<Self, Error> {
+        Self::load_with_bit_width(cid, store, DEFAULT_BIT_WIDTH)
+    }
+
+    /// Lazily instantiate a hamt from this root Cid with a specified bit width.
+    pub fn load_with_bit_width(cid: &Cid, store: BS, bit_width: u32) -> Result<Self, Error> {
+        Ok(Self {
+            hamt: Hamt::load_with_bit_width(cid, store, bit_width)?,
+        })
+    }
+
+    /// Returns a reference to the underlying store of the Hamt.
+    pub fn store(&self) -> &BS {
+        self.hamt.store()
+    }
+
+    /// Inserts a key-value pair, replacing any tombstone for the key. Returns the previous live
+    /// value, if any.
+    pub fn set(&mut self, key: K, value: V) -> Result<Option<V>, Error>
+    where
+        V: PartialEq,
+    {
+        Ok(self
+            .hamt
+            .set(key, SoftDeleteEntry::Live(value))?
+            .and_then(SoftDeleteEntry::into_live))
+    }
+
+    /// Returns a reference to the live value corresponding to the key, or `None` if the key is
+    /// absent or tombstoned.
+    pub fn get<Q>(&self, k: &Q) -> Result<Option<&V>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.hamt.get(k)?.and_then(SoftDeleteEntry::live))
+    }
+
+    /// Returns `true` if a live value exists for the given key.
+    pub fn contains_key<Q>(&self, k: &Q) -> Result<bool, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.get(k)?.is_some())
+    }
+
+    /// Returns `true` if the key was deleted, and hasn't been set again or purged since.
+    pub fn is_tombstoned<Q>(&self, k: &Q) -> Result<bool, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(matches!(
+            self.hamt.get(k)?,
+            Some(SoftDeleteEntry::Tombstone)
+        ))
+    }
+
+    /// Replaces the live value at the key with a tombstone, returning the value. Nothing is
+    /// recorded for keys without a live value.
+    pub fn delete<Q>(&mut self, k: &Q) -> Result<Option<V>, Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: PartialEq,
+    {
+        // Removing the entry gives us back an owned key to store the tombstone under.
+        Ok(match self.hamt.delete(k)? {
+            Some((key, entry)) => {
+                self.hamt.set(key, SoftDeleteEntry::Tombstone)?;
+                entry.into_live()
+            }
+            None => None,
+        })
+    }
+
+    /// Removes all tombstones, returning the number removed.
+    pub fn purge_tombstones(&mut self) -> Result<usize, Error>
+    where
+        K: Clone,
+    {
+        let mut tombstoned = Vec::new();
+        self.hamt.for_each(|k, entry| {
+            if let SoftDeleteEntry::Tombstone = entry {
+                tombstoned.push(k.clone());
+            }
+            Ok(())
+        })?;
+        for k in &tombstoned {
+            self.hamt.delete(k)?;
+        }
+        Ok(tombstoned.len())
+    }
+
+    /// Flush root and return Cid for hamt
+    pub fn flush(&mut self) -> Result<Cid, Error> {
+        self.hamt.flush()
+    }
+
+    /// Iterates over each live KV in the Hamt, skipping tombstones.
+    pub fn for_each<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&K, &V) -> anyhow::Result<()>,
+    {
+        self.hamt.for_each(|k, entry| match entry {
+            SoftDeleteEntry::Live(v) => f(k, v),
+            SoftDeleteEntry::Tombstone => Ok(()),
+        })
+    }
+
+    /// Iterates over each entry in the Hamt, including tombstones.
+    pub fn for_each_entry<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnMut(&K, &SoftDeleteEntry<V>) -> anyhow::Result<()>,
+    {
+        self.hamt.for_each(f)
+    }
+
+    /// Consumes this HAMT and returns the Blockstore it owns.
+    pub fn into_store(self) -> BS {
+        self.hamt.into_store()
+    }
+}
+
+impl<BS, V, K, H> Flushable for SoftDeleteHamt<BS, V, K, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+    H: HashAlgorithm,
+{
+    fn flush_batched(&mut self, batch: &Batch) -> Result<Cid, Error> {
+        self.hamt.flush_batched(batch)
+    }
+}
@@ -1,14 +1,22 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::sync::Mutex;
 
+use async_trait::async_trait;
+use cid::Cid;
 use fvm_ipld_blockstore::tracking::{BSStats, TrackingBlockstore};
-use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_blockstore::{AsyncBlockstore, Blockstore, BlockstoreBridge, MemoryBlockstore};
 use fvm_ipld_encoding::CborStore;
 #[cfg(feature = "identity")]
 use fvm_ipld_hamt::Identity;
-use fvm_ipld_hamt::{BytesKey, Hamt};
+use fvm_ipld_hamt::{
+    flush_all, BytesKey, Change, Checkpoint, Error, Flushable, Hamt, HashAlgorithm,
+    InsertionOrderHamt, Sha256, SoftDeleteEntry, SoftDeleteHamt,
+};
+use libipld_core::ipld::Ipld;
 use multihash::Code;
 use serde_bytes::ByteBuf;
 
@@ -478,6 +486,692 @@ fn clean_child_ordering() {
     assert_eq!(*store.stats.borrow(), BSStats {r: 3, w: 11, br: 1449, bw: 1751});
 }
 
+#[test]
+fn approximate_size_bytes() {
+    let mem = MemoryBlockstore::default();
+    let store = TrackingBlockstore::new(&mem);
+
+    let mut hamt: Hamt<_, _> = Hamt::new_with_bit_width(&store, 5);
+    assert_eq!(hamt.approximate_size_bytes().unwrap(), 3);
+
+    for i in 0..200 {
+        hamt.set(tstring(i), tstring("filler")).unwrap();
+    }
+
+    let estimate = hamt.approximate_size_bytes().unwrap();
+    assert_eq!(store.stats.borrow().w, 0, "estimating should not write");
+
+    let c = hamt.flush().unwrap();
+    let actual = store.stats.borrow().bw;
+    assert!(
+        estimate.abs_diff(actual) <= actual / 20,
+        "estimate {} not within 5% of flushed size {}",
+        estimate,
+        actual
+    );
+
+    // Clean, unloaded subtrees are measured by their stored size.
+    let reloaded: Hamt<_, BytesKey> = Hamt::load_with_bit_width(&c, &store, 5).unwrap();
+    assert_eq!(reloaded.approximate_size_bytes().unwrap(), actual);
+
+    // Modifications are reflected in the estimate.
+    let mut hamt: Hamt<_, BytesKey> = Hamt::load_with_bit_width(&c, &store, 5).unwrap();
+    for i in 0..50 {
+        hamt.delete(&tstring(i)).unwrap();
+    }
+    let estimate = hamt.approximate_size_bytes().unwrap();
+    assert!(estimate < actual);
+}
+
+#[test]
+fn from_sorted() {
+    let store = MemoryBlockstore::default();
+
+    for bit_width in [3, 5, 8] {
+        let mut expected: Hamt<_, _> = Hamt::new_with_bit_width(&store, bit_width);
+        let mut entries = Vec::new();
+        for i in 0..500 {
+            expected.set(tstring(i), i).unwrap();
+            entries.push((tstring(i), i));
+        }
+        // Duplicate keys are overwritten, as with `set`.
+        expected.set(tstring(7), 1000).unwrap();
+        entries.push((tstring(7), 1000));
+
+        // Stable sort, so the duplicate stays after the original.
+        entries.sort_by_key(|(k, _)| Sha256::hash(k));
+        let mut hamt: Hamt<_, _> =
+            Hamt::from_sorted_with_bit_width(&store, entries, bit_width).unwrap();
+        assert_eq!(hamt.get(&tstring(7)).unwrap(), Some(&1000));
+        assert_eq!(hamt.flush().unwrap(), expected.flush().unwrap());
+    }
+
+    let mut empty: Hamt<_, u8> = Hamt::from_sorted(&store, Vec::new()).unwrap();
+    assert_eq!(
+        empty.flush().unwrap(),
+        Hamt::<_, u8>::new(&store).flush().unwrap()
+    );
+
+    let mut unsorted: Vec<_> = (0..10).map(|i| (tstring(i), i)).collect();
+    unsorted.sort_by_key(|(k, _)| std::cmp::Reverse(Sha256::hash(k)));
+    assert!(matches!(
+        Hamt::<_, _>::from_sorted(&store, unsorted),
+        Err(Error::Unsorted)
+    ));
+}
+
+#[test]
+fn soft_delete() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: SoftDeleteHamt<_, _> = SoftDeleteHamt::new_with_bit_width(&store, 5);
+    for i in 0..10 {
+        hamt.set(tstring(i), i).unwrap();
+    }
+
+    // tombstoned keys read as absent
+    assert_eq!(hamt.delete(&tstring(3)).unwrap(), Some(3));
+    assert_eq!(hamt.delete(&tstring(3)).unwrap(), None);
+    assert_eq!(hamt.delete(&tstring(7)).unwrap(), Some(7));
+    assert_eq!(hamt.delete(&tstring(100)).unwrap(), None);
+    assert_eq!(hamt.get(&tstring(3)).unwrap(), None);
+    assert!(!hamt.contains_key(&tstring(3)).unwrap());
+    assert!(hamt.is_tombstoned(&tstring(3)).unwrap());
+    assert!(!hamt.is_tombstoned(&tstring(100)).unwrap());
+
+    let mut live = Vec::new();
+    hamt.for_each(|_, v| {
+        live.push(*v);
+        Ok(())
+    })
+    .unwrap();
+    live.sort_unstable();
+    assert_eq!(live, vec![0, 1, 2, 4, 5, 6, 8, 9]);
+
+    let mut tombstones = 0;
+    hamt.for_each_entry(|_, entry| {
+        if let SoftDeleteEntry::Tombstone = entry {
+            tombstones += 1;
+        }
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(tombstones, 2);
+
+    // tombstones survive a round trip through the store
+    let c = hamt.flush().unwrap();
+    let mut hamt: SoftDeleteHamt<_, i32> =
+        SoftDeleteHamt::load_with_bit_width(&c, &store, 5).unwrap();
+    assert!(hamt.is_tombstoned(&tstring(7)).unwrap());
+
+    // tombstoned keys can be re-set
+    assert_eq!(hamt.set(tstring(7), 70).unwrap(), None);
+    assert_eq!(hamt.get(&tstring(7)).unwrap(), Some(&70));
+    assert!(!hamt.is_tombstoned(&tstring(7)).unwrap());
+
+    // purging removes the remaining tombstone, leaving the same map as a hard delete
+    assert_eq!(hamt.purge_tombstones().unwrap(), 1);
+    assert_eq!(hamt.purge_tombstones().unwrap(), 0);
+    assert!(!hamt.is_tombstoned(&tstring(3)).unwrap());
+
+    let mut expected: SoftDeleteHamt<_, _> = SoftDeleteHamt::new_with_bit_width(&store, 5);
+    for i in (0..10).filter(|&i| i != 3) {
+        expected
+            .set(tstring(i), if i == 7 { 70 } else { i })
+            .unwrap();
+    }
+    assert_eq!(hamt.flush().unwrap(), expected.flush().unwrap());
+}
+
+#[test]
+fn split_at_prefix() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, _> = Hamt::new(&store);
+    for i in 0..1000 {
+        hamt.set(tstring(i), i).unwrap();
+    }
+    // shards share flushed (linked) nodes as well as unflushed ones
+    hamt.flush().unwrap();
+    for i in 1000..1100 {
+        hamt.set(tstring(i), i).unwrap();
+    }
+
+    for count in [1, 3, 256] {
+        let shards = hamt.split_at_prefix(count).unwrap();
+        assert_eq!(shards.len(), count);
+
+        let mut seen = Vec::new();
+        let mut last_prefix = 0;
+        for mut shard in shards {
+            let mut expected: Hamt<_, _> = Hamt::new(&store);
+            let mut prefixes = Vec::new();
+            shard
+                .for_each(|k, v| {
+                    seen.push(*v);
+                    prefixes.push(Sha256::hash(k)[0]);
+                    expected.set(k.clone(), *v)?;
+                    Ok(())
+                })
+                .unwrap();
+
+            // shards cover contiguous, increasing prefix ranges
+            if let (Some(min), Some(max)) = (prefixes.iter().min(), prefixes.iter().max()) {
+                assert!(*min as usize >= last_prefix);
+                last_prefix = *max as usize + 1;
+            }
+            assert_eq!(shard.flush().unwrap(), expected.flush().unwrap());
+        }
+
+        // together, the shards hold every entry exactly once
+        seen.sort_unstable();
+        assert_eq!(seen, (0..1100).collect::<Vec<_>>());
+    }
+
+    assert!(matches!(
+        hamt.split_at_prefix(0),
+        Err(Error::InvalidShardCount(0))
+    ));
+    assert!(matches!(
+        hamt.split_at_prefix(257),
+        Err(Error::InvalidShardCount(257))
+    ));
+}
+
+#[test]
+fn for_each_checkpointed() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, _> = Hamt::new(&store);
+    for i in 0..1000 {
+        hamt.set(tstring(i), i).unwrap();
+    }
+    let root = hamt.flush().unwrap();
+
+    let mut visited = Vec::new();
+    let mut checkpoints = Vec::new();
+    hamt.for_each_checkpointed(|_, v, checkpoint| {
+        visited.push(*v);
+        checkpoints.push(fvm_ipld_encoding::to_vec(checkpoint)?);
+        Ok(())
+    })
+    .unwrap();
+    // the same entries are visited, in the same order, as by for_each
+    let mut expected = Vec::new();
+    hamt.for_each(|_, v| {
+        expected.push(*v);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(visited, expected);
+
+    // resuming from a persisted checkpoint (on a freshly loaded HAMT) visits exactly the entries
+    // after it
+    for mid in [0, 437, 999] {
+        let checkpoint: Checkpoint = fvm_ipld_encoding::from_slice(&checkpoints[mid]).unwrap();
+        let hamt: Hamt<_, usize> = Hamt::load(&root, &store).unwrap();
+        let mut remaining = Vec::new();
+        hamt.resume_checkpointed(&checkpoint, |_, v, _| {
+            remaining.push(*v);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(remaining, visited[mid + 1..]);
+    }
+}
+
+#[test]
+fn join() {
+    let store = MemoryBlockstore::default();
+    let mut left: Hamt<_, _> = Hamt::new(&store);
+    for i in 0..1000 {
+        left.set(tstring(i), i).unwrap();
+    }
+    // one side has flushed (linked) nodes, and the other only unflushed ones
+    left.flush().unwrap();
+    let mut right: Hamt<_, _> = Hamt::new(&store);
+    for i in 500..1500 {
+        right.set(tstring(i), i.to_string()).unwrap();
+    }
+
+    let mut joined: Vec<_> = left
+        .join(&right)
+        .map(|res| res.map(|(k, v, v2)| (k.clone(), *v, v2.clone())))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    joined.sort_unstable_by_key(|(_, v, _)| *v);
+
+    // only the common keys are yielded, each with its values in both HAMTs
+    let expected: Vec<_> = (500..1000)
+        .map(|i| (tstring(i), i, i.to_string()))
+        .collect();
+    assert_eq!(joined, expected);
+
+    // HAMTs with no keys in common yield nothing
+    let empty: Hamt<_, String> = Hamt::new(&store);
+    assert_eq!(left.join(&empty).count(), 0);
+
+    // HAMTs with different bit widths can't be joined
+    let narrow: Hamt<_, String> = Hamt::new_with_bit_width(&store, 5);
+    let mut mismatched = left.join(&narrow);
+    assert!(matches!(
+        mismatched.next(),
+        Some(Err(Error::BitWidthMismatch(8, 5)))
+    ));
+    assert!(mismatched.next().is_none());
+}
+
+#[test]
+fn iter() {
+    let mem = MemoryBlockstore::default();
+    let store = TrackingBlockstore::new(&mem);
+    let mut hamt: Hamt<_, _> = Hamt::new(&store);
+    for i in 0..1000 {
+        hamt.set(tstring(i), i).unwrap();
+    }
+    let root = hamt.flush().unwrap();
+
+    // entries are yielded in the same order as for_each visits them
+    let mut expected = Vec::new();
+    hamt.for_each(|_, v| {
+        expected.push(*v);
+        Ok(())
+    })
+    .unwrap();
+    let visited: Vec<_> = hamt.iter().map(|res| *res.unwrap().1).collect();
+    assert_eq!(visited, expected);
+
+    // stopping early doesn't load the rest of the HAMT
+    let hamt: Hamt<_, usize> = Hamt::load(&root, &store).unwrap();
+    let before = store.stats.borrow().r;
+    assert_eq!(hamt.iter().take(5).count(), 5);
+    let partial = store.stats.borrow().r - before;
+    assert_eq!(hamt.iter().count(), 1000);
+    let full = store.stats.borrow().r - before;
+    assert!(partial < full, "{} !< {}", partial, full);
+
+    // a corrupted node yields a single error, after which iteration stops
+    let root_node: Ipld = mem.get_cbor(&root).unwrap().unwrap();
+    let child = match &root_node {
+        Ipld::List(fields) => match &fields[1] {
+            Ipld::List(pointers) => pointers
+                .iter()
+                .find_map(|p| match p {
+                    Ipld::Link(cid) => Some(*cid),
+                    _ => None,
+                })
+                .unwrap(),
+            _ => panic!("unexpected pointers: {:?}", fields[1]),
+        },
+        _ => panic!("unexpected root: {:?}", root_node),
+    };
+    mem.put_keyed(&child, &[0x80]).unwrap();
+    let hamt: Hamt<_, usize> = Hamt::load(&root, &mem).unwrap();
+    let mut iter = hamt.iter();
+    assert!(iter.by_ref().any(|res| res.is_err()));
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn diff() {
+    let store = MemoryBlockstore::default();
+    let empty: Hamt<_, usize> = Hamt::new(&store);
+    let mut populated: Hamt<_, _> = Hamt::new(&store);
+    for i in 0..100 {
+        populated.set(tstring(i), i).unwrap();
+    }
+
+    // everything is added to, or removed from, an empty HAMT
+    let sorted = |mut changes: Vec<Change<BytesKey, usize>>| {
+        changes.sort_unstable_by_key(|change| match change {
+            Change::Add(_, v) | Change::Remove(_, v) | Change::Modify(_, v, _) => *v,
+        });
+        changes
+    };
+    assert_eq!(
+        sorted(empty.diff(&populated).unwrap()),
+        (0..100)
+            .map(|i| Change::Add(tstring(i), i))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        sorted(populated.diff(&empty).unwrap()),
+        (0..100)
+            .map(|i| Change::Remove(tstring(i), i))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(populated.diff(&populated).unwrap(), vec![]);
+
+    // HAMTs with different bit widths can't be compared
+    let narrow: Hamt<_, usize> = Hamt::new_with_bit_width(&store, 5);
+    assert!(matches!(
+        populated.diff(&narrow),
+        Err(Error::BitWidthMismatch(8, 5))
+    ));
+}
+
+#[test]
+fn diff_prunes_shared_subtrees() {
+    let mem = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, _> = Hamt::new_with_bit_width(&mem, 5);
+    for i in 0..10_000 {
+        hamt.set(tstring(i), i).unwrap();
+    }
+    let before = hamt.flush().unwrap();
+    hamt.set(tstring(1234), 0).unwrap();
+    hamt.delete(&tstring(42)).unwrap();
+    hamt.set(tstring(10_000), 10_000).unwrap();
+    let after = hamt.flush().unwrap();
+
+    let store = TrackingBlockstore::new(&mem);
+    let load = |root| Hamt::<_, usize>::load_with_bit_width(root, &store, 5).unwrap();
+    let mut changes = load(&before).diff(&load(&after)).unwrap();
+    changes.sort_unstable_by_key(|change| format!("{:?}", change));
+    assert_eq!(
+        changes,
+        vec![
+            Change::Add(tstring(10_000), 10_000),
+            Change::Modify(tstring(1234), 1234, 0),
+            Change::Remove(tstring(42), 42),
+        ]
+    );
+
+    // only the nodes on the paths to the changed keys are loaded
+    let diff_reads = store.stats.borrow().r;
+    load(&before).for_each(|_, _| Ok(())).unwrap();
+    let full_reads = store.stats.borrow().r - diff_reads;
+    assert!(
+        diff_reads * 10 < full_reads,
+        "{} reads to diff, {} to traverse",
+        diff_reads,
+        full_reads
+    );
+}
+
+#[test]
+fn insertion_order() {
+    let mem = MemoryBlockstore::default();
+    let store = TrackingBlockstore::new(&mem);
+    let mut hamt: InsertionOrderHamt<_, _> = InsertionOrderHamt::new(&store);
+    // insert keys in an order unrelated to their hashes or values
+    let order: Vec<usize> = (0..200).map(|i| (i * 37) % 200).collect();
+    for i in &order {
+        hamt.set(tstring(i), *i).unwrap();
+    }
+    let visited = |hamt: &InsertionOrderHamt<_, usize>| {
+        let mut visited = Vec::new();
+        hamt.for_each_insertion_order(|_, v| {
+            visited.push(*v);
+            Ok(())
+        })
+        .unwrap();
+        visited
+    };
+    assert_eq!(visited(&hamt), order);
+
+    // setting an existing key keeps its position, while a deleted key moves to the end when set
+    // again, including after reloading
+    hamt.set(tstring(order[0]), order[0]).unwrap();
+    assert_eq!(
+        hamt.delete(&tstring(order[1])).unwrap().unwrap().1,
+        order[1]
+    );
+    let root = hamt.flush().unwrap();
+    let mut hamt: InsertionOrderHamt<_, _> = InsertionOrderHamt::load(&root, &store).unwrap();
+    let reads = store.stats.borrow().r;
+    hamt.set(tstring(order[1]), order[1]).unwrap();
+    let set_reads = store.stats.borrow().r - reads;
+
+    // the next sequence number is stored with the root, so setting a key doesn't scan the HAMT
+    let reads = store.stats.borrow().r;
+    InsertionOrderHamt::<_, usize>::load(&root, &store)
+        .unwrap()
+        .for_each(|_, _| Ok(()))
+        .unwrap();
+    let full_reads = store.stats.borrow().r - reads;
+    assert!(
+        set_reads < full_reads,
+        "{} reads to set, {} to traverse",
+        set_reads,
+        full_reads
+    );
+
+    let mut expected = order.clone();
+    expected.remove(1);
+    expected.push(order[1]);
+    assert_eq!(visited(&hamt), expected);
+    assert_eq!(hamt.get(&tstring(order[1])).unwrap(), Some(&order[1]));
+}
+
+#[test]
+fn flush_all_batched() {
+    // builds three HAMTs of different sizes, with a value type too large to be inlined in a leaf
+    let build = |store| -> Vec<Hamt<_, String>> {
+        (1..=3)
+            .map(|n| {
+                let mut hamt = Hamt::new_with_bit_width(store, 5);
+                for i in 0..n * 50 {
+                    hamt.set(tstring(i), format!("{}-{}", n, i)).unwrap();
+                }
+                hamt
+            })
+            .collect()
+    };
+
+    let separate = MemoryBlockstore::default();
+    let expected: Vec<_> = build(&separate)
+        .iter_mut()
+        .map(|hamt| hamt.flush().unwrap())
+        .collect();
+
+    let store = MemoryBlockstore::default();
+    let mut hamts = build(&store);
+    let mut flushables: Vec<&mut dyn Flushable> = hamts
+        .iter_mut()
+        .map(|hamt| hamt as &mut dyn Flushable)
+        .collect();
+    let roots = flush_all(&store, &mut flushables).unwrap();
+    assert_eq!(roots, expected);
+
+    // all the nodes were written, and the HAMTs are clean
+    for (n, root) in (1..=3).zip(&roots) {
+        let hamt: Hamt<_, String> = Hamt::load_with_bit_width(root, &store, 5).unwrap();
+        for i in 0..n * 50 {
+            assert_eq!(
+                hamt.get(&tstring(i)).unwrap(),
+                Some(&format!("{}-{}", n, i))
+            );
+        }
+    }
+    for (hamt, root) in hamts.iter_mut().zip(&roots) {
+        assert_eq!(hamt.flush().unwrap(), *root);
+    }
+}
+
+#[test]
+fn set_many_and_delete_many() {
+    let store = MemoryBlockstore::default();
+
+    // keys in a pseudo-random order, with some duplicates
+    let mut random = Vec::new();
+    let mut x: u64 = 1;
+    for i in 0..1000 {
+        x = x
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        random.push((tstring(x % 800), i));
+    }
+    // keys sharing the first byte of their hashes, so they're clustered in the same subtree
+    let clustered: Vec<_> = (0..20_000)
+        .map(tstring)
+        .filter(|k| Sha256::hash(k)[0] == 0)
+        .enumerate()
+        .map(|(i, k)| (k, i))
+        .collect();
+    assert!(clustered.len() > 50);
+
+    for entries in [random, clustered] {
+        for bit_width in [3, 5, 8] {
+            let mut expected: Hamt<_, _> = Hamt::new_with_bit_width(&store, bit_width);
+            for (k, v) in entries.iter() {
+                expected.set(k.clone(), *v).unwrap();
+            }
+            let mut hamt: Hamt<_, _> = Hamt::new_with_bit_width(&store, bit_width);
+            hamt.set_many(entries.iter().cloned()).unwrap();
+            assert_eq!(hamt.flush().unwrap(), expected.flush().unwrap());
+
+            // deleting half of the keys
+            let deleted: Vec<_> = entries.iter().step_by(2).map(|(k, _)| k).collect();
+            for k in deleted.iter() {
+                expected.delete(*k).unwrap();
+            }
+            hamt.delete_many(deleted).unwrap();
+            assert_eq!(hamt.flush().unwrap(), expected.flush().unwrap());
+        }
+    }
+
+    // deleting absent keys is a no-op
+    let mut hamt: Hamt<_, _> = Hamt::new(&store);
+    hamt.set_many((0..100).map(|i| (tstring(i), i))).unwrap();
+    let root = hamt.flush().unwrap();
+    let absent: Vec<_> = (100..200).map(tstring).collect();
+    hamt.delete_many(&absent).unwrap();
+    assert_eq!(hamt.flush().unwrap(), root);
+
+    let mut empty: Hamt<_, usize> = Hamt::new(&store);
+    empty.delete_many(&absent).unwrap();
+    assert!(empty.is_empty());
+}
+
+/// An async blockstore, which yields to the runtime before each operation.
+#[derive(Default)]
+struct AsyncMemoryBlockstore(Mutex<HashMap<Cid, Vec<u8>>>);
+
+#[async_trait]
+impl AsyncBlockstore for AsyncMemoryBlockstore {
+    async fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        tokio::task::yield_now().await;
+        Ok(self.0.lock().unwrap().get(k).cloned())
+    }
+
+    async fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        tokio::task::yield_now().await;
+        self.0.lock().unwrap().insert(*k, block.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn blockstore_bridge() {
+    /// Builds a HAMT, then reloads and modifies it, returning the final root.
+    fn exercise(store: &impl Blockstore) -> Cid {
+        let mut hamt: Hamt<_, u64, u64> = Hamt::new_with_bit_width(store, 3);
+        for i in 0..200 {
+            hamt.set(i, i * 2).unwrap();
+        }
+        let root = hamt.flush().unwrap();
+
+        let mut hamt: Hamt<_, u64, u64> = Hamt::load_with_bit_width(&root, store, 3).unwrap();
+        for i in 0..200 {
+            assert_eq!(hamt.get(&i).unwrap(), Some(&(i * 2)));
+        }
+        for i in 0..100 {
+            hamt.delete(&i).unwrap();
+        }
+        hamt.flush().unwrap()
+    }
+
+    let expected = exercise(&MemoryBlockstore::default());
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+
+    // from a thread outside the runtime
+    let bridge = BlockstoreBridge::new(AsyncMemoryBlockstore::default(), runtime.handle().clone());
+    assert_eq!(exercise(&bridge), expected);
+
+    // from a task on the runtime itself, which must not stall the runtime while blocking
+    let nested = BlockstoreBridge::new(AsyncMemoryBlockstore::default(), runtime.handle().clone());
+    let root = runtime
+        .block_on(runtime.spawn(async move { exercise(&nested) }))
+        .unwrap();
+    assert_eq!(root, expected);
+
+    // blocking would deadlock a current-thread runtime, so it fails instead
+    let current = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    current.block_on(async {
+        bridge.get(&expected).unwrap_err();
+    });
+}
+
+#[test]
+fn count_matching() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, u64, u64> = Hamt::new_with_bit_width(&store, 3);
+    for i in 0..500 {
+        hamt.set(i, i * 7).unwrap();
+    }
+    let mut expected = 0;
+    hamt.for_each(|_, v| {
+        if v % 2 == 0 {
+            expected += 1;
+        }
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(expected, 250);
+    assert_eq!(hamt.count_matching(|_, v| v % 2 == 0).unwrap(), expected);
+
+    // entries in unflushed and reloaded HAMTs are counted alike
+    let root = hamt.flush().unwrap();
+    let reloaded: Hamt<_, u64, u64> = Hamt::load_with_bit_width(&root, &store, 3).unwrap();
+    assert_eq!(
+        reloaded.count_matching(|_, v| v % 2 == 0).unwrap(),
+        expected
+    );
+    assert_eq!(reloaded.count_matching(|k, _| *k < 10).unwrap(), 10);
+}
+
+#[test]
+fn verify_root_shallow() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, u64, u64> = Hamt::new_with_bit_width(&store, 3);
+    for i in 0..200 {
+        hamt.set(i, i).unwrap();
+    }
+    let root = hamt.flush().unwrap();
+    Hamt::<MemoryBlockstore, u64, u64>::verify_root_shallow(&root, &store).unwrap();
+
+    // copy just the root block into another store, so all of its children are missing
+    let partial = MemoryBlockstore::default();
+    partial
+        .put_keyed(&root, &store.get(&root).unwrap().unwrap())
+        .unwrap();
+    assert!(matches!(
+        Hamt::<MemoryBlockstore, u64, u64>::verify_root_shallow(&root, &partial),
+        Err(Error::CidNotFound(_))
+    ));
+
+    // a missing root is reported too
+    let empty = MemoryBlockstore::default();
+    assert!(matches!(
+        Hamt::<MemoryBlockstore, u64, u64>::verify_root_shallow(&root, &empty),
+        Err(Error::CidNotFound(_))
+    ));
+
+    // as is a root whose bitfield doesn't match its pointers
+    let malformed = store
+        .put_cbor(
+            &(ByteBuf::from(vec![0b111]), Vec::<Ipld>::new()),
+            Code::Blake2b256,
+        )
+        .unwrap();
+    assert!(matches!(
+        Hamt::<MemoryBlockstore, u64, u64>::verify_root_shallow(&malformed, &store),
+        Err(Error::MalformedNode(_))
+    ));
+}
+
 fn tstring(v: impl Display) -> BytesKey {
     BytesKey(v.to_string().into_bytes())
 }
@@ -7,7 +7,7 @@ use fvm_shared::error::ErrorNumber;
 use fvm_shared::{actor, ActorID, MAX_CID_LEN};
 use num_traits::FromPrimitive;
 
-use crate::{sys, SyscallResult, MAX_ACTOR_ADDR_LEN};
+use crate::{status_code_to_bool, sys, SyscallResult, MAX_ACTOR_ADDR_LEN};
 
 /// Resolves the ID address of an actor. Returns `None` if the address cannot be resolved.
 /// Successfully resolving an address doesn't necessarily mean the actor exists (e.g., if the
@@ -43,6 +43,19 @@ pub fn get_actor_code_cid(addr: &Address) -> Option<Cid> {
     }
 }
 
+/// Determines whether the actor at the address is an account actor.
+pub fn is_account(addr: &Address) -> SyscallResult<bool> {
+    let bytes = addr.to_bytes();
+    unsafe { sys::actor::is_account(bytes.as_ptr(), bytes.len() as u32).map(status_code_to_bool) }
+}
+
+/// Looks up the nonce of the actor at the address. Returns 0 if the actor doesn't exist or isn't an
+/// account actor.
+pub fn nonce_of(addr: &Address) -> SyscallResult<u64> {
+    let bytes = addr.to_bytes();
+    unsafe { sys::actor::nonce_of(bytes.as_ptr(), bytes.len() as u32) }
+}
+
 /// Generates a new actor address for an actor deployed
 /// by the calling actor.
 pub fn new_actor_address() -> Address {
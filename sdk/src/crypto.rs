@@ -1,8 +1,8 @@
 use cid::Cid;
 use fvm_ipld_encoding::{to_vec, Cbor};
-use fvm_shared::address::Address;
+use fvm_shared::address::{Address, BLS_PUB_LEN};
 use fvm_shared::consensus::ConsensusFault;
-use fvm_shared::crypto::signature::Signature;
+use fvm_shared::crypto::signature::{Signature, SignatureType};
 use fvm_shared::piece::PieceInfo;
 use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
@@ -13,6 +13,9 @@ use num_traits::FromPrimitive;
 
 use crate::{status_code_to_bool, sys, SyscallResult};
 
+/// The maximum length of a key address: a protocol byte followed by a BLS public key.
+const MAX_KEY_ADDRESS_LEN: usize = BLS_PUB_LEN + 1;
+
 /// Verifies that a signature is valid for an address and plaintext.
 pub fn verify_signature(
     signature: &Signature,
@@ -36,6 +39,21 @@ pub fn verify_signature(
     }
 }
 
+/// Computes the key address (f1 for secp256k1, f3 for BLS) of a public key.
+pub fn pubkey_to_address(sig_type: SignatureType, pubkey: &[u8]) -> SyscallResult<Address> {
+    let mut out = [0u8; MAX_KEY_ADDRESS_LEN];
+    unsafe {
+        let len = sys::crypto::pubkey_to_address(
+            sig_type as u32,
+            pubkey.as_ptr(),
+            pubkey.len() as u32,
+            out.as_mut_ptr(),
+            MAX_KEY_ADDRESS_LEN as u32,
+        )?;
+        Ok(Address::from_bytes(&out[..len as usize]).expect("syscall returned invalid address"))
+    }
+}
+
 /// Hashes input data using blake2b with 256 bit output.
 pub fn hash_blake2b(data: &[u8]) -> [u8; 32] {
     const BLAKE2B_256: u64 = 0xb220;
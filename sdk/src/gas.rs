@@ -6,3 +6,16 @@ pub fn charge(name: &str, compute: u64) {
         // can only happen if name isn't utf8, memory corruption, etc.
         .expect("failed to charge gas")
 }
+
+/// Returns the gas the current invocation was granted. Combined with [`available`], this gives the
+/// gas used by the invocation so far.
+pub fn limit() -> u64 {
+    let milligas = unsafe { sys::gas::limit() }.expect("failed to get gas limit");
+    milligas / 1000
+}
+
+/// Returns the gas available to the current invocation.
+pub fn available() -> u64 {
+    let milligas = unsafe { sys::gas::available() }.expect("failed to get gas available");
+    milligas / 1000
+}
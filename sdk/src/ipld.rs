@@ -1,7 +1,8 @@
 use cid::Cid;
+use fvm_ipld_encoding::{to_vec, PathSegment};
 use fvm_shared::MAX_CID_LEN;
 
-use crate::{sys, SyscallResult};
+use crate::{status_code_to_bool, sys, SyscallResult};
 
 /// The unit/void object.
 pub const UNIT: u32 = sys::ipld::UNIT;
@@ -73,6 +74,38 @@ pub fn get_block(id: fvm_shared::sys::BlockId, size_hint: Option<u32>) -> Syscal
     Ok(buf)
 }
 
+/// Creates a new block holding the value at `path` within the DAG-CBOR block referenced by
+/// BlockId, returning the new block's BlockId.
+pub fn get_block_field(
+    id: fvm_shared::sys::BlockId,
+    path: &[PathSegment],
+) -> SyscallResult<fvm_shared::sys::BlockId> {
+    let path = to_vec(path).expect("failed to serialize path");
+    unsafe { sys::ipld::block_read_field(id, path.as_ptr(), path.len() as u32) }
+}
+
+/// Returns whether `data` is the content addressed by `cid`, i.e. whether it hashes to the CID's
+/// digest with the CID's hash function. The CID's codec isn't checked, and CIDs with truncated
+/// digests never match.
+pub fn verify_cid(cid: &Cid, data: &[u8]) -> SyscallResult<bool> {
+    let cid = cid.to_bytes();
+    unsafe {
+        sys::ipld::verify_cid(
+            cid.as_ptr(),
+            cid.len() as u32,
+            data.as_ptr(),
+            data.len() as u32,
+        )
+        .map(status_code_to_bool)
+    }
+}
+
+/// Returns the size of the block [`put_block`] would write from the supplied data, without writing
+/// it. Fails if the codec isn't supported.
+pub fn block_measure(codec: fvm_shared::sys::Codec, data: &[u8]) -> SyscallResult<u32> {
+    unsafe { sys::ipld::block_measure(codec, data.as_ptr(), data.len() as u32) }
+}
+
 /// Writes the supplied block and returns the BlockId.
 pub fn put_block(
     codec: fvm_shared::sys::Codec,
@@ -35,6 +35,17 @@ pub fn value_received() -> TokenAmount {
         .expect("invalid bigint")
 }
 
+/// Returns the nonce of the top-level message that started this call stack.
+pub fn origin_nonce() -> u64 {
+    unsafe { sys::vm::origin_nonce() }.expect("failed to get origin nonce")
+}
+
+/// Returns the codec the caller expects the return value in, as set in its
+/// [`SendOptions`](crate::send::SendOptions). `DAG_CBOR` by default.
+pub fn expected_return_codec() -> Codec {
+    unsafe { sys::vm::expected_return_codec() }.expect("failed to get expected return codec")
+}
+
 /// Returns the message codec and parameters.
 pub fn params_raw(id: BlockId) -> SyscallResult<(Codec, Vec<u8>)> {
     if id == NO_DATA_BLOCK_ID {
@@ -45,3 +56,15 @@ pub fn params_raw(id: BlockId) -> SyscallResult<(Codec, Vec<u8>)> {
         Ok((codec, crate::ipld::get_block(id, Some(size))?))
     }
 }
+
+/// Copies the raw message parameters into `buf` without opening a block, returning their total
+/// size. If the parameters are larger than `buf`, only the first `buf.len()` bytes are copied.
+pub fn read_params(buf: &mut [u8]) -> SyscallResult<u32> {
+    unsafe { sys::vm::read_params(buf.as_mut_ptr(), buf.len() as u32) }
+}
+
+/// Returns the codec of the message parameters without opening a block, or zero if there are no
+/// parameters.
+pub fn params_codec() -> Codec {
+    unsafe { sys::vm::params_codec() }.expect("failed to get params codec")
+}
@@ -1,11 +1,11 @@
 use std::convert::TryInto;
 
 use fvm_shared::clock::ChainEpoch;
-use fvm_shared::econ::TokenAmount;
+use fvm_shared::econ::{CircSupplyDetail, TokenAmount};
 use fvm_shared::version::NetworkVersion;
 
-use crate::sys;
 use crate::vm::INVOCATION_CONTEXT;
+use crate::{sys, SyscallResult};
 
 pub fn curr_epoch() -> ChainEpoch {
     INVOCATION_CONTEXT.network_curr_epoch
@@ -33,3 +33,14 @@ pub fn total_fil_circ_supply() -> TokenAmount {
             .into()
     }
 }
+
+/// Returns the components of the circulating supply, if the client provided them.
+pub fn circ_supply_detail() -> SyscallResult<CircSupplyDetail> {
+    let detail = unsafe { sys::network::circ_supply_detail()? };
+    Ok(CircSupplyDetail {
+        mined: detail.mined.into(),
+        vested: detail.vested.into(),
+        burnt: detail.burnt.into(),
+        locked: detail.locked.into(),
+    })
+}
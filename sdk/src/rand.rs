@@ -32,3 +32,12 @@ pub fn get_beacon_randomness(
     };
     Ok(Randomness(ret.to_vec()))
 }
+
+/// Gets 32 bytes of deterministic pseudo-randomness local to this invocation. The same invocation
+/// and `counter` always produce the same output.
+///
+/// This is not randomness in any security sense: anyone who knows the message can predict it.
+pub fn local_prng(counter: u64) -> SyscallResult<Randomness> {
+    let ret = unsafe { sys::rand::local_prng(counter)? };
+    Ok(Randomness(ret.to_vec()))
+}
@@ -5,10 +5,24 @@ use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::receipt::Receipt;
+use fvm_shared::sys::SendFlags;
 use fvm_shared::MethodNum;
 
 use crate::{sys, SyscallResult, NO_DATA_BLOCK_ID};
 
+/// Options controlling how a send invokes its recipient. The default gives the recipient all the
+/// remaining gas, doesn't ask for a return codec, and sets no flags.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SendOptions {
+    /// The most gas the recipient may use.
+    pub gas_limit: Option<u64>,
+    /// The codec the caller expects the return value in. The recipient can read it with
+    /// [`expected_return_codec`](crate::message::expected_return_codec), but it isn't enforced.
+    pub return_codec: Option<u64>,
+    /// Flags controlling how the recipient is invoked.
+    pub flags: SendFlags,
+}
+
 /// Sends a message to another actor.
 // TODO: Drop the use of receipts here as we don't return the gas used. Alternatively, we _could_
 // return gas used?
@@ -17,6 +31,53 @@ pub fn send(
     method: MethodNum,
     params: RawBytes,
     value: TokenAmount,
+) -> SyscallResult<Receipt> {
+    send_raw(to, params, value, |recipient, params_id, value| unsafe {
+        sys::send::send(
+            recipient.as_ptr(),
+            recipient.len() as u32,
+            method,
+            params_id,
+            value.hi,
+            value.lo,
+        )
+    })
+}
+
+/// Like [`send`], but with [`SendOptions`] controlling how the recipient is invoked. If a
+/// `gas_limit` is specified, the recipient may use at most that much gas.
+pub fn send_with_options(
+    to: &Address,
+    method: MethodNum,
+    params: RawBytes,
+    value: TokenAmount,
+    options: SendOptions,
+) -> SyscallResult<Receipt> {
+    send_raw(to, params, value, |recipient, params_id, value| unsafe {
+        sys::send::send_ext(
+            recipient.as_ptr(),
+            recipient.len() as u32,
+            method,
+            params_id,
+            value.hi,
+            value.lo,
+            options.gas_limit.unwrap_or(u64::MAX),
+            options.return_codec.unwrap_or(0),
+            options.flags.bits(),
+        )
+    })
+}
+
+/// Creates the params block and reads the return value around the given send syscall.
+fn send_raw(
+    to: &Address,
+    params: RawBytes,
+    value: TokenAmount,
+    syscall: impl FnOnce(
+        &[u8],
+        u32,
+        fvm_shared::sys::TokenAmount,
+    ) -> SyscallResult<fvm_shared::sys::out::send::Send>,
 ) -> SyscallResult<Receipt> {
     let recipient = to.to_bytes();
     let value: fvm_shared::sys::TokenAmount = value
@@ -37,14 +98,7 @@ pub fn send(
             return_id,
             return_codec: _, // assume cbor for now.
             return_size,
-        } = sys::send::send(
-            recipient.as_ptr(),
-            recipient.len() as u32,
-            method,
-            params_id,
-            value.hi,
-            value.lo,
-        )?;
+        } = syscall(&recipient, params_id, value)?;
 
         // Process the result.
         let exit_code = ExitCode::new(exit_code);
@@ -1,11 +1,13 @@
 use cid::Cid;
+use fvm_ipld_encoding::de::{Deserialize, Deserializer};
+use fvm_ipld_encoding::ser::{Serialize, Serializer};
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::MAX_CID_LEN;
 
 use crate::error::{ActorDeleteError, NoStateError};
-use crate::sys;
+use crate::{sys, SyscallResult};
 
 /// Get the IPLD root CID. Fails if the actor doesn't have state (before the first call to
 /// `set_root` and after actor deletion).
@@ -21,6 +23,83 @@ pub fn root() -> Result<Cid, NoStateError> {
     }
 }
 
+/// Get a commitment over the actor's state. This is simply the state root CID, which commits to
+/// the entire state (but not the actor's balance or nonce).
+///
+/// Fails if the actor doesn't have state.
+pub fn state_commitment() -> Result<Cid, NoStateError> {
+    root()
+}
+
+/// Get a commitment over the actor's state root, balance, and nonce, suitable for bridge proofs.
+/// Unlike [`state_commitment`], this changes whenever the actor's balance changes, even if the
+/// state root doesn't.
+///
+/// Fails if the actor has been deleted.
+pub fn state_commitment_with_balance() -> Result<[u8; 32], NoStateError> {
+    let mut ret = [0u8; 32];
+    unsafe {
+        sys::sself::state_commitment_with_balance(ret.as_mut_ptr()).map_err(|e| match e {
+            ErrorNumber::IllegalOperation => NoStateError,
+            e => panic!(
+                "unexpected error from `self::state_commitment_with_balance` syscall: {}",
+                e
+            ),
+        })?;
+    }
+    Ok(ret)
+}
+
+/// Actor state of type `S`, preceded by a counter area for [`CountedState::bump_counter`].
+///
+/// Counters are an opt-in convention: the FVM doesn't reserve any part of an actor's state.
+/// Actors that want counters store their state root as a `CountedState`, which is encoded as a
+/// DAG-CBOR array of the counters followed by the state.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CountedState<S> {
+    /// The counters, by slot. Missing counters are zero.
+    pub counters: Vec<u64>,
+    /// The rest of the actor's state.
+    pub state: S,
+}
+
+impl<S> CountedState<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            counters: Vec::new(),
+            state,
+        }
+    }
+
+    /// Returns the value of the counter in `slot`.
+    pub fn counter(&self, slot: u64) -> u64 {
+        self.counters
+            .get(slot as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Increments the counter in `slot` of the actor's state, which must be a `CountedState<S>`,
+    /// and returns the new value. This sets a new state root, so any loaded state must be reloaded
+    /// afterwards.
+    pub fn bump_counter(slot: u64) -> SyscallResult<u64> {
+        unsafe { sys::sself::bump_counter(slot) }
+    }
+}
+
+impl<S: Serialize> Serialize for CountedState<S> {
+    fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+        (&self.counters, &self.state).serialize(serializer)
+    }
+}
+
+impl<'de, S: Deserialize<'de>> Deserialize<'de> for CountedState<S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (counters, state) = Deserialize::deserialize(deserializer)?;
+        Ok(Self { counters, state })
+    }
+}
+
 /// Set the actor's state-tree root.
 ///
 /// Fails if:
@@ -49,6 +49,41 @@ super::fvm_syscalls! {
         obuf_len: u32,
     ) -> Result<u32>;
 
+    /// Determines whether the actor at an address is an account actor.
+    ///
+    /// # Arguments
+    ///
+    /// `addr_off` and `addr_len` specify the location and length of the actor's address.
+    ///
+    /// # Returns
+    ///
+    /// 0 if the actor is an account actor, -1 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                    |
+    /// |---------------------|-----------------------------------------------------------|
+    /// | [`NotFound`]        | if the target actor does not exist                        |
+    /// | [`IllegalArgument`] | if the passed address buffer isn't valid, in memory, etc. |
+    pub fn is_account(addr_off: *const u8, addr_len: u32) -> Result<i32>;
+
+    /// Looks up the nonce (sequence number) of the actor at an address.
+    ///
+    /// # Arguments
+    ///
+    /// `addr_off` and `addr_len` specify the location and length of the actor's address.
+    ///
+    /// # Returns
+    ///
+    /// The actor's nonce, or 0 if the actor doesn't exist or isn't an account actor.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                    |
+    /// |---------------------|-----------------------------------------------------------|
+    /// | [`IllegalArgument`] | if the passed address buffer isn't valid, in memory, etc. |
+    pub fn nonce_of(addr_off: *const u8, addr_len: u32) -> Result<u64>;
+
     /// Returns the builtin-actor type ID for the given CodeCID, or 0 if the CodeCID is not a
     /// builtin actor.
     ///
@@ -36,6 +36,33 @@ super::fvm_syscalls! {
         plaintext_len: u32,
     ) -> Result<i32>;
 
+    /// Computes the key address of a public key: an f1 address for a secp256k1 key, or an f3
+    /// address for a BLS key. The address is written to the passed output buffer.
+    ///
+    /// Returns the length of the address written to the output buffer.
+    ///
+    /// # Arguments
+    ///
+    /// - `sig_type` is the type of the key (secp256k1 or BLS).
+    /// - `key_off` and `key_len` specify the location and length of the public key. Secp256k1
+    ///   keys must be uncompressed (65 bytes), and BLS keys 48 bytes.
+    /// - `obuf_off` and `obuf_len` specify the location and length of the output buffer. 49 bytes
+    ///   is guaranteed to be enough.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                     |
+    /// |---------------------|------------------------------------------------------------|
+    /// | [`IllegalArgument`] | unknown key type, invalid key length, or invalid buffers   |
+    /// | [`BufferTooSmall`]  | if the output buffer isn't large enough to fit the address |
+    pub fn pubkey_to_address(
+        sig_type: u32,
+        key_off: *const u8,
+        key_len: u32,
+        obuf_off: *mut u8,
+        obuf_len: u32,
+    ) -> Result<u32>;
+
     /// Hashes input data using the specified hash function. The digest is written to the passed
     /// digest buffer and truncated to `digest_len`.
     ///
@@ -43,6 +70,8 @@ super::fvm_syscalls! {
     ///
     /// # Arguments
     ///
+    /// - `hash_code` is the multicodec code of the hash function, one of
+    ///   [`SupportedHashes`](fvm_shared::crypto::hash::SupportedHashes).
     /// - `data_off` and `data_len` specify location and length of the data to be hashed.
     /// - `digest_off` and `digest_len` specify the location and length of the output digest buffer.
     ///
@@ -53,6 +82,7 @@ super::fvm_syscalls! {
     /// | Error               | Reason                                          |
     /// |---------------------|-------------------------------------------------|
     /// | [`IllegalArgument`] | the input buffer does not point to valid memory |
+    /// | [`IllegalArgument`] | the hash function isn't supported               |
     pub fn hash(
         hash_code: u64,
         data_off: *const u8,
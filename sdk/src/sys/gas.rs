@@ -22,7 +22,19 @@ super::fvm_syscalls! {
     /// | [`IllegalArgument`] | invalid name buffer. |
     pub fn charge(name_off: *const u8, name_len: u32, amount: u64) -> Result<()>;
 
-    // Returns the amount of gas remaining.
-    // TODO not implemented.
-    // pub fn remaining() -> Result<u64>;
+    /// Returns the gas the current invocation was granted, in milligas: the gas available when it
+    /// started, after applying the gas limit of the send that invoked it.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn limit() -> Result<u64>;
+
+    /// Returns the gas available to the current message, in milligas. This accounts for all the
+    /// gas used so far, including by the caller's own execution up to this call.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn available() -> Result<u64>;
 }
@@ -26,10 +26,11 @@ super::fvm_syscalls! {
     ///
     /// # Errors
     ///
-    /// | Error               | Reason                                      |
-    /// |---------------------|---------------------------------------------|
-    /// | [`NotFound`]        | the target block isn't in the reachable set |
-    /// | [`IllegalArgument`] | there's something wrong with the CID        |
+    /// | Error               | Reason                                                |
+    /// |---------------------|-------------------------------------------------------|
+    /// | [`NotFound`]        | the target block isn't in the reachable set           |
+    /// | [`IllegalArgument`] | there's something wrong with the CID                  |
+    /// | [`IllegalArgument`] | the block isn't canonical DAG-CBOR (strict mode only) |
     pub fn block_open(cid: *const u8) -> Result<IpldOpen>;
 
     /// Creates a new block, returning the block's ID. The block's children must be in the reachable
@@ -51,6 +52,23 @@ super::fvm_syscalls! {
     /// | [`IllegalArgument`] | the block isn't in memory, etc.                         |
     pub fn block_create(codec: u64, data: *const u8, len: u32) -> Result<u32>;
 
+    /// Returns the size of the block [`block_create`] would create from the given data, without
+    /// creating it. That's the length of the data, once the codec has been checked. Only a flat
+    /// compute charge applies, and the data isn't copied.
+    ///
+    /// # Arguments
+    ///
+    /// - `codec` is the codec of the block.
+    /// - `data` and `len` specify the location and length of the block data.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                           |
+    /// |---------------------|----------------------------------|
+    /// | [`IllegalCodec`]    | the passed codec isn't supported |
+    /// | [`IllegalArgument`] | the data isn't in memory         |
+    pub fn block_measure(codec: u64, data: *const u8, len: u32) -> Result<u32>;
+
     /// Reads the block identified by `id` into `obuf`, starting at `offset`, reading _at most_
     /// `max_len` bytes.
     ///
@@ -89,6 +107,55 @@ super::fvm_syscalls! {
     /// | [`InvalidHandle`] | if the handle isn't known. |
     pub fn block_stat(id: u32) -> Result<IpldStat>;
 
+    /// Finds the value at a path within a DAG-CBOR block, and creates a new block containing just
+    /// that value, returning the new block's ID. The whole block isn't copied into actor memory.
+    ///
+    /// Gas is charged for the bytes of the block traversed to find the value, and for creating
+    /// the new block.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` is ID of the block to read the value from.
+    /// - `path_off` and `path_len` specify the location and length of the path: a cbor-encoded
+    ///   list of [`PathSegment`][fvm_ipld_encoding::PathSegment]s (array indices or map keys).
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                               |
+    /// |---------------------|------------------------------------------------------|
+    /// | [`InvalidHandle`]   | if the handle isn't known.                           |
+    /// | [`IllegalCodec`]    | the block isn't DAG-CBOR                             |
+    /// | [`NotFound`]        | there's no value at the path                         |
+    /// | [`Serialization`]   | the block isn't valid DAG-CBOR                       |
+    /// | [`IllegalArgument`] | if the passed path isn't valid, in memory, etc.      |
+    pub fn block_read_field(id: u32, path_off: *const u8, path_len: u32) -> Result<u32>;
+
+    /// Checks whether the given data matches a CID, by hashing it with the CID's hash function and
+    /// comparing the digests. The CID's codec isn't checked, and CIDs with truncated digests never
+    /// match. Gas is charged in proportion to the length of the data.
+    ///
+    /// # Arguments
+    ///
+    /// - `cid_off` and `cid_len` specify the location and length of the CID.
+    /// - `data_off` and `data_len` specify the location and length of the data.
+    ///
+    /// # Returns
+    ///
+    /// 0 if the data matches the CID, -1 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                              |
+    /// |---------------------|-----------------------------------------------------|
+    /// | [`IllegalCid`]      | the CID's hash function isn't supported             |
+    /// | [`IllegalArgument`] | the CID is invalid, or the buffers aren't in memory |
+    pub fn verify_cid(
+        cid_off: *const u8,
+        cid_len: u32,
+        data_off: *const u8,
+        data_len: u32,
+    ) -> Result<i32>;
+
     /// Computes the given block's CID, writing the resulting CID into `cid`.
     ///
     /// The returned CID is added to the reachable set.
@@ -112,6 +179,7 @@ super::fvm_syscalls! {
     /// | [`InvalidHandle`]   | if the handle isn't known.                        |
     /// | [`IllegalCid`]      | hash code and/or hash length aren't supported.    |
     /// | [`IllegalArgument`] | if the passed buffer isn't valid, in memory, etc. |
+    /// | [`NotFound`]        | the block links to a missing block (strict mode). |
     pub fn block_link(
         id: u32,
         hash_fun: u64,
@@ -1,5 +1,12 @@
 //! Syscalls for network metadata.
 
+#[doc(inline)]
+pub use fvm_shared::sys::out::network::*;
+
+// for documentation links
+#[cfg(doc)]
+use crate::sys::ErrorNumber::*;
+
 super::fvm_syscalls! {
     module = "network";
 
@@ -16,4 +23,13 @@ super::fvm_syscalls! {
     ///
     /// None
     pub fn total_fil_circ_supply() -> Result<super::TokenAmount>;
+
+    /// Gets the components of the circulating supply (mined, vested, burnt, and locked funds).
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                                  |
+    /// |----------------------|-----------------------------------------|
+    /// | [`IllegalOperation`] | the client didn't provide the breakdown |
+    pub fn circ_supply_detail() -> Result<CircSupplyDetail>;
 }
@@ -54,4 +54,22 @@ super::fvm_syscalls! {
         entropy_off: *const u8,
         entropy_len: u32,
     ) -> Result<[u8; RANDOMNESS_LENGTH]>;
+
+    /// Gets 32 bytes of deterministic pseudo-randomness local to the current invocation.
+    ///
+    /// The output is derived from the message being executed, the receiving actor, the method
+    /// number, and `counter`. The same invocation and counter always produce the same output, so
+    /// actors can draw a stable sequence by incrementing `counter`.
+    ///
+    /// **This randomness is entirely predictable and must not be used for anything security
+    /// sensitive.** It's intended for things like internal shuffling.
+    ///
+    /// # Arguments
+    ///
+    /// - `counter` selects the draw within the current invocation.
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn local_prng(counter: u64) -> Result<[u8; RANDOMNESS_LENGTH]>;
 }
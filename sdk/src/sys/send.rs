@@ -37,7 +37,9 @@ super::fvm_syscalls! {
     /// | [`InsufficientFunds`] | tried to send more FIL than available.               |
     /// | [`InvalidHandle`]     | parameters block not found.                          |
     /// | [`LimitExceeded`]     | recursion limit reached.                             |
-    /// | [`IllegalArgument`]   | invalid recipient address buffer.                    |
+    /// | [`IllegalArgument`]   | invalid recipient address buffer, or the BLS "zero"  |
+    /// |                       | address, which can never be created.                 |
+    /// | [`IllegalOperation`]  | tried to transfer value in read-only mode.           |
     pub fn send(
         recipient_off: *const u8,
         recipient_len: u32,
@@ -46,4 +48,40 @@ super::fvm_syscalls! {
         value_hi: u64,
         value_lo: u64,
     ) -> Result<Send>;
+
+    /// Like [`send`], with options controlling how the recipient is invoked.
+    ///
+    /// # Arguments
+    ///
+    /// The arguments of [`send`], followed by:
+    ///
+    /// - `gas_limit` is the maximum gas the recipient may use, or `u64::MAX` to give it all the
+    ///   remaining gas.
+    /// - `return_codec` is the codec the caller expects the return value in, or 0 if it doesn't
+    ///   care. The recipient can read it with [`expected_return_codec`](super::vm::expected_return_codec).
+    /// - `flags` is a [`SendFlags`](fvm_shared::sys::SendFlags) bitset controlling how the
+    ///   recipient is invoked (e.g., read-only or value-only). All other bits are reserved.
+    ///
+    /// If the recipient runs out of the gas given to it, the send aborts with `SYS_OUT_OF_GAS`
+    /// and the caller continues with the rest of its gas.
+    ///
+    /// # Errors
+    ///
+    /// As for [`send`], and:
+    ///
+    /// | Error                 | Reason                                               |
+    /// |-----------------------|------------------------------------------------------|
+    /// | [`IllegalArgument`]   | invalid flags.                                       |
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_ext(
+        recipient_off: *const u8,
+        recipient_len: u32,
+        method: u64,
+        params: u32,
+        value_hi: u64,
+        value_lo: u64,
+        gas_limit: u64,
+        return_codec: u64,
+        flags: u64,
+    ) -> Result<Send>;
 }
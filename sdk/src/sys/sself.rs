@@ -62,4 +62,43 @@ super::fvm_syscalls! {
     /// | [`Forbidden`]       | beneficiary is not allowed (usually means beneficiary is self) |
     /// | [`IllegalArgument`] | if the passed address buffer isn't valid, in memory, etc.      |
     pub fn self_destruct(addr_off: *const u8, addr_len: u32) -> Result<()>;
+
+    /// Computes a Blake2b-256 commitment over the calling actor's state root, balance, and
+    /// sequence number (the DAG-CBOR encoding of the tuple `(root, balance, sequence)`).
+    ///
+    /// # Arguments
+    ///
+    /// - `obuf_off` is the location in memory where the 32 byte commitment will be written.
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                                                |
+    /// |----------------------|-------------------------------------------------------|
+    /// | [`IllegalOperation`] | actor has been deleted                                |
+    /// | [`IllegalArgument`]  | if the passed buffer isn't valid, in memory, etc.     |
+    pub fn state_commitment_with_balance(obuf_off: *mut u8) -> Result<()>;
+
+    /// Increments the counter in `slot` of the calling actor's counter area, returning the new
+    /// value. The counter area is the first element of the actor's state, which must be a DAG-CBOR
+    /// array whose first element is an array of `u64` counters. Missing counters start at zero.
+    ///
+    /// Counters are an opt-in convention, which the SDK's `CountedState` encodes: the FVM doesn't
+    /// reserve any part of actor state. Only actors whose state follows it should call this.
+    ///
+    /// This sets a new state root, so the actor must reload its state afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// - `slot` is the index of the counter, which must be less than
+    ///   [`MAX_COUNTER_SLOTS`](fvm_shared::MAX_COUNTER_SLOTS).
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                                                  |
+    /// |----------------------|---------------------------------------------------------|
+    /// | [`IllegalOperation`] | actor has been deleted, or its state has no counter area |
+    /// | [`IllegalArgument`]  | slot is out of range                                    |
+    /// | [`LimitExceeded`]    | the counter would overflow                              |
+    /// | [`Serialization`]    | the actor's state isn't valid DAG-CBOR                  |
+    pub fn bump_counter(slot: u64) -> Result<u64>;
 }
@@ -3,6 +3,10 @@
 #[doc(inline)]
 pub use fvm_shared::sys::out::vm::InvocationContext;
 
+// for documentation links
+#[cfg(doc)]
+use crate::sys::ErrorNumber::*;
+
 super::fvm_syscalls! {
     module = "vm";
 
@@ -25,6 +29,92 @@ super::fvm_syscalls! {
     pub fn abort(code: u32, message_off: *const u8, message_len: u32) -> !;
 
 
+    /// Copies `len` bytes from `src_off` to `dst_off` within the actor's memory. The source and
+    /// destination regions may overlap. Charges gas proportional to `len`.
+    ///
+    /// # Arguments
+    ///
+    /// - `dst_off` is the offset (in wasm memory) of the destination region.
+    /// - `src_off` is the offset (in wasm memory) of the source region.
+    /// - `len` is the number of bytes to copy.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                              |
+    /// |---------------------|-----------------------------------------------------|
+    /// | [`IllegalArgument`] | the source or destination region is out of bounds.  |
+    pub fn memcopy(dst_off: *mut u8, src_off: *const u8, len: u32) -> Result<()>;
+
+    /// Zeroes `len` bytes at `off` in the actor's memory. The host always performs the write, so
+    /// it can be used to clear sensitive data. Charges gas proportional to `len`.
+    ///
+    /// # Arguments
+    ///
+    /// - `off` is the offset (in wasm memory) of the region to zero.
+    /// - `len` is the number of bytes to zero.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                       |
+    /// |---------------------|------------------------------|
+    /// | [`IllegalArgument`] | the region is out of bounds. |
+    pub fn memzero(off: *mut u8, len: u32) -> Result<()>;
+
+    /// Copies the raw parameters of this invocation into the output buffer, without opening a
+    /// block. This is a fast path for small parameters.
+    ///
+    /// # Arguments
+    ///
+    /// - `obuf` is the output buffer (in wasm memory) where the FVM will write the parameters.
+    /// - `obuf_len` is the length of the output buffer. At most this many bytes are copied.
+    ///
+    /// # Returns
+    ///
+    /// The total size of the parameters (zero if there are none), which may exceed `obuf_len`.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                            |
+    /// |---------------------|---------------------------------------------------|
+    /// | [`IllegalArgument`] | if the passed buffer isn't valid, in memory, etc. |
+    pub fn read_params(obuf: *mut u8, obuf_len: u32) -> Result<u32>;
+
+    /// Returns the codec of the parameters of this invocation, without opening a block.
+    ///
+    /// # Returns
+    ///
+    /// The IPLD codec of the parameters, or zero if there are none.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn params_codec() -> Result<u64>;
+
+    /// Returns the value transferred with this invocation, without reading the full invocation
+    /// context.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn value_received() -> Result<super::TokenAmount>;
+
+    /// Returns the nonce of the top-level message that started this call stack, which is the same
+    /// for every invocation in the stack.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn origin_nonce() -> Result<u64>;
+
+    /// Returns the codec the caller expects the return value in, as passed to
+    /// [`send_ext`](super::send::send_ext). Defaults to `DAG_CBOR` if the caller didn't specify
+    /// one, or sent with [`send`](super::send::send).
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn expected_return_codec() -> Result<u64>;
+
     /// Returns the details about this invocation.
     ///
     /// # Errors
@@ -1,3 +1,4 @@
+use std::ops::Range;
 use std::ptr;
 
 use fvm_shared::sys::out::vm::InvocationContext;
@@ -27,3 +28,33 @@ pub fn abort(code: u32, message: Option<&str>) -> ! {
         sys::vm::abort(code, message, message_len as u32);
     }
 }
+
+/// Copies `src` to `dest` within `buf`, using the host to perform the copy. This behaves like
+/// [`slice::copy_within`] (the regions may overlap), but charges gas proportional to the length of
+/// the copied region instead of per executed instruction.
+///
+/// # Panics
+///
+/// Panics if either the source or destination range is out of bounds.
+pub fn copy_within(buf: &mut [u8], src: Range<usize>, dest: usize) {
+    assert!(
+        src.start <= src.end && src.end <= buf.len(),
+        "source range out of bounds"
+    );
+    let len = src.end - src.start;
+    assert!(dest <= buf.len() - len, "destination out of bounds");
+    unsafe {
+        let base = buf.as_mut_ptr();
+        sys::vm::memcopy(base.add(dest), base.add(src.start), len as u32)
+            .expect("bounds checked above");
+    }
+}
+
+/// Zeroes `buf`, using the host to perform the write. Unlike zeroing the buffer in wasm, this
+/// can't be optimized away, so it's suitable for clearing sensitive data such as key material.
+/// Charges gas proportional to the length of the buffer.
+pub fn zero(buf: &mut [u8]) {
+    unsafe {
+        sys::vm::memzero(buf.as_mut_ptr(), buf.len() as u32).expect("buffer is in bounds");
+    }
+}
@@ -0,0 +1,12 @@
+use num_derive::FromPrimitive;
+
+/// Hash functions supported by the `crypto::hash` syscall, identified by their multicodec code.
+/// All of them produce 256 bit digests.
+#[derive(Clone, Debug, PartialEq, FromPrimitive, Copy, Eq, Hash)]
+#[repr(u64)]
+pub enum SupportedHashes {
+    Sha2_256 = 0x12,
+    Keccak256 = 0x1b,
+    Blake3_256 = 0x1e,
+    Blake2b256 = 0xb220,
+}
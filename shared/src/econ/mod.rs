@@ -2,6 +2,34 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use num_bigint::BigInt;
+use serde_tuple::*;
+
+use crate::bigint::bigint_ser;
 
 /// Token type to be used within the VM.
 pub type TokenAmount = BigInt;
+
+/// The components of the circulating supply.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct CircSupplyDetail {
+    /// Rewards emitted by the reward actor.
+    #[serde(with = "bigint_ser")]
+    pub mined: TokenAmount,
+    /// Funds vested from lock-ups in the genesis state.
+    #[serde(with = "bigint_ser")]
+    pub vested: TokenAmount,
+    /// Funds burnt.
+    #[serde(with = "bigint_ser")]
+    pub burnt: TokenAmount,
+    /// Pledge and deal collateral locked by the storage power and market actors.
+    #[serde(with = "bigint_ser")]
+    pub locked: TokenAmount,
+}
+
+impl CircSupplyDetail {
+    /// Returns the circulating supply described by these components: the funds mined and vested,
+    /// less the funds burnt and locked.
+    pub fn total(&self) -> TokenAmount {
+        &self.mined + &self.vested - &self.burnt - &self.locked
+    }
+}
@@ -49,6 +49,9 @@ pub const IDENTITY_HASH: u64 = 0x0;
 /// The maximum supported CID size.
 pub const MAX_CID_LEN: usize = 100;
 
+/// The number of counter slots an actor may reserve in its state.
+pub const MAX_COUNTER_SLOTS: u64 = 256;
+
 /// Identifier for Actors, includes builtin and initialized actors
 pub type ActorID = u64;
 
@@ -29,6 +29,27 @@ pub struct Message {
 
 impl Cbor for Message {}
 
+/// Generates structurally valid messages (non-negative amounts, a positive gas limit within the
+/// block gas limit, and small nonces), so that fuzzed messages reach execution rather than failing
+/// validation.
+#[cfg(feature = "arb")]
+impl<'a> arbitrary::Arbitrary<'a> for Message {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Message {
+            version: 0,
+            from: u.arbitrary()?,
+            to: u.arbitrary()?,
+            sequence: u.int_in_range(0..=3)?,
+            value: TokenAmount::from(u.arbitrary::<u64>()?),
+            method_num: u.arbitrary()?,
+            params: RawBytes::new(u.arbitrary()?),
+            gas_limit: u.int_in_range(1..=crate::BLOCK_GAS_LIMIT)?,
+            gas_fee_cap: TokenAmount::from(u.arbitrary::<u64>()?),
+            gas_premium: TokenAmount::from(u.arbitrary::<u64>()?),
+        })
+    }
+}
+
 impl Message {
     /// Helper function to convert the message into signing bytes.
     /// This function returns the message `Cid` bytes.
@@ -6,6 +6,23 @@ pub mod out;
 pub type BlockId = u32;
 pub type Codec = u64;
 
+bitflags::bitflags! {
+    /// Flags controlling the behavior of a send, passed to the `send_ext` syscall as a `u64`. Sends
+    /// made with the original `send` syscall have no flags set.
+    ///
+    /// All bits not defined here are reserved and must be zero; the FVM rejects sends with unknown
+    /// bits set.
+    #[derive(Default)]
+    pub struct SendFlags: u64 {
+        /// Run the receiver (and everything it calls) in read-only mode: it may not modify its
+        /// state, transfer value, create actors, or self-destruct.
+        const READ_ONLY = 1 << 0;
+        /// Only transfer value: the receiver is not invoked and the parameters must be empty, as
+        /// if the message were sent to `METHOD_SEND`.
+        const VALUE_ONLY = 1 << 1;
+    }
+}
+
 /// The token amount type used in syscalls. It can represent any token amount (in atto-FIL) from 0
 /// to `2^128-1` attoFIL. Or 0 to about 340 exaFIL.
 ///
@@ -74,6 +91,7 @@ assert_syscall_safe! {
     out::ipld::IpldStat,
     out::send::Send,
     out::crypto::VerifyConsensusFault,
+    out::network::CircSupplyDetail,
     out::vm::InvocationContext,
 }
 
@@ -56,6 +56,19 @@ pub mod crypto {
     }
 }
 
+pub mod network {
+    use crate::sys::TokenAmount;
+
+    #[derive(Debug, Copy, Clone)]
+    #[repr(packed, C)]
+    pub struct CircSupplyDetail {
+        pub mined: TokenAmount,
+        pub vested: TokenAmount,
+        pub burnt: TokenAmount,
+        pub locked: TokenAmount,
+    }
+}
+
 pub mod vm {
     use crate::clock::ChainEpoch;
     use crate::sys::TokenAmount;
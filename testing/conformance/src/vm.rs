@@ -11,13 +11,14 @@ use fvm::state_tree::{ActorState, StateTree};
 use fvm::DefaultKernel;
 use fvm_ipld_blockstore::MemoryBlockstore;
 use fvm_ipld_car::load_car_unchecked;
+use fvm_ipld_encoding::PathSegment;
 use fvm_shared::actor::builtin::Manifest;
 use fvm_shared::address::Address;
 use fvm_shared::bigint::BigInt;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::consensus::ConsensusFault;
 use fvm_shared::crypto::signature::SignatureType;
-use fvm_shared::econ::TokenAmount;
+use fvm_shared::econ::{CircSupplyDetail, TokenAmount};
 use fvm_shared::piece::PieceInfo;
 use fvm_shared::randomness::RANDOMNESS_LENGTH;
 use fvm_shared::sector::{
@@ -156,6 +157,26 @@ where
         self.machine.transfer(from, to, value)
     }
 
+    fn discard_buffered_writes(&mut self) -> Result<()> {
+        self.machine.discard_buffered_writes()
+    }
+
+    fn pending_writes(&self) -> Vec<(Cid, usize)> {
+        self.machine.pending_writes()
+    }
+
+    fn record_reads(&mut self) -> Result<Cid> {
+        self.machine.record_reads()
+    }
+
+    fn recording_reads(&self) -> bool {
+        self.machine.recording_reads()
+    }
+
+    fn take_reads(&mut self) -> BTreeMap<Cid, Vec<u8>> {
+        self.machine.take_reads()
+    }
+
     fn into_store(self) -> Self::Blockstore {
         self.machine.into_store()
     }
@@ -192,11 +213,12 @@ where
         method: MethodNum,
         params: Option<Block>,
         value: &TokenAmount,
+        options: SendOptions,
     ) -> Result<InvocationResult> {
         // K is the kernel specified by the non intercepted kernel.
         // We wrap that here.
         self.0
-            .send::<TestKernel<K>>(from, to, method, params, value)
+            .send::<TestKernel<K>>(from, to, method, params, value, options)
     }
 
     fn with_transaction(
@@ -278,6 +300,26 @@ where
     fn invocation_count(&self) -> u64 {
         self.0.invocation_count()
     }
+
+    fn expected_return_codec(&self) -> u64 {
+        self.0.expected_return_codec()
+    }
+
+    fn read_only(&self) -> bool {
+        self.0.read_only()
+    }
+
+    fn set_price_list(&mut self, price_list: &'static PriceList) {
+        self.0.set_price_list(price_list)
+    }
+
+    fn record_artifact(&mut self, size: u64) -> bool {
+        self.0.record_artifact(size)
+    }
+
+    fn record_state_write(&mut self, id: ActorID, state: Cid) {
+        self.0.record_state_write(id, state)
+    }
 }
 
 /// A kernel for intercepting syscalls.
@@ -333,7 +375,7 @@ where
     C: CallManager<Machine = TestMachine<M>>,
     K: Kernel<CallManager = TestCallManager<C>>,
 {
-    fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>> {
+    fn resolve_address(&mut self, address: &Address) -> Result<Option<ActorID>> {
         self.0.resolve_address(address)
     }
 
@@ -341,6 +383,14 @@ where
         self.0.get_actor_code_cid(id)
     }
 
+    fn is_account(&mut self, address: &Address) -> Result<bool> {
+        self.0.is_account(address)
+    }
+
+    fn nonce_of(&mut self, address: &Address) -> Result<u64> {
+        self.0.nonce_of(address)
+    }
+
     fn new_actor_address(&mut self) -> Result<Address> {
         self.0.new_actor_address()
     }
@@ -377,6 +427,10 @@ where
         self.0.block_create(codec, data)
     }
 
+    fn block_measure(&mut self, codec: u64, data: &[u8]) -> Result<u32> {
+        self.0.block_measure(codec, data)
+    }
+
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
         self.0.block_link(id, hash_fun, hash_len)
     }
@@ -388,6 +442,14 @@ where
     fn block_stat(&mut self, id: BlockId) -> Result<BlockStat> {
         self.0.block_stat(id)
     }
+
+    fn block_read_field(&mut self, id: BlockId, path: &[PathSegment]) -> Result<BlockId> {
+        self.0.block_read_field(id, path)
+    }
+
+    fn verify_cid(&mut self, cid: &Cid, data: &[u8]) -> Result<bool> {
+        self.0.verify_cid(cid, data)
+    }
 }
 
 impl<M, C, K> CircSupplyOps for TestKernel<K>
@@ -400,6 +462,14 @@ where
     fn total_fil_circ_supply(&self) -> Result<TokenAmount> {
         Ok(self.1.circ_supply.clone())
     }
+
+    // Not forwarded. The vectors only specify the total circulating supply, so the inner kernel's
+    // breakdown wouldn't add up to it.
+    fn circ_supply_detail(&self) -> Result<CircSupplyDetail> {
+        Err(fvm::syscall_error!(IllegalOperation;
+            "circulating supply breakdown not available in conformance tests")
+        .into())
+    }
 }
 
 impl<M, C, K> CryptoOps for TestKernel<K>
@@ -413,6 +483,11 @@ where
         self.0.hash(code, data)
     }
 
+    // forwarded
+    fn pubkey_to_address(&mut self, sig_type: SignatureType, pubkey: &[u8]) -> Result<Address> {
+        self.0.pubkey_to_address(sig_type, pubkey)
+    }
+
     // forwarded
     fn compute_unsealed_sector_cid(
         &mut self,
@@ -494,7 +569,15 @@ where
         self.0.debug_enabled()
     }
 
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+    fn max_artifact_name_len(&self) -> u32 {
+        self.0.max_artifact_name_len()
+    }
+
+    fn max_artifact_size(&self) -> u32 {
+        self.0.max_artifact_size()
+    }
+
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<()> {
         self.0.store_artifact(name, data)
     }
 }
@@ -509,6 +592,10 @@ where
         self.0.gas_used()
     }
 
+    fn gas_limit(&self) -> Gas {
+        self.0.gas_limit()
+    }
+
     fn charge_gas(&mut self, name: &str, compute: Gas) -> Result<()> {
         self.0.charge_gas(name, compute)
     }
@@ -543,6 +630,22 @@ where
     fn msg_value_received(&self) -> TokenAmount {
         self.0.msg_value_received()
     }
+
+    fn msg_read_params(&mut self, buf: &mut [u8]) -> Result<u32> {
+        self.0.msg_read_params(buf)
+    }
+
+    fn msg_params_codec(&self) -> u64 {
+        self.0.msg_params_codec()
+    }
+
+    fn msg_origin_nonce(&self) -> u64 {
+        self.0.msg_origin_nonce()
+    }
+
+    fn msg_expected_return_codec(&self) -> u64 {
+        self.0.msg_expected_return_codec()
+    }
 }
 
 impl<M, C, K> NetworkOps for TestKernel<K>
@@ -589,6 +692,10 @@ where
         self.0
             .get_randomness_from_beacon(personalization, rand_epoch, entropy)
     }
+
+    fn get_local_randomness(&mut self, counter: u64) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        self.0.get_local_randomness(counter)
+    }
 }
 
 impl<M, C, K> SelfOps for TestKernel<K>
@@ -609,6 +716,14 @@ where
         self.0.current_balance()
     }
 
+    fn state_commitment_with_balance(&mut self) -> Result<[u8; 32]> {
+        self.0.state_commitment_with_balance()
+    }
+
+    fn bump_counter(&mut self, slot: u64) -> Result<u64> {
+        self.0.bump_counter(slot)
+    }
+
     fn self_destruct(&mut self, beneficiary: &Address) -> Result<()> {
         self.0.self_destruct(beneficiary)
     }
@@ -626,7 +741,8 @@ where
         method: u64,
         params: BlockId,
         value: &TokenAmount,
+        options: SendOptions,
     ) -> Result<SendResult> {
-        self.0.send(recipient, method, params, value)
+        self.0.send(recipient, method, params, value, options)
     }
 }